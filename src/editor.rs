@@ -5,17 +5,53 @@ mod textrope;
 mod inputstate;
 mod textbox;
 mod command;
+mod font;
+mod document;
+mod prompt;
+mod message;
+mod glyphatlas;
+mod buffer;
+mod tabbar;
+mod layout;
+mod pane;
+mod gutter;
+mod wrap;
+mod highlight;
+mod config;
+mod theme;
+mod editorconfig;
+mod tabs;
 
-use std::{error::Error, path::PathBuf, sync::{Arc, Mutex}};
+pub use config::Config;
 
-use sdl3::{dialog::{show_open_file_dialog, show_save_file_dialog, DialogFileFilter}, event::{Event, WindowEvent}, get_error, keyboard::Keycode, mouse::MouseButton, pixels::Color, render::{Canvas, TextureCreator}, sys::{keyboard::{SDL_GetModState, SDL_StartTextInput, SDL_StopTextInput}, keycode::SDL_KMOD_CTRL}, ttf::Sdl3TtfContext, video::{Window, WindowContext}, EventPump, VideoSubsystem};
+use std::{error::Error, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+
+use sdl3::{dialog::{show_open_file_dialog, show_save_file_dialog, DialogFileFilter}, event::{Event, WindowEvent}, get_error, keyboard::Keycode, mouse::MouseButton, pixels::{PixelFormat, PixelFormatEnum}, render::{BlendMode, Canvas, Texture, TextureCreator}, sys::{keyboard::{SDL_GetModState, SDL_StartTextInput, SDL_StopTextInput}, keycode::SDL_KMOD_CTRL}, ttf::Sdl3TtfContext, video::{Window, WindowContext}, EventPump, EventSubsystem, VideoSubsystem};
 
 use crate::{editor::{command::CommandState, inputstate::InputState}, vector::Vector2D};
 use crate::editor::textbox::TextBox;
 use crate::editor::command::Command;
+use crate::editor::font::FontSettings;
+use crate::editor::document::{Document, Encoding, LineEnding, SaveOptions};
+use crate::editor::prompt::{AnswerFilter, PendingAction, Prompt, PromptChoice};
+use crate::editor::message::{MessageLevel, Messages};
+use crate::editor::buffer::Buffer;
+use crate::editor::tabbar::TabBar;
+use crate::editor::layout::{Area, Layout, SplitDirection};
+use crate::editor::pane::Pane;
+use crate::editor::theme::Theme;
+use crate::editor::editorconfig::FileSettings;
 
 const DEFAULT_TEXT_POS: Vector2D = Vector2D { x: 0, y: 0};
 const DEFAULT_CONSOLE_POS: Vector2D = Vector2D {x: 0, y: 500};
+const WINDOW_TITLE: &str = "Text Editor";
+/// Pixels left between split panes
+const PANE_GAP: u32 = 2;
+const SWAP_INTERVAL: Duration = Duration::from_secs(4);
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Pushed by the file dialogs so the event loop wakes up to pick up the chosen files
+struct DialogClosed;
 
 #[allow(dead_code)]
 pub enum TextAlignment {
@@ -31,26 +67,148 @@ pub enum Component {
 pub struct State<'a> {
     quit: bool,
     input: InputState,
-    text: TextBox<'a>,
+    buffers: Vec<Buffer<'a>>,
+    /// Index of the buffer shown in the focused pane
+    current: usize,
+    panes: Vec<Pane>,
+    focus: usize,
+    layout: Layout,
+    tabs: TabBar<'a>,
     console: TextBox<'a>,
     active_component: Component,
     command_state: CommandState,
     open_file_paths: Arc<Mutex<Vec<PathBuf>>>,
-    save_file_paths: Arc<Mutex<Vec<PathBuf>>>,
+    /// Paths picked to save under, with the index of the buffer each one is for
+    save_file_paths: Arc<Mutex<Vec<(usize, PathBuf)>>>,
+    title: String,
+    prompt: Option<Prompt>,
+    answer_filter: AnswerFilter,
+    /// Runs once the save dialog started by a prompt has written the buffer at the index
+    after_save: Option<(usize, PendingAction)>,
+    messages: Messages,
+    save_options: SaveOptions,
+    swap_timer: Instant,
+    disk_check_timer: Instant,
+    config: Config,
+    /// When the config file was last written, checked to reload it after it is saved
+    config_modified: Option<SystemTime>,
+    theme: Theme,
+    /// Clears the whole frame and redraws everything, set when the layout changed
+    full_repaint: bool,
 }
 
 impl <'a> State<'a> {
+    fn buffer(&self) -> &Buffer<'a> {
+        &self.buffers[self.current]
+    }
+
+    fn buffer_mut(&mut self) -> &mut Buffer<'a> {
+        &mut self.buffers[self.current]
+    }
+
     fn switch_to_text(&mut self) {
         self.console.deactivate();
-        self.text.activate();
+        self.buffer_mut().text.activate();
         self.active_component = Component::TEXT;
     }
 
     fn switch_to_console(&mut self) {
-        self.text.deactivate();
+        self.buffer_mut().text.deactivate();
         self.console.activate();
         self.active_component = Component::CONSOLE;
     }
+
+    /// Shows the buffer at index in the focused pane, keeping keyboard focus where it was
+    ///
+    /// The old buffer keeps the pane's cursor and scroll position for when it is shown again
+    fn switch_buffer(&mut self, index: usize) {
+        if index >= self.buffers.len() || index == self.current {
+            return;
+        }
+        let active = self.buffer().text.is_active();
+        self.buffer_mut().text.deactivate();
+        let pane = &mut self.panes[self.focus];
+        pane.buffer = index;
+        pane.view = self.buffers[index].text.view();
+        let Area { pos, width, height } = pane.area;
+        self.current = index;
+        if active {
+            self.buffer_mut().text.activate();
+        }
+        self.buffer_mut().text.resize(pos, width as i32, height as i32);
+    }
+
+    /// Moves keyboard focus to another pane, parking the old pane's view
+    fn focus_pane(&mut self, index: usize) {
+        if index >= self.panes.len() || index == self.focus {
+            return;
+        }
+        let active = self.buffer().text.is_active();
+        self.buffer_mut().text.deactivate();
+        let old_pane = &mut self.panes[self.focus];
+        self.buffers[old_pane.buffer].text.swap_view(&mut old_pane.view);
+
+        self.focus = index;
+        let new_pane = &mut self.panes[index];
+        self.buffers[new_pane.buffer].text.swap_view(&mut new_pane.view);
+        self.current = new_pane.buffer;
+        if active {
+            self.buffer_mut().text.activate();
+        }
+        self.buffer_mut().text.request_render();
+    }
+
+    fn pane_at(&self, x: f32, y: f32) -> Option<usize> {
+        self.panes.iter().enumerate().position(|(i, pane)| {
+            if i == self.focus {
+                self.buffer().text.click_in_window(x, y)
+            } else {
+                pane.view.click_in_window(x, y)
+            }
+        })
+    }
+
+    fn next_buffer(&mut self) {
+        self.switch_buffer((self.current + 1) % self.buffers.len());
+    }
+
+    fn previous_buffer(&mut self) {
+        self.switch_buffer((self.current + self.buffers.len() - 1) % self.buffers.len());
+    }
+
+    /// Lists the buffers as "1: main.rs*  [2: notes.txt]", brackets mark the current one
+    fn buffer_list(&self) -> String {
+        self.buffers.iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                let entry = format!("{}: {}", i + 1, buffer.label());
+                if i == self.current { format!("[{entry}]") } else { entry }
+            })
+            .collect::<Vec<String>>()
+            .join("  ")
+    }
+
+    /// Repaints everything in the colors of theme
+    fn set_theme(&mut self, theme: Theme) {
+        for buffer in &mut self.buffers {
+            buffer.text.set_theme(&theme);
+        }
+        self.tabs.set_theme(&theme);
+        self.console.set_theme(&theme);
+        self.console.set_background_color(theme.console);
+        self.theme = theme;
+        self.full_repaint = true;
+    }
+
+    fn report_info(&mut self, text: impl Into<String>) {
+        self.messages.info(text);
+        self.console.request_render();
+    }
+
+    fn report_error(&mut self, text: impl Into<String>) {
+        self.messages.error(text);
+        self.console.request_render();
+    }
 }
 
 pub struct Editor<'a> {
@@ -66,66 +224,152 @@ pub struct EditorContext<'a> {
     video_subsystem: &'a VideoSubsystem,
     ttf_context: &'a Sdl3TtfContext,
     events: &'a mut EventPump,
+    event_subsystem: &'a EventSubsystem,
     canvas: Canvas<Window>,
-    texture_creator: TextureCreator<WindowContext>,
+    texture_creator: &'a TextureCreator<WindowContext>,
+    /// Everything drawn so far, only the damaged parts are redrawn before it is copied to the window
+    frame: Texture<'a>,
+    font_path: PathBuf,
 }
 
 impl <'a> Editor<'a> {
-    pub fn build(video_subsystem: &'a VideoSubsystem, ttf_context: &'a Sdl3TtfContext, events: &'a mut EventPump, window: Window) -> Result<Self, Box<dyn Error>> {
-        unsafe { SDL_StartTextInput(window.raw()); }
+    pub fn build(
+        video_subsystem: &'a VideoSubsystem,
+        ttf_context: &'a Sdl3TtfContext,
+        events: &'a mut EventPump,
+        event_subsystem: &'a EventSubsystem,
+        canvas: Canvas<Window>,
+        texture_creator: &'a TextureCreator<WindowContext>,
+        config: Config,
+    ) -> Result<Self, Box<dyn Error>> {
+        unsafe { SDL_StartTextInput(canvas.window().raw()); }
+        event_subsystem.register_custom_event::<DialogClosed>()?;
 
-        let (window_width, window_height) = window.size();
-        let canvas = window.into_canvas();
-        let texture_creator = canvas.texture_creator();
+        let (window_width, window_height) = canvas.window().size();
+        let font_path = FontSettings::new(&config).resolve()?;
+        let (theme, theme_error) = match Theme::load(&config.theme) {
+            Ok(theme) => (theme, None),
+            Err(err) => (Theme::default(), Some(format!("Could not load theme: {err}"))),
+        };
+        let mut text = TextBox::build(
+            DEFAULT_TEXT_POS,
+            window_width,
+            window_height - 100,
+            &config,
+            &theme,
+            &font_path,
+            video_subsystem,
+            ttf_context,
+            texture_creator,
+        )?;
+        text.set_line_numbers(config.line_numbers);
+        text.set_wrap(config.wrap);
+        let first_pane = Pane::new(0, text.view(), Area::default());
+        let mut tabs = TabBar::new(TextBox::build(
+            DEFAULT_TEXT_POS,
+            window_width,
+            window_height,
+            &config,
+            &theme,
+            &font_path,
+            video_subsystem,
+            ttf_context,
+            texture_creator,
+        )?);
+        tabs.set_theme(&theme);
+        let mut console = TextBox::build(
+            DEFAULT_CONSOLE_POS,
+            window_width,
+            window_height,
+            &config,
+            &theme,
+            &font_path,
+            video_subsystem,
+            ttf_context,
+            texture_creator,
+        )?;
+        console.set_background_color(theme.console);
+        let frame = create_frame(texture_creator, &canvas)?;
 
         let mut new_editor = Self {
             context: EditorContext {
                 video_subsystem,
                 ttf_context,
                 events,
+                event_subsystem,
                 canvas,
                 texture_creator,
+                frame,
+                font_path: font_path.clone(),
             },
             state: State {
                 quit: false,
-                text: TextBox::build(
-                    DEFAULT_TEXT_POS,
-                    window_width,
-                    window_height - 100,
-                    None,
-                    video_subsystem,
-                    ttf_context
-                )?,
-                console: TextBox::build(
-                    DEFAULT_CONSOLE_POS,
-                    window_width,
-                    window_height,
-                    Some(Color::RGB(20, 20, 60)),
-                    video_subsystem,
-                    ttf_context
-                )?,
+                buffers: vec![Buffer::new(Document::new(), text)],
+                current: 0,
+                panes: vec![first_pane],
+                focus: 0,
+                layout: Layout::new(0),
+                tabs,
+                console,
                 active_component: Component::TEXT,
                 input: InputState::default(),
                 command_state: CommandState::new(),
                 open_file_paths: Arc::new(Mutex::new(Vec::new())),
                 save_file_paths: Arc::new(Mutex::new(Vec::new())),
+                title: String::from(WINDOW_TITLE),
+                prompt: None,
+                answer_filter: AnswerFilter::default(),
+                after_save: None,
+                messages: Messages::new(),
+                save_options: SaveOptions { backup: config.backup },
+                swap_timer: Instant::now(),
+                disk_check_timer: Instant::now(),
+                config,
+                config_modified: Config::modified(),
+                theme,
+                full_repaint: true,
             },
         };
-        new_editor.state.text.activate();
+        new_editor.state.switch_to_text();
+        if let Some(err) = theme_error {
+            new_editor.state.report_error(err);
+        }
+        Self::realign_textboxes(&mut new_editor.state, window_width as i32, window_height as i32);
 
         Ok(new_editor)
     }
 
+    /// Shows problems found while starting up, like settings in the config file that are not valid
+    pub fn report_errors(&mut self, errors: Vec<String>) {
+        for err in errors {
+            self.state.report_error(err);
+        }
+    }
+
     pub fn should_quit(&self) -> bool {
         self.state.quit
     }
 
+    /// Sleeps until an event arrives or the next timer is due, then handles every pending event
     pub fn handle_input(&mut self) -> Result<(), Box<dyn Error>> {
-        for event in self.context.events.poll_iter() {
+        let timeout = self.next_deadline().saturating_duration_since(Instant::now());
+        let timeout_ms = timeout.as_nanos().div_ceil(1_000_000).min(u32::MAX as u128) as u32;
+        let mut events = Vec::new();
+        if let Some(event) = self.context.events.wait_event_timeout(timeout_ms) {
+            events.push(event);
+            events.extend(self.context.events.poll_iter());
+        }
+        for event in events {
+            if self.state.answer_filter.swallows(&event) {
+                continue;
+            }
+            if self.state.prompt.is_some() && self.handle_prompt_input(&event)? {
+                continue;
+            }
             match &event {
                 // Window control
-                Event::Quit { .. } => self.state.quit = true,
-                Event::KeyUp { keycode: Some(Keycode::W), .. } if self.state.input.keyboard.ctrl_down() => self.state.quit = true,
+                Event::Quit { .. } => self.request_action(PendingAction::QUIT(Vec::new())),
+                Event::KeyUp { keycode: Some(Keycode::W), .. } if self.state.input.keyboard.ctrl_down() => self.request_action(PendingAction::QUIT(Vec::new())),
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     match self.state.active_component {
                         Component::CONSOLE => self.state.switch_to_text(),
@@ -134,8 +378,11 @@ impl <'a> Editor<'a> {
                 },
                 Event::Window { win_event: WindowEvent::Resized(w_w, w_h), .. } |
                 Event::Window { win_event: WindowEvent::PixelSizeChanged(w_w, w_h), ..} => {
-                    Self::realign_textboxes(&mut self.state.text, &mut self.state.console, *w_w, *w_h);
+                    Self::realign_textboxes(&mut self.state, *w_w, *w_h);
                 },
+                // The window or the frame texture lost what was drawn on it
+                Event::Window { win_event: WindowEvent::Exposed, .. } |
+                Event::RenderTargetsReset { .. } => self.state.full_repaint = true,
 
                 // Keyboard state
                 Event::KeyDown { keycode: Some(Keycode::LShift), .. } |
@@ -149,7 +396,7 @@ impl <'a> Editor<'a> {
                 Event::KeyDown { keycode: Some(Keycode::Return), ..} => {
                     match self.state.active_component {
                         Component::CONSOLE => {
-                            Self::handle_cmd(&mut self.state);
+                            self.handle_cmd()?;
                             continue;
                         },
                         _ => {},
@@ -159,23 +406,46 @@ impl <'a> Editor<'a> {
                 // Keyboard cmds
                 Event::KeyDown { keycode: Some(Keycode::Equals), .. }
                 if self.state.input.keyboard.ctrl_down() => {
-                    self.state.text.enlarge_text()?;
                     self.state.console.enlarge_text()?;
-                    let (w_w, w_h) = self.context.canvas.window().size();
-                    Self::realign_textboxes(&mut self.state.text, &mut self.state.console, w_w as i32, w_h as i32);
+                    self.zoom_text(self.state.console.font_size())?;
                 }
                 Event::KeyDown { keycode: Some(Keycode::Minus), .. }
                 if self.state.input.keyboard.ctrl_down() => {
-                    self.state.text.shrink_text()?;
                     self.state.console.shrink_text()?;
-                    let (w_w, w_h) = self.context.canvas.window().size();
-                    Self::realign_textboxes(&mut self.state.text, &mut self.state.console, w_w as i32, w_h as i32);
+                    self.zoom_text(self.state.console.font_size())?;
                 }
 
+                Event::KeyDown { keycode: Some(Keycode::Tab), .. }
+                if self.state.input.keyboard.ctrl_down() => {
+                    if self.state.input.keyboard.shift_down() {
+                        self.state.previous_buffer();
+                    } else {
+                        self.state.next_buffer();
+                    }
+                    continue;
+                },
+                Event::KeyDown { keycode: Some(Keycode::Backslash), .. }
+                if self.state.input.keyboard.ctrl_down() => {
+                    if self.state.input.keyboard.shift_down() {
+                        self.split_pane(SplitDirection::HORIZONTAL);
+                    } else {
+                        self.split_pane(SplitDirection::VERTICAL);
+                    }
+                    continue;
+                },
+
                 // Mouse state
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. }
+                if self.state.tabs.click_in_window(*x, *y) => {
+                    if let Some(index) = self.state.tabs.tab_at(*x, *y) {
+                        self.state.switch_buffer(index);
+                    }
+                    continue;
+                },
                 Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
                     self.state.input.mouse.press_left();
-                    if self.state.text.click_in_window(*x, *y) {
+                    if let Some(index) = self.state.pane_at(*x, *y) {
+                        self.state.focus_pane(index);
                         self.state.switch_to_text();
                     } else {
                         self.state.switch_to_console();
@@ -183,10 +453,10 @@ impl <'a> Editor<'a> {
                 },
                 Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } => self.state.input.mouse.release_left(),
                 Event::MouseWheel { .. } => {
-                    self.state.text.handle_input(event, &self.state.input)?;
+                    self.state.buffers[self.state.current].text.handle_input(event, &self.state.input)?;
                     continue;
                 },
-                Event::DropFile { filename, .. } => Self::open_file_from_path(&mut self.state.text, &filename),
+                Event::DropFile { filename, .. } => _ = self.open_buffer(Path::new(filename)),
 
                 // File io
                 Event::KeyDown { keycode: Some(Keycode::O), .. }
@@ -202,6 +472,7 @@ impl <'a> Editor<'a> {
                         },
                     ];
                     let file_path_ref = self.state.open_file_paths.clone();
+                    let sender = self.context.event_subsystem.event_sender();
                     show_open_file_dialog(
                         &filters,
                         None::<PathBuf>,
@@ -215,47 +486,17 @@ impl <'a> Editor<'a> {
                                 err.into_inner()
                             });
                             open_file_paths.extend_from_slice(&file_paths);
+                            _ = sender.push_custom_event(DialogClosed);
                         }),
                         ).map_err(|err| err.to_string())?;
                 },
                 Event::KeyDown { keycode: Some(Keycode::S), .. }
-                if unsafe { SDL_GetModState() } & SDL_KMOD_CTRL > 0 => {
-                    let filters = [
-                        DialogFileFilter {
-                            name: "All Files (*.*)",
-                            pattern: "*",
-                        },
-                        DialogFileFilter {
-                            name: "Text Document (*.txt)",
-                            pattern: "txt",
-                        },
-                    ];
-                    let file_path_ref = self.state.save_file_paths.clone();
-                    show_save_file_dialog(
-                        &filters,
-                        None::<PathBuf>,
-                        self.context.canvas.window(),
-                        Box::new(move |result, _| {
-                            let Ok(mut file_paths) = result else { return };
-                            let mut open_file_paths = file_path_ref.lock().unwrap_or_else(|mut err| {
-                                **err.get_mut() = vec![];
-                                file_path_ref.clear_poison();
-                                err.into_inner()
-                            });
-                            for file_path in file_paths.iter_mut() {
-                                if file_path.extension() == None {
-                                    file_path.set_extension("txt");
-                                }
-                            }
-                            open_file_paths.extend_from_slice(&file_paths);
-                        }),
-                        ).map_err(|err| err.to_string())?;
-                },
+                if unsafe { SDL_GetModState() } & SDL_KMOD_CTRL > 0 => self.save_document()?,
 
                 _ => {},
             }
             match self.state.active_component {
-                Component::TEXT => self.state.text.handle_input(event, &self.state.input)?,
+                Component::TEXT => self.state.buffers[self.state.current].text.handle_input(event, &self.state.input)?,
                 Component::CONSOLE => self.state.console.handle_input(event, &self.state.input)?,
             }
         }
@@ -263,108 +504,720 @@ impl <'a> Editor<'a> {
         Ok(())
     }
 
+    /// Redraws the damaged parts of the frame and shows it, does nothing if nothing changed
     pub fn render(&mut self) -> Result<(), Box<dyn Error>> {
-        if self.state.text.should_render() | self.state.console.should_render() {
-            self.context.canvas.set_draw_color(Color::BLACK);
-            self.context.canvas.clear();
-            self.state.text.draw(&mut self.context.canvas, &self.context.texture_creator)?;
-            self.state.console.draw(&mut self.context.canvas, &self.context.texture_creator)?;
-            self.state.text.draw_console(&mut self.context.canvas, &self.context.texture_creator)?;
-            if !self.context.canvas.present() {
-                return Err(Box::new(get_error()));
-            }
-        };
+        let (frame_width, frame_height) = self.context.canvas.output_size()?;
+        if (self.context.frame.width(), self.context.frame.height()) != (frame_width, frame_height) {
+            self.context.frame = create_frame(self.context.texture_creator, &self.context.canvas)?;
+            self.state.full_repaint = true;
+        }
+        Self::mark_stale_panes(&mut self.state);
+        let state = &self.state;
+        let panes_render = state.panes.iter().enumerate().any(|(i, pane)| i != state.focus && pane.view.should_render());
+        if !(state.full_repaint || panes_render || state.tabs.should_render() || state.buffer().text.should_render() || state.console.should_render()) {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        self.context.canvas.with_texture_canvas(&mut self.context.frame, |canvas| {
+            result = Self::draw_frame(&mut self.state, canvas);
+        })?;
+        result?;
+        self.context.canvas.copy(&self.context.frame, None, None)?;
+        if !self.context.canvas.present() {
+            return Err(Box::new(get_error()));
+        }
         Ok(())
     }
 
+    /// Earliest time something changes without input: a cursor blink, a message expiring or a timer
+    fn next_deadline(&self) -> Instant {
+        let state = &self.state;
+        [
+            state.buffer().text.next_blink(),
+            state.console.next_blink(),
+            state.messages.deadline(),
+            Some(state.swap_timer + SWAP_INTERVAL),
+            Some(state.disk_check_timer + DISK_CHECK_INTERVAL),
+        ].into_iter().flatten().min().unwrap_or_else(Instant::now)
+    }
+
     pub fn update(&mut self) {
-        self.state.text.update();
+        self.state.buffer_mut().text.update();
         self.state.console.update();
 
         self.check_open_files();
         self.check_save_files();
+        self.update_title();
+        self.update_tabs();
+        self.update_swap();
+        self.check_disk_changes();
+        if self.state.messages.update() {
+            self.state.console.request_render();
+        }
     }
 
-    pub fn close(self) {
+    pub fn close(mut self) {
+        for buffer in &mut self.state.buffers {
+            buffer.discard_swap();
+        }
         unsafe {SDL_StopTextInput(self.context.canvas.window().raw()); }
     }
 }
 
 impl <'a> Editor<'a> {
     pub fn open_file(&mut self, file_path: &str) {
-        Self::open_file_from_path(&mut self.state.text, &file_path);
+        let file_path = Path::new(file_path);
+        let index = if file_path.exists() {
+            let Some(index) = self.open_buffer(file_path) else {
+                return;
+            };
+            index
+        } else {
+            let index = self.state.current;
+            self.state.buffers[index].set_path(file_path.to_path_buf(), &self.state.config.filetypes);
+            self.state.report_info(format!("New file {}", file_path.display()));
+            index
+        };
+
+        let buffer = &self.state.buffers[index];
+        if let Some(swap_path) = buffer.foreign_swap() {
+            self.state.prompt = Some(Prompt::recover_swap(&buffer.document.name(), index, swap_path.to_path_buf()));
+            self.state.console.request_render();
+        }
     }
 
     fn check_open_files(&mut self) {
-        let mut open_file_paths = self.state.open_file_paths.lock().unwrap_or_else(|mut err| {
+        let open_file_paths = std::mem::take(&mut *self.state.open_file_paths.lock().unwrap_or_else(|mut err| {
             **err.get_mut() = vec![];
             self.state.open_file_paths.clear_poison();
             err.into_inner()
-        });
-        while let Some(file_path) = open_file_paths.pop() {
-            Self::open_file_from_path(&mut self.state.text, file_path.to_str().unwrap_or(""));
+        }));
+        for file_path in open_file_paths {
+            self.open_buffer(&file_path);
         }
     }
 
     fn check_save_files(&mut self) {
-        let mut save_file_paths = self.state.save_file_paths.lock().unwrap_or_else(|mut err| {
+        let mut save_file_paths = std::mem::take(&mut *self.state.save_file_paths.lock().unwrap_or_else(|mut err| {
             **err.get_mut() = vec![];
-            self.state.open_file_paths.clear_poison();
+            self.state.save_file_paths.clear_poison();
             err.into_inner()
-        });
-        while let Some(file_path) = save_file_paths.pop() {
-            let data = self.state.text.export();
-            let normalized_data = data.replace("\n", "\r\n");
-            _ = std::fs::write(file_path, normalized_data);
+        }));
+        while let Some((index, file_path)) = save_file_paths.pop() {
+            let path = file_path.display().to_string();
+            match self.state.buffers[index].save_as(file_path, &self.state.save_options, &self.state.config.filetypes) {
+                Ok(bytes) => self.state.report_info(format!("Wrote {path} ({bytes} bytes)")),
+                Err(err) => self.state.report_error(format!("Could not save {path}: {err}")),
+            }
+            let saved = !self.state.buffers[index].is_modified();
+            if let Some((_, action)) = self.state.after_save.take_if(|(after_index, _)| saved && *after_index == index) {
+                self.request_action(action);
+            }
+        }
+    }
+
+    /// Saves to the current path, or asks for one if the document was never saved
+    fn save_document(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.state.buffer().document.path().is_some() {
+            let index = self.state.current;
+            Self::write_document(&mut self.state, index);
+            Ok(())
+        } else {
+            self.show_save_dialog()
+        }
+    }
+
+    fn write_document(state: &mut State, index: usize) {
+        let buffer = &mut state.buffers[index];
+        let path = buffer.document.path().map_or_else(String::new, |path| path.display().to_string());
+        match buffer.save(&state.save_options) {
+            Ok(bytes) => state.report_info(format!("Wrote {path} ({bytes} bytes)")),
+            Err(err) => state.report_error(format!("Could not save {path}: {err}")),
+        }
+    }
+
+    fn show_save_dialog(&self) -> Result<(), Box<dyn Error>> {
+        let filters = [
+            DialogFileFilter {
+                name: "All Files (*.*)",
+                pattern: "*",
+            },
+            DialogFileFilter {
+                name: "Text Document (*.txt)",
+                pattern: "txt",
+            },
+        ];
+        let file_path_ref = self.state.save_file_paths.clone();
+        let index = self.state.current;
+        let sender = self.context.event_subsystem.event_sender();
+        show_save_file_dialog(
+            &filters,
+            None::<PathBuf>,
+            self.context.canvas.window(),
+            Box::new(move |result, _| {
+                let Ok(mut file_paths) = result else { return };
+                let mut open_file_paths = file_path_ref.lock().unwrap_or_else(|mut err| {
+                    **err.get_mut() = vec![];
+                    file_path_ref.clear_poison();
+                    err.into_inner()
+                });
+                for file_path in file_paths.iter_mut() {
+                    if file_path.extension() == None {
+                        file_path.set_extension("txt");
+                    }
+                }
+                open_file_paths.extend(file_paths.into_iter().map(|file_path| (index, file_path)));
+                _ = sender.push_custom_event(DialogClosed);
+            }),
+            ).map_err(|err| err.to_string())?;
+        Ok(())
+    }
+
+    /// Runs the action, or asks the user first if it would lose unsaved changes
+    ///
+    /// With several modified buffers the prompt shows the first, saving moves on to the next
+    fn request_action(&mut self, action: PendingAction) {
+        let discarded = match &action {
+            PendingAction::QUIT(discarded) => discarded.as_slice(),
+            PendingAction::RECOVER(..) => &[],
+        };
+        let modified = self.state.buffers.iter()
+            .enumerate()
+            .filter(|(i, buffer)| buffer.is_modified() && !discarded.contains(i))
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+        let Some(&index) = modified.first() else {
+            return self.execute_action(action);
+        };
+        self.state.switch_buffer(index);
+        let name = match modified.len() {
+            1 => self.state.buffer().document.name(),
+            count => format!("{} (and {} more)", self.state.buffer().document.name(), count - 1),
+        };
+        self.state.prompt = Some(Prompt::unsaved_changes(&name, index, action));
+        self.state.switch_to_text();
+        self.state.console.request_render();
+    }
+
+    fn execute_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::QUIT(_) => self.state.quit = true,
+            PendingAction::RECOVER(index, swap_path) => self.recover_swap(index, &swap_path),
+        }
+    }
+
+    fn recover_swap(&mut self, index: usize, swap_path: &Path) {
+        match self.state.buffers[index].recover(swap_path) {
+            Ok(()) => self.state.report_info("Recovered unsaved changes, :w to keep them"),
+            Err(err) => self.state.report_error(format!("Could not read {}: {err}", swap_path.display())),
+        }
+    }
+
+    /// Returns true if the event was used to answer the prompt
+    fn handle_prompt_input(&mut self, event: &Event) -> Result<bool, Box<dyn Error>> {
+        let choice = match event {
+            Event::KeyDown { keycode: Some(Keycode::LShift | Keycode::RShift | Keycode::LCtrl | Keycode::RCtrl), .. } => return Ok(false),
+            Event::KeyDown { keycode: Some(keycode), .. } => self.state.prompt.as_ref().and_then(|prompt| prompt.choice(*keycode)),
+            Event::TextInput { .. } | Event::DropFile { .. } | Event::Quit { .. } => None,
+            _ => return Ok(false),
+        };
+        let Some(choice) = choice else {
+            return Ok(true);
+        };
+        let Some(prompt) = self.state.prompt.take() else {
+            return Ok(true);
+        };
+        self.state.answer_filter.answered();
+        self.state.console.request_render();
+
+        // The answer is about the buffer that was asked for, even if a click switched away from it
+        let index = prompt.buffer();
+        match (choice, prompt.into_action()) {
+            (PromptChoice::DISCARD, PendingAction::RECOVER(index, _)) => match self.state.buffers[index].delete_foreign_swap() {
+                Ok(()) => self.state.report_info("Deleted unsaved changes from the crash"),
+                Err(err) => self.state.report_error(format!("Could not delete swap file: {err}")),
+            },
+            (PromptChoice::SAVE, action) => {
+                if self.state.buffers[index].document.path().is_none() {
+                    self.state.after_save = Some((index, action));
+                    self.state.switch_buffer(index);
+                    self.show_save_dialog()?;
+                } else {
+                    Self::write_document(&mut self.state, index);
+                    if !self.state.buffers[index].is_modified() {
+                        self.request_action(action);
+                    }
+                }
+            },
+            (PromptChoice::DISCARD, PendingAction::QUIT(mut discarded)) => {
+                discarded.push(index);
+                self.request_action(PendingAction::QUIT(discarded));
+            },
+            (PromptChoice::DISCARD | PromptChoice::RECOVER, action) => self.execute_action(action),
+            (PromptChoice::CANCEL, _) => {},
+        }
+        Ok(true)
+    }
+
+    /// Reloads documents that another program rewrote, or warns if that would lose edits
+    fn check_disk_changes(&mut self) {
+        if self.state.disk_check_timer.elapsed() < DISK_CHECK_INTERVAL {
+            return;
+        }
+        self.state.disk_check_timer = Instant::now();
+        self.check_config_file();
+
+        for index in 0..self.state.buffers.len() {
+            let buffer = &mut self.state.buffers[index];
+            if !buffer.document.poll_disk_change() {
+                continue;
+            }
+            if buffer.is_modified() {
+                let name = buffer.document.name();
+                self.state.report_error(format!("{name} changed on disk, :w to overwrite or :e! to reload"));
+            } else {
+                Self::reload_buffer(&mut self.state, index);
+            }
+        }
+    }
+
+    /// Periodically writes unsaved text to swap files so a crash can be recovered from
+    fn update_swap(&mut self) {
+        if self.state.swap_timer.elapsed() < SWAP_INTERVAL {
+            return;
+        }
+        self.state.swap_timer = Instant::now();
+
+        let errors = self.state.buffers.iter_mut()
+            .filter_map(|buffer| buffer.update_swap().err().map(|err| format!("Could not write swap file for {}: {err}", buffer.document.name())))
+            .collect::<Vec<String>>();
+        for error in errors {
+            self.state.report_error(error);
+        }
+    }
+
+    fn update_tabs(&mut self) {
+        let labels = self.state.buffers.iter().map(Buffer::label).collect();
+        self.state.tabs.update(labels, self.state.current);
+    }
+
+    fn update_title(&mut self) {
+        let buffer = self.state.buffer();
+        let title = buffer.document.title(buffer.is_modified(), WINDOW_TITLE);
+        if title != self.state.title {
+            _ = self.context.canvas.window_mut().set_title(&title);
+            self.state.title = title;
         }
     }
 }
 
 impl <'a> Editor<'a> {
-    fn realign_textboxes(text: &mut TextBox, console: &mut TextBox, w_w: i32, w_h: i32) {
-        let console_height = console.height_of_one_line() as i32;
-        let text_height = w_h  - console_height - 10;
-        text.resize(Vector2D::new(0, 0), w_w, text_height as i32);
-        console.resize(Vector2D::new(0, text_height as u32 + 10), w_w, console_height as i32);
+    /// Draws everything that changed onto the frame
+    fn draw_frame(state: &mut State, canvas: &mut Canvas<Window>) -> Result<(), Box<dyn Error>> {
+        if std::mem::take(&mut state.full_repaint) {
+            canvas.set_draw_color(state.theme.border);
+            canvas.clear();
+            state.tabs.request_render();
+            for pane in &mut state.panes {
+                pane.view.request_render();
+            }
+            state.buffer_mut().text.request_render();
+            state.console.request_render();
+        }
+
+        if state.tabs.should_render() {
+            state.tabs.draw(canvas)?;
+        }
+        for (i, pane) in state.panes.iter_mut().enumerate() {
+            if i != state.focus {
+                state.buffers[pane.buffer].text.draw_view(&mut pane.view, canvas)?;
+            }
+        }
+        let buffer = &mut state.buffers[state.current];
+        // The status line shows the cursor position, so it follows any change to the focused text
+        let status_changed = buffer.text.should_render();
+        buffer.text.draw(canvas)?;
+        if !status_changed && !state.console.should_render() {
+            return Ok(());
+        }
+        state.console.request_render();
+        state.console.draw(canvas)?;
+        let status = match buffer.text.language() {
+            Some(language) => format!("{}  {}", language.name(), buffer.document.status()),
+            None => buffer.document.status(),
+        };
+        buffer.text.draw_console(canvas, &status)?;
+        if let Some(prompt) = &state.prompt {
+            state.console.draw_message(canvas, prompt.message(), None)?;
+        } else if let Some(message) = state.messages.current() && !state.console.is_active() {
+            let color = match message.level() {
+                MessageLevel::INFO => None,
+                MessageLevel::ERROR => Some(state.theme.error),
+            };
+            state.console.draw_message(canvas, message.text(), color)?;
+        }
+        Ok(())
+    }
+
+    /// Repaints panes showing a buffer that was changed through another pane
+    fn mark_stale_panes(state: &mut State) {
+        for (i, pane) in state.panes.iter_mut().enumerate() {
+            let revision = state.buffers[pane.buffer].text.revision();
+            if pane.revision != revision {
+                pane.revision = revision;
+                if i != state.focus {
+                    pane.view.request_render();
+                }
+            }
+        }
+    }
+
+    /// Stacks the tab bar, the panes and the console, then lays out the panes in their area
+    fn realign_textboxes(state: &mut State, w_w: i32, w_h: i32) {
+        state.full_repaint = true;
+        let tabs_height = state.tabs.height() as i32;
+        let console_height = state.console.height_of_one_line() as i32;
+        let text_height = w_h - tabs_height - console_height - 10;
+        state.tabs.resize(Vector2D::new(0, 0), w_w, tabs_height);
+        state.console.resize(Vector2D::new(0, (tabs_height + text_height) as u32 + 10), w_w, console_height);
+
+        let text_area = Area::new(Vector2D::new(0, tabs_height as u32), w_w.max(0) as u32, text_height.max(0) as u32);
+        for (index, area) in state.layout.arrange(text_area, PANE_GAP) {
+            let pane = &mut state.panes[index];
+            pane.area = area;
+            let Area { pos, width, height } = area;
+            if index == state.focus {
+                state.buffers[pane.buffer].text.resize(pos, width as i32, height as i32);
+            } else {
+                pane.view.resize(pos, width as i32, height as i32);
+            }
+        }
+    }
+
+    fn realign_to_window(&mut self) {
+        let (w_w, w_h) = self.context.canvas.window().size();
+        Self::realign_textboxes(&mut self.state, w_w as i32, w_h as i32);
+    }
+
+    /// Applies the console's font size to the tab bar and every buffer and pane
+    fn zoom_text(&mut self, font_size: f32) -> Result<(), Box<dyn Error>> {
+        self.state.tabs.set_font_size(font_size)?;
+        for buffer in &mut self.state.buffers {
+            buffer.text.set_font_size(font_size)?;
+        }
+        for (i, pane) in self.state.panes.iter_mut().enumerate() {
+            if i != self.state.focus {
+                self.state.buffers[pane.buffer].text.fit_view(&mut pane.view)?;
+            }
+        }
+        self.realign_to_window();
+        Ok(())
+    }
+
+    /// Shows the focused pane's buffer in a new pane next to it, with its own cursor
+    fn split_pane(&mut self, direction: SplitDirection) {
+        let view = self.state.buffer().text.view();
+        let new_pane = self.state.panes.len();
+        self.state.panes.push(Pane::new(self.state.current, view, Area::default()));
+        self.state.layout.split(self.state.focus, new_pane, direction);
+        self.state.focus_pane(new_pane);
+        self.realign_to_window();
+    }
+
+    fn close_pane(&mut self) {
+        if self.state.panes.len() == 1 {
+            return self.state.report_error("Cannot close the last pane");
+        }
+        let closed = self.state.focus;
+        self.state.focus_pane(if closed == 0 { 1 } else { closed - 1 });
+        self.state.panes.remove(closed);
+        self.state.layout.remove(closed);
+        if self.state.focus > closed {
+            self.state.focus -= 1;
+        }
+        self.realign_to_window();
+    }
+
+    /// Opens the file in its own buffer, or switches to it if it is already open
+    ///
+    /// An empty untitled buffer is reused instead of leaving it behind. Returns the index of the buffer
+    /// showing the file, None if it could not be opened
+    fn open_buffer(&mut self, file_path: &Path) -> Option<usize> {
+        if let Some(index) = self.state.buffers.iter().position(|buffer| buffer.is_file(file_path)) {
+            self.state.switch_buffer(index);
+            return Some(index);
+        }
+        let (document, data) = match Document::open(file_path) {
+            Ok(opened) => opened,
+            Err(err) => {
+                self.state.report_error(format!("Could not open {}: {err}", file_path.display()));
+                return None;
+            },
+        };
+        if !self.state.buffer().is_scratch() && let Err(err) = self.add_buffer() {
+            self.state.report_error(format!("Could not open {}: {err}", file_path.display()));
+            return None;
+        }
+        let index = self.state.current;
+        self.state.buffers[index].load(document, data, &self.state.config.filetypes);
+        Some(index)
+    }
+
+    /// Opens the file in the current buffer, dropping its unsaved changes, or switches to it if it is already open
+    fn open_in_place(&mut self, file_path: &Path) {
+        if let Some(index) = self.state.buffers.iter().position(|buffer| buffer.is_file(file_path)) {
+            return self.state.switch_buffer(index);
+        }
+        match Document::open(file_path) {
+            Ok((document, data)) => {
+                let index = self.state.current;
+                self.state.buffers[index].load(document, data, &self.state.config.filetypes);
+            },
+            Err(err) => self.state.report_error(format!("Could not open {}: {err}", file_path.display())),
+        }
     }
 
-    fn open_file_from_path(text: &mut TextBox, file_path: &str) {
-        let data = std::fs::read_to_string(file_path).unwrap_or_else(|_| String::new());
-        let normalized_data = data.replace("\r\n", "\n");
-        text.set_text(normalized_data);
+    /// Adds an empty buffer after the others and switches to it
+    fn add_buffer(&mut self) -> Result<(), Box<dyn Error>> {
+        let (w_w, w_h) = self.context.canvas.window().size();
+        let mut text = TextBox::build(
+            DEFAULT_TEXT_POS,
+            w_w,
+            w_h,
+            &self.state.config,
+            &self.state.theme,
+            &self.context.font_path,
+            self.context.video_subsystem,
+            self.context.ttf_context,
+            self.context.texture_creator,
+        )?;
+        text.set_font_size(self.state.console.font_size())?;
+        text.set_line_numbers(self.state.config.line_numbers);
+        text.set_wrap(self.state.config.wrap);
+        self.state.buffers.push(Buffer::new(Document::new(), text));
+        self.state.switch_buffer(self.state.buffers.len() - 1);
+        Ok(())
+    }
+
+    /// Rereads the buffer's file from disk, keeping the cursor where it was
+    fn reload_buffer(state: &mut State, index: usize) {
+        let buffer = &mut state.buffers[index];
+        let name = buffer.document.name();
+        match buffer.reload(&state.config.filetypes) {
+            Ok(()) => state.report_info(format!("Reloaded {name}")),
+            Err(err) => state.report_error(format!("Could not reload {name}: {err}")),
+        }
     }
 
-    fn handle_cmd(state: &mut State) {
-        let cmd_str = state.console.extract_text();
+    fn handle_cmd(&mut self) -> Result<(), Box<dyn Error>> {
+        let cmd_str = self.state.console.extract_text();
         let cmd = Command::new(cmd_str);
-        let clone_cmd = cmd.clone();
+        self.state.command_state.set_prev(cmd.clone());
 
         match cmd {
-            Command::JUMP(..) => state.text.execute_cmd(cmd),
-            Command::QUIT => state.quit = true,
-            Command::OPEN(file_path) => {
-                let mut open_file_paths = state.open_file_paths.lock().unwrap_or_else(|mut err| {
-                    **err.get_mut() = vec![];
-                    state.open_file_paths.clear_poison();
-                    err.into_inner()
-                });
-                open_file_paths.push(file_path.clone());
+            Command::JUMP(..) => self.state.buffer_mut().text.execute_cmd(cmd),
+            Command::QUIT(true) => self.state.quit = true,
+            Command::QUIT(false) => self.request_action(PendingAction::QUIT(Vec::new())),
+            Command::OPEN(file_path, false) => _ = self.open_buffer(&file_path),
+            Command::OPEN(file_path, true) => self.open_in_place(&file_path),
+            Command::RELOAD => {
+                let index = self.state.current;
+                Self::reload_buffer(&mut self.state, index);
             },
-            Command::WRITE(file_path) => {
-                let mut open_file_paths = state.save_file_paths.lock().unwrap_or_else(|mut err| {
+            Command::BUFFER(index) if index < self.state.buffers.len() => self.state.switch_buffer(index),
+            Command::BUFFER(index) => self.state.report_error(format!("No buffer {}", index + 1)),
+            Command::BNEXT => self.state.next_buffer(),
+            Command::BPREV => self.state.previous_buffer(),
+            Command::LIST => self.state.report_info(self.state.buffer_list()),
+            Command::SPLIT(direction) => self.split_pane(direction),
+            Command::CLOSE => self.close_pane(),
+            Command::WRITE(Some(file_path)) => {
+                let mut open_file_paths = self.state.save_file_paths.lock().unwrap_or_else(|mut err| {
                     **err.get_mut() = vec![];
-                    state.save_file_paths.clear_poison();
+                    self.state.save_file_paths.clear_poison();
                     err.into_inner()
                 });
-                open_file_paths.push(file_path.clone());
+                open_file_paths.push((self.state.current, file_path.clone()));
             },
+            Command::WRITE(None) => self.save_document()?,
             Command::RUN(program, args) => {
-                let _ = std::process::Command::new(program)
-                    .args(args).spawn();
+                if let Err(err) = std::process::Command::new(&program).args(args).spawn() {
+                    self.state.report_error(format!("Could not run {program}: {err}"));
+                }
+            },
+            Command::SET(key, value) => self.handle_set(&key, &value),
+            Command::THEME(Some(name)) => match Theme::load(&name) {
+                Ok(theme) => {
+                    self.state.config.theme = name.clone();
+                    self.state.set_theme(theme);
+                    self.state.report_info(format!("Theme {name}"));
+                },
+                Err(err) => self.state.report_error(format!("Could not load theme: {err}")),
+            },
+            Command::THEME(None) => {
+                let themes = Theme::available().join(", ");
+                self.state.report_info(format!("Theme {}, available: {themes}", self.state.theme.name));
+            },
+            Command::ERROR => self.state.report_error("Unknown command"),
+            _ => self.state.command_state.execute_cmd(&mut self.state.buffers[self.state.current].text, cmd),
+        }
+        Ok(())
+    }
+
+    fn handle_set(&mut self, key: &str, value: &str) {
+        match key {
+            "ff" | "fileformat" => {
+                let Some(line_ending) = LineEnding::from_name(value) else {
+                    return self.state.report_error(format!("Unknown file format \"{value}\", expected unix or dos"));
+                };
+                self.state.buffer_mut().set_line_ending(line_ending);
+                self.state.report_info(format!("Line endings set to {}", line_ending.name()));
+            },
+            "enc" | "encoding" => {
+                let Some(encoding) = Encoding::from_name(value) else {
+                    return self.state.report_error(format!("Unknown encoding \"{value}\", expected utf-8, utf-8-bom, utf-16le, utf-16be or latin1"));
+                };
+                self.state.buffer_mut().document.set_encoding(encoding);
+                self.state.report_info(format!("Encoding set to {}", encoding.name()));
+            },
+            // Also taken by the current buffer, over its file settings and the indentation found in it
+            "indent_style" | "indent_size" => {
+                let mut chosen = FileSettings::default();
+                if let Err(err) = chosen.set(key, value) {
+                    return self.state.report_error(err);
+                }
+                if let Some(Some(style)) = chosen.indent_style {
+                    let mut config = self.state.config.clone();
+                    config.indent_style = style;
+                    if let Err(err) = self.apply_config(config) {
+                        return self.state.report_error(format!("Could not apply {key}: {err}"));
+                    }
+                }
+                let index = self.state.current;
+                self.state.buffers[index].choose_indent(&chosen, &self.state.config.filetypes);
+                self.state.report_info(format!("Set {key} to {value}"));
+            },
+            _ => {
+                let mut config = self.state.config.clone();
+                if let Err(err) = config.set(key, value).and_then(|_| config.check()) {
+                    return self.state.report_error(err);
+                }
+                match self.apply_config(config) {
+                    Ok(()) => self.state.report_info(format!("Set {key} to {value}")),
+                    Err(err) => self.state.report_error(format!("Could not apply {key}: {err}")),
+                }
             },
-            Command::ERROR => {},
-            _ => state.command_state.execute_cmd(&mut state.text, cmd),
         }
-        state.command_state.set_prev(clone_cmd);
     }
-}
\ No newline at end of file
+
+    /// Brings every text box, pane and the window in line with config
+    ///
+    /// The editor keeps its old config if a step fails, like loading the font at the new size
+    fn apply_config(&mut self, mut config: Config) -> Result<(), Box<dyn Error>> {
+        let old = self.state.config.clone();
+        let theme = if config.theme != old.theme {
+            match Theme::load(&config.theme) {
+                Ok(theme) => Some(theme),
+                Err(err) => {
+                    config.theme = old.theme.clone();
+                    self.state.report_error(format!("Could not load theme: {err}"));
+                    None
+                },
+            }
+        } else {
+            None
+        };
+
+        let old_font_size = self.state.console.font_size();
+        let font_size = if config.font_size != old.font_size { config.font_size } else { old_font_size };
+        if let Err(err) = self.switch_config(&old, &config, font_size) {
+            // Put back what was already changed, the old font loaded before so it loads again
+            _ = self.switch_config(&config, &old, old_font_size);
+            return Err(err);
+        }
+        if let Some(theme) = theme {
+            self.state.set_theme(theme);
+        }
+        self.state.config = config;
+        Ok(())
+    }
+
+    /// Changes the settings that differ between from and to, ending with the text at font_size
+    fn switch_config(&mut self, from: &Config, to: &Config, font_size: f32) -> Result<(), Box<dyn Error>> {
+        self.state.save_options.backup = to.backup;
+        if to.wrap != from.wrap {
+            for buffer in &mut self.state.buffers {
+                buffer.text.set_wrap(to.wrap);
+            }
+            for pane in &mut self.state.panes {
+                pane.view.set_wrap(to.wrap);
+            }
+        }
+        if to.line_numbers != from.line_numbers {
+            for buffer in &mut self.state.buffers {
+                buffer.text.set_line_numbers(to.line_numbers);
+            }
+            for pane in &mut self.state.panes {
+                pane.view.request_render();
+            }
+        }
+        if to.filetypes != from.filetypes {
+            for buffer in &mut self.state.buffers {
+                buffer.apply_file_settings(&to.filetypes);
+            }
+        }
+        if (to.window_width, to.window_height) != (from.window_width, from.window_height) {
+            self.context.canvas.window_mut().set_size(to.window_width, to.window_height)?;
+        }
+        if (&to.font, &to.font_family) != (&from.font, &from.font_family) {
+            self.set_font_path(FontSettings::new(to).resolve()?)?;
+        }
+
+        self.state.console.set_config(to)?;
+        self.state.tabs.set_config(to)?;
+        for buffer in &mut self.state.buffers {
+            buffer.text.set_config(to)?;
+        }
+        if font_size != self.state.console.font_size() {
+            self.state.console.set_font_size(font_size)?;
+        }
+        self.zoom_text(self.state.console.font_size())
+    }
+
+    /// Draws the text of every box with the font file at font_path
+    fn set_font_path(&mut self, font_path: PathBuf) -> Result<(), Box<dyn Error>> {
+        self.state.console.set_font_path(&font_path)?;
+        self.state.tabs.set_font_path(&font_path)?;
+        for buffer in &mut self.state.buffers {
+            buffer.text.set_font_path(&font_path)?;
+        }
+        self.context.font_path = font_path;
+        Ok(())
+    }
+
+    /// Reloads the config file if it was written since it was last read
+    fn check_config_file(&mut self) {
+        let modified = Config::modified();
+        if modified == self.state.config_modified {
+            return;
+        }
+        self.state.config_modified = modified;
+        let (config, errors) = Config::load();
+        let had_errors = !errors.is_empty();
+        self.report_errors(errors);
+        match self.apply_config(config) {
+            Ok(()) if !had_errors => self.state.report_info("Reloaded config"),
+            Ok(()) => {},
+            Err(err) => self.state.report_error(format!("Could not apply config: {err}")),
+        }
+    }
+}
+
+/// Render target covering the whole window
+fn create_frame<'a>(texture_creator: &'a TextureCreator<WindowContext>, canvas: &Canvas<Window>) -> Result<Texture<'a>, Box<dyn Error>> {
+    let (width, height) = canvas.output_size()?;
+    let mut frame = texture_creator.create_texture_target(PixelFormat::from(PixelFormatEnum::ARGB8888), width, height)?;
+    frame.set_blend_mode(BlendMode::None);
+    Ok(frame)
+}
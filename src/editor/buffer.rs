@@ -0,0 +1,234 @@
+use std::{fs, io, path::{Path, PathBuf}, thread::JoinHandle};
+
+use crate::editor::{document::{Document, LineEnding, SaveOptions}, editorconfig::{FileSettings, Section}, highlight::Language, tabs, textbox::TextBox, textrope::Rope};
+
+/// An open document and the text box holding its text, cursor and scroll position
+pub struct Buffer<'a> {
+    pub document: Document,
+    pub text: TextBox<'a>,
+    /// Text revision held by the swap file this editor wrote, if any
+    swap_revision: Option<usize>,
+    /// Swap file being written on another thread, with the text revision it holds
+    swap_write: Option<(usize, JoinHandle<io::Result<()>>)>,
+    /// Swap file left behind by a crash, it is not written over until it is recovered or deleted
+    foreign_swap: Option<PathBuf>,
+    /// Formatting rules for the file, from the user config and `.editorconfig`
+    settings: FileSettings,
+    /// Indentation picked with `:set`, it wins over the file settings and over what the text looks like
+    chosen: FileSettings,
+}
+
+impl <'a> Buffer<'a> {
+    pub fn new(document: Document, text: TextBox<'a>) -> Self {
+        Self {
+            document,
+            text,
+            swap_revision: None,
+            swap_write: None,
+            foreign_swap: None,
+            settings: FileSettings::default(),
+            chosen: FileSettings::default(),
+        }
+    }
+
+    /// True if the buffer differs from the file on disk
+    pub fn is_modified(&self) -> bool {
+        self.text.is_modified() || self.document.format_changed()
+    }
+
+    /// True for an empty, untouched and untitled buffer that can be reused to open a file
+    pub fn is_scratch(&self) -> bool {
+        self.document.path().is_none() && !self.is_modified() && self.text.is_empty()
+    }
+
+    /// True if the buffer is showing the file at path
+    pub fn is_file(&self, path: &Path) -> bool {
+        let Some(own_path) = self.document.path() else {
+            return false;
+        };
+        own_path == path || fs::canonicalize(own_path).ok().is_some_and(|own_path| fs::canonicalize(path).ok() == Some(own_path))
+    }
+
+    /// Name shown in the tab bar, the star marks unsaved changes
+    pub fn label(&self) -> String {
+        let modified_marker = if self.is_modified() { "*" } else { "" };
+        format!("{}{}", self.document.name(), modified_marker)
+    }
+
+    /// Replaces the text with a freshly opened document
+    pub fn load(&mut self, document: Document, text: Rope, filetypes: &[Section]) {
+        self.discard_swap();
+        self.text.set_text(text);
+        self.document = document;
+        self.foreign_swap = self.document.newer_swap();
+        self.apply_file_settings(filetypes);
+        if self.text.is_modified() {
+            // Mixed line endings were converted, that is part of opening the file and not an edit to undo
+            let text = self.text.snapshot();
+            self.text.set_text(text);
+        }
+        self.mark_loaded();
+    }
+
+    /// Points the buffer at a new file, as when saving under another name
+    pub fn set_path(&mut self, path: PathBuf, filetypes: &[Section]) {
+        self.document.set_path(path);
+        self.foreign_swap = self.document.newer_swap();
+        self.apply_file_settings(filetypes);
+    }
+
+    /// Writes the buffer to a new file and points it there, returns the number of bytes written
+    ///
+    /// If writing fails the buffer stays on its old file and that file's settings
+    pub fn save_as(&mut self, path: PathBuf, options: &SaveOptions, filetypes: &[Section]) -> io::Result<usize> {
+        let (old_document, old_foreign_swap) = (self.document.clone(), self.foreign_swap.clone());
+        self.set_path(path, filetypes);
+        let result = self.save(options);
+        if result.is_err() {
+            self.document = old_document;
+            self.foreign_swap = old_foreign_swap;
+            self.apply_file_settings(filetypes);
+        }
+        result
+    }
+
+    /// Rereads the file from disk, keeping the cursor where it was
+    pub fn reload(&mut self, filetypes: &[Section]) -> io::Result<()> {
+        let Some(path) = self.document.path() else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "Document has no file path"));
+        };
+        let (document, text) = Document::open(path)?;
+        self.text.replace_text(text.slice(..).to_string());
+        self.document = document;
+        self.apply_file_settings(filetypes);
+        self.mark_loaded();
+        Ok(())
+    }
+
+    /// Writes the buffer to its file, returns the number of bytes written
+    ///
+    /// Trailing whitespace and the final newline are fixed first if the file settings ask for it
+    pub fn save(&mut self, options: &SaveOptions) -> io::Result<usize> {
+        let text = self.text.export();
+        let tidied = self.settings.tidy(&text);
+        if tidied != text {
+            self.text.replace_text(tidied);
+        }
+        let bytes = self.document.save(self.text.export(), options)?;
+        self.text.mark_saved();
+        self.discard_swap();
+        Ok(bytes)
+    }
+
+    /// Converts the line endings written on save, a file with mixed endings loses its '\r's
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if self.document.line_ending() == LineEnding::MIXED {
+            let text = self.text.export();
+            self.text.replace_text(text.replace("\r\n", "\n"));
+        }
+        self.document.set_line_ending(line_ending);
+    }
+
+    /// Replaces the text with the contents of a swap file left behind by a crash
+    pub fn recover(&mut self, swap_path: &Path) -> io::Result<()> {
+        let text = Document::read_swap(swap_path)?;
+        self.text.replace_text(text);
+        self.foreign_swap = None;
+        Ok(())
+    }
+
+    /// The swap file left behind by a crash, while it waits to be recovered or deleted
+    pub fn foreign_swap(&self) -> Option<&Path> {
+        self.foreign_swap.as_deref()
+    }
+
+    /// Deletes the swap file left behind by a crash, edits made since are written to a new one
+    pub fn delete_foreign_swap(&mut self) -> io::Result<()> {
+        self.document.remove_swap()?;
+        self.foreign_swap = None;
+        Ok(())
+    }
+
+    /// Writes the swap file if the text changed since the last write, removes it once nothing is unsaved
+    ///
+    /// The write runs on another thread from a snapshot of the text, so editing goes on while it does
+    pub fn update_swap(&mut self) -> io::Result<()> {
+        if self.swap_write.as_ref().is_some_and(|(_, write)| !write.is_finished()) {
+            return Ok(());
+        }
+        self.finish_swap_write()?;
+        if self.document.path().is_none() || self.foreign_swap.is_some() {
+            return Ok(());
+        }
+        if !self.is_modified() {
+            self.discard_swap();
+            return Ok(());
+        }
+        let revision = self.text.revision();
+        if self.swap_revision == Some(revision) {
+            return Ok(());
+        }
+        self.swap_write = self.document.write_swap(self.text.snapshot()).map(|write| (revision, write));
+        Ok(())
+    }
+
+    /// Waits for the swap file being written, if any, and notes the revision it holds once it is on disk
+    fn finish_swap_write(&mut self) -> io::Result<()> {
+        let Some((revision, write)) = self.swap_write.take() else {
+            return Ok(());
+        };
+        write.join().unwrap_or_else(|_| Err(io::Error::other("Swap file writer panicked")))?;
+        self.swap_revision = Some(revision);
+        Ok(())
+    }
+
+    /// Picks the syntax highlighting grammar from the file extension, and the indentation, line endings
+    /// and encoding from the user's per file type settings and any `.editorconfig` above the file
+    ///
+    /// Indentation chosen with `:set` comes first, what is still open is guessed from the text
+    pub fn apply_file_settings(&mut self, filetypes: &[Section]) {
+        let path = self.document.path();
+        let language = path.and_then(Language::from_path);
+        let mut settings = path.map_or_else(FileSettings::default, |path| FileSettings::for_path(path, filetypes));
+        let tab_width = settings.tab_width.flatten().or(settings.indent_size.flatten());
+        settings.merge(&self.chosen);
+        let mut indent_style = settings.indent_style.flatten();
+        let mut indent_size = settings.indent_size.flatten();
+        if (indent_style.is_none() || indent_size.is_none())
+            && let Some((style, size)) = tabs::detect_indent(self.text.lines())
+            && indent_style.is_none_or(|own_style| own_style == style) {
+            indent_style = Some(style);
+            indent_size = indent_size.or(size);
+        }
+        if let Some(line_ending) = settings.end_of_line.flatten() {
+            self.set_line_ending(line_ending);
+        }
+        if let Some(encoding) = settings.charset.flatten() {
+            self.document.set_encoding(encoding);
+        }
+        self.text.set_language(language);
+        self.text.set_indent(indent_style, indent_size, tab_width);
+        self.settings = settings;
+    }
+
+    /// Overrides the indentation for this buffer with the `indent_style` or `indent_size` set in chosen
+    pub fn choose_indent(&mut self, chosen: &FileSettings, filetypes: &[Section]) {
+        self.chosen.merge(chosen);
+        self.apply_file_settings(filetypes);
+    }
+
+    /// Matches the text just read from disk, the file settings applied while loading it take effect on the next save
+    fn mark_loaded(&mut self) {
+        self.text.mark_saved();
+        self.document.clear_format_changed();
+    }
+
+    /// Removes the swap file if this editor wrote one
+    pub fn discard_swap(&mut self) {
+        // A write still running would bring the file back after it is removed
+        let written = self.finish_swap_write().is_ok();
+        if (self.swap_revision.take().is_some() || !written) && self.foreign_swap.is_none() {
+            _ = self.document.remove_swap();
+        }
+    }
+}
\ No newline at end of file
@@ -1,18 +1,38 @@
 use std::path::PathBuf;
 
-use crate::editor::textbox::TextBox;
+use crate::editor::{layout::SplitDirection, textbox::TextBox};
 
 mod parse;
 
 #[derive(Clone)]
 pub enum Command {
     ERROR,
-    QUIT,
-    WRITE(PathBuf),
-    OPEN(PathBuf),
+    /// `true` for `:q!`, which skips the unsaved changes check
+    QUIT(bool),
+    WRITE(Option<PathBuf>),
+    /// Opens the file in a new buffer, `true` for `:o!`, which opens it in the current buffer and drops its unsaved changes
+    OPEN(PathBuf, bool),
+    /// `:e!`, rereads the file from disk and drops unsaved changes
+    RELOAD,
     JUMP(u32, u32),
     RUN(String, Vec<String>),
     FIND(Option<String>),
+    /// `:b N`, zero based
+    BUFFER(usize),
+    /// `:bn`
+    BNEXT,
+    /// `:bp`
+    BPREV,
+    /// `:ls`, lists the open buffers
+    LIST,
+    /// `:split` or `:vsplit`
+    SPLIT(SplitDirection),
+    /// `:close`, closes the focused pane
+    CLOSE,
+    /// `:set key=value`
+    SET(String, String),
+    /// `:theme name` switches the colors, `:theme` lists the themes
+    THEME(Option<String>),
     PREVIOUS,
 }
 
@@ -1,6 +1,6 @@
 use std::str::{FromStr, Split};
 
-use crate::editor::command::Command;
+use crate::editor::{command::Command, layout::SplitDirection};
 
 pub fn parse(cmd_str: String) -> Command {
     match cmd_str.chars().nth(0) {
@@ -14,12 +14,24 @@ pub fn parse_editor_cmd(cmd_str: &str) -> Command {
     let mut words = cmd_str.split(' ');
 
     match words.by_ref().next() {
-        Some("q") => Command::QUIT,
+        Some("q") => check_rem(words, Command::QUIT(false)),
+        Some("q!") => check_rem(words, Command::QUIT(true)),
         Some("j") => parse_jump_cmd(words),
         Some("w") => parse_write_cmd(words),
-        Some("o") => parse_open_cmd(words),
+        Some("o") => parse_open_cmd(words, false),
+        Some("o!") => parse_open_cmd(words, true),
+        Some("e!") => check_rem(words, Command::RELOAD),
         Some("r") => parse_run_cmd(words),
         Some("f") => parse_find_cmd(words),
+        Some("set") => parse_set_cmd(words),
+        Some("theme") => parse_theme_cmd(words),
+        Some("b") => parse_buffer_cmd(words),
+        Some("bn") => check_rem(words, Command::BNEXT),
+        Some("bp") => check_rem(words, Command::BPREV),
+        Some("ls") => check_rem(words, Command::LIST),
+        Some("split" | "sp") => check_rem(words, Command::SPLIT(SplitDirection::HORIZONTAL)),
+        Some("vsplit" | "vs") => check_rem(words, Command::SPLIT(SplitDirection::VERTICAL)),
+        Some("close") => check_rem(words, Command::CLOSE),
         _ => Command::ERROR,
     }
 }
@@ -48,23 +60,33 @@ fn parse_jump_cmd(mut words: Split<char>) -> Command {
 }
 
 fn parse_write_cmd(mut words: Split<char>) -> Command {
+    let path_buf = words.next()
+        .filter(|file_path| !file_path.is_empty())
+        .map(|file_path| file_path.into());
+    let cmd = Command::WRITE(path_buf);
+
+    check_rem(words, cmd)
+}
+
+fn parse_open_cmd(mut words: Split<char>, force: bool) -> Command {
     let Some(file_path) = words.next() else {
         return Command::ERROR;
     };
 
     let path_buf = file_path.into();
-    let cmd = Command::WRITE(path_buf);
+    let cmd = Command::OPEN(path_buf, force);
 
     check_rem(words, cmd)
 }
 
-fn parse_open_cmd(mut words: Split<char>) -> Command {
-    let Some(file_path) = words.next() else {
+fn parse_buffer_cmd(mut words: Split<char>) -> Command {
+    let Ok(Some(buffer_num)) = parse_num_arg::<usize>(&mut words) else {
         return Command::ERROR;
     };
-
-    let path_buf = file_path.into();
-    let cmd = Command::OPEN(path_buf);
+    let Some(buffer_num_min_one) = buffer_num.checked_sub(1) else {
+        return Command::ERROR;
+    };
+    let cmd = Command::BUFFER(buffer_num_min_one);
 
     check_rem(words, cmd)
 }
@@ -86,6 +108,27 @@ fn parse_find_cmd(mut words: Split<char>) -> Command {
     check_rem(words, cmd)
 }
 
+fn parse_set_cmd(mut words: Split<char>) -> Command {
+    let Some((key, value)) = words.next().and_then(|setting| setting.split_once('=')) else {
+        return Command::ERROR;
+    };
+    if key.is_empty() {
+        return Command::ERROR;
+    }
+    let cmd = Command::SET(key.to_string(), value.to_string());
+
+    check_rem(words, cmd)
+}
+
+fn parse_theme_cmd(mut words: Split<char>) -> Command {
+    let name = words.next()
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string());
+    let cmd = Command::THEME(name);
+
+    check_rem(words, cmd)
+}
+
 /// Helpers
 fn parse_num_arg<T>(words: &mut Split<char>) -> Result<Option<T>, ()>
 where T: FromStr {
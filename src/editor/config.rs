@@ -0,0 +1,309 @@
+use std::{env, fmt::Display, fs, io, path::PathBuf, str::FromStr, time::{Duration, SystemTime}};
+
+use crate::editor::{editorconfig::{IndentStyle, Section}, gutter::LineNumbers};
+
+const APP_DIR: &str = "text_editor";
+const CONFIG_FILE: &str = "config.toml";
+
+/// Settings read from `config.toml` in the config directory, and changed at runtime with `:set key=value`
+///
+/// The file holds `key = value` lines, `#` starts a comment and string values may be quoted.
+/// Sections like `[*.md]` hold EditorConfig properties for the files matching their glob
+#[derive(Clone, PartialEq, Debug)]
+pub struct Config {
+    /// Path of the font file, it wins over font_family
+    pub font: Option<PathBuf>,
+    /// Font family looked for in the platform font directories before the built in ones
+    pub font_family: Option<String>,
+    pub font_size: f32,
+    pub min_font_size: f32,
+    pub max_font_size: f32,
+    /// Points added or removed by one zoom in or out
+    pub zoom_step: f32,
+    /// Space between the edge of a box and its text
+    pub text_padding: u32,
+    /// Space between two lines
+    pub line_padding: u32,
+    /// Columns between tab stops
+    pub tab_width: u32,
+    /// Whether Tab inserts a tab character or spaces, a file's own indentation wins
+    pub indent_style: IndentStyle,
+    /// Lines moved by one step of the mouse wheel
+    pub scroll_lines: usize,
+    pub blink_period: Duration,
+    /// Only read at startup, a change at runtime resizes the window
+    pub window_width: u32,
+    pub window_height: u32,
+    pub theme: String,
+    pub wrap: bool,
+    pub line_numbers: LineNumbers,
+    /// Keep the previous version of a file as `file~` when saving
+    pub backup: bool,
+    /// Per file type settings, `.editorconfig` files override them
+    pub filetypes: Vec<Section>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            font: None,
+            font_family: None,
+            font_size: 24.0,
+            min_font_size: 12.0,
+            max_font_size: 126.0,
+            zoom_step: 2.0,
+            text_padding: 16,
+            line_padding: 2,
+            tab_width: 4,
+            indent_style: IndentStyle::SPACE,
+            scroll_lines: 8,
+            blink_period: Duration::from_millis(500),
+            window_width: 800,
+            window_height: 600,
+            theme: String::from("dark"),
+            wrap: false,
+            line_numbers: LineNumbers::default(),
+            backup: false,
+            filetypes: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    pub const MIN_WINDOW_WIDTH: u32 = 400;
+    pub const MIN_WINDOW_HEIGHT: u32 = 400;
+
+    pub fn path() -> Option<PathBuf> {
+        config_dir().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// Reads the config file, a missing file gives the defaults
+    ///
+    /// Settings that are not valid keep their defaults, the returned errors say which ones and why
+    pub fn load() -> (Self, Vec<String>) {
+        let Some(path) = Self::path() else {
+            return (Self::default(), Vec::new());
+        };
+        match fs::read_to_string(&path) {
+            Ok(source) => {
+                let (config, errors) = Self::parse(&source);
+                let errors = errors.into_iter().map(|err| format!("{}: {err}", path.display())).collect();
+                (config, errors)
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (Self::default(), Vec::new()),
+            Err(err) => (Self::default(), vec![format!("Could not read {}: {err}", path.display())]),
+        }
+    }
+
+    /// When the config file was last written, None if there is none
+    pub fn modified() -> Option<SystemTime> {
+        fs::metadata(Self::path()?).and_then(|metadata| metadata.modified()).ok()
+    }
+
+    pub fn parse(source: &str) -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(pattern) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                config.filetypes.push(Section::new(pattern.trim()));
+                continue;
+            }
+            let result = match (line.split_once('='), config.filetypes.last_mut()) {
+                (Some((key, value)), Some(section)) => section.settings.set(key.trim(), unquote(value.trim())),
+                (Some((key, value)), None) => config.set(key.trim(), unquote(value.trim())),
+                (None, _) => Err(String::from("expected key = value")),
+            };
+            if let Err(err) = result {
+                errors.push(format!("line {}: {err}", i + 1));
+            }
+        }
+        if let Err(err) = config.check() {
+            errors.push(err);
+            let default = Self::default();
+            config.min_font_size = default.min_font_size;
+            config.max_font_size = default.max_font_size;
+            config.font_size = config.font_size.clamp(default.min_font_size, default.max_font_size);
+        }
+        (config, errors)
+    }
+
+    /// Changes one setting, nothing changes if the value is not valid
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "font" => self.font = (!value.is_empty()).then(|| PathBuf::from(value)),
+            "font_family" => self.font_family = (!value.is_empty()).then(|| value.to_string()),
+            "font_size" => self.font_size = parse_number(key, value, 4.0, 400.0)?,
+            "min_font_size" => self.min_font_size = parse_number(key, value, 4.0, 400.0)?,
+            "max_font_size" => self.max_font_size = parse_number(key, value, 4.0, 400.0)?,
+            "zoom_step" => self.zoom_step = parse_number(key, value, 0.5, 50.0)?,
+            "text_padding" => self.text_padding = parse_number(key, value, 0, 200)?,
+            "line_padding" => self.line_padding = parse_number(key, value, 0, 100)?,
+            "tab_width" | "ts" => self.tab_width = parse_number(key, value, 1, 16)?,
+            "indent_style" => {
+                self.indent_style = IndentStyle::from_name(value)
+                    .ok_or_else(|| format!("Expected space or tab for indent_style, got \"{value}\""))?;
+            },
+            "scroll_lines" => self.scroll_lines = parse_number(key, value, 1, 100)?,
+            "blink_ms" => self.blink_period = Duration::from_millis(parse_number(key, value, 100, 5000)?),
+            "window_width" => self.window_width = parse_number(key, value, Self::MIN_WINDOW_WIDTH, 16384)?,
+            "window_height" => self.window_height = parse_number(key, value, Self::MIN_WINDOW_HEIGHT, 16384)?,
+            "theme" if value.is_empty() => return Err(String::from("theme needs a name")),
+            "theme" => self.theme = value.to_string(),
+            "wrap" => self.wrap = parse_switch(key, value)?,
+            "backup" => self.backup = parse_switch(key, value)?,
+            "nu" | "number" => {
+                self.line_numbers = LineNumbers::from_name(value)
+                    .ok_or_else(|| format!("Unknown line numbering \"{value}\", expected off, absolute, relative or hybrid"))?;
+            },
+            _ => return Err(format!("Unknown setting \"{key}\"")),
+        }
+        Ok(())
+    }
+
+    /// Checks the settings that depend on each other
+    pub fn check(&self) -> Result<(), String> {
+        if self.min_font_size > self.max_font_size {
+            return Err(String::from("min_font_size must not be larger than max_font_size"));
+        }
+        if !(self.min_font_size..=self.max_font_size).contains(&self.font_size) {
+            return Err(format!("font_size must be between {} and {}", self.min_font_size, self.max_font_size));
+        }
+        Ok(())
+    }
+}
+
+/// Directory holding the user's settings and themes, `~/.config/text_editor` outside of Windows
+pub fn config_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        return env::var_os("APPDATA").map(|app_data| PathBuf::from(app_data).join(APP_DIR));
+    }
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join(APP_DIR))
+}
+
+/// Cuts the line at the first `#` outside of quotes
+pub fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+pub fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|value| value.strip_suffix('"')).unwrap_or(value)
+}
+
+fn parse_number<T: FromStr + PartialOrd + Display + Copy>(key: &str, value: &str, min: T, max: T) -> Result<T, String> {
+    let number = value.parse::<T>().map_err(|_| format!("Expected a number for {key}, got \"{value}\""))?;
+    // Written as contains so NaN, which is neither below nor above the range, is turned down too
+    if !(min..=max).contains(&number) {
+        return Err(format!("{key} must be between {min} and {max}, got {number}"));
+    }
+    Ok(number)
+}
+
+fn parse_switch(key: &str, value: &str) -> Result<bool, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "on" | "true" | "yes" | "1" => Ok(true),
+        "off" | "false" | "no" | "0" => Ok(false),
+        _ => Err(format!("Expected on or off for {key}, got \"{value}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let source = "\
+            # Bigger text, light colors\n\
+            font_size = 30\n\
+            theme = \"light\" # bundled\n\
+            \n\
+            wrap = on\n\
+            nu = relative\n\
+            blink_ms = 800\n";
+        let (config, errors) = Config::parse(source);
+        assert!(errors.is_empty());
+        assert_eq!(config.font_size, 30.0);
+        assert_eq!(config.theme, "light");
+        assert!(config.wrap);
+        assert_eq!(config.line_numbers, LineNumbers::RELATIVE);
+        assert_eq!(config.blink_period, Duration::from_millis(800));
+        assert_eq!(config.tab_width, Config::default().tab_width);
+    }
+
+    #[test]
+    fn filetype_test() {
+        let source = "\
+            tab_width = 8\n\
+            [*.md]\n\
+            trim_trailing_whitespace = false\n\
+            [Makefile]\n\
+            indent_style = tab\n\
+            wrap = on\n";
+        let (config, errors) = Config::parse(source);
+        assert_eq!(errors, vec!["line 6: Unknown file setting \"wrap\""]);
+        assert_eq!(config.tab_width, 8);
+        assert_eq!(config.filetypes.len(), 2);
+        assert!(config.filetypes[0].matches("docs/README.md"));
+        assert_eq!(config.filetypes[0].settings.trim_trailing_whitespace, Some(Some(false)));
+        assert_eq!(config.filetypes[1].settings.indent_style, Some(Some(IndentStyle::TAB)));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let source = "tab_width = 0\nwrap = maybe\nfont_size = 20\nfont_weight = bold\nscroll_lines\n";
+        let (config, errors) = Config::parse(source);
+        assert_eq!(errors, vec![
+            "line 1: tab_width must be between 1 and 16, got 0",
+            "line 2: Expected on or off for wrap, got \"maybe\"",
+            "line 4: Unknown setting \"font_weight\"",
+            "line 5: expected key = value",
+        ]);
+        assert_eq!(config.tab_width, Config::default().tab_width);
+        assert_eq!(config.font_size, 20.0);
+
+        let (config, errors) = Config::parse("min_font_size = 40\nmax_font_size = 30");
+        assert_eq!(errors, vec!["min_font_size must not be larger than max_font_size"]);
+        assert!(config.check().is_ok());
+    }
+
+    #[test]
+    fn font_test() {
+        let (mut config, errors) = Config::parse("font = \"/usr/share/fonts/Fira Code.ttf\"\nfont_family = Fira Code\n");
+        assert!(errors.is_empty());
+        assert_eq!(config.font, Some(PathBuf::from("/usr/share/fonts/Fira Code.ttf")));
+        assert_eq!(config.font_family.as_deref(), Some("Fira Code"));
+        assert!(config.set("font", "").is_ok());
+        assert_eq!(config.font, None);
+    }
+
+    #[test]
+    fn set_test() {
+        let mut config = Config::default();
+        assert!(config.set("ts", "8").is_ok());
+        assert_eq!(config.tab_width, 8);
+        assert!(config.set("indent_style", "tab").is_ok());
+        assert_eq!(config.indent_style, IndentStyle::TAB);
+        assert!(config.set("font_size", "big").is_err());
+        assert!(config.set("font_size", "nan").is_err());
+        assert!(config.set("zoom_step", "inf").is_err());
+        assert_eq!(config.font_size, Config::default().font_size);
+        assert!(config.set("font_size", "200").is_ok());
+        assert!(config.check().is_err());
+    }
+}
\ No newline at end of file
@@ -1,20 +1,22 @@
 use sdl3::{pixels::Color, render::{Canvas, FPoint}, video::Window};
 
-use crate::{editor::{inputstate::InputState, textrope::TextRope, windowstate::WindowState}, vector::Vector2D};
+use crate::{editor::{inputstate::InputState, tabs, textrope::TextRope, windowstate::WindowState, wrap}, vector::Vector2D};
 use std::{error::Error, time::{Duration, Instant}, u32, usize};
 
-const DEFAULT_BLINK_PERIOD: Duration = Duration::from_millis(500);
-const DEFAULT_CURSOR_COLOR: Color = crate::editor::textbox::DEFAULT_FONT_COLOR;
 
+#[derive(Clone)]
 pub struct Cursor {
     pos: Vector2D,
     select_start_pos: Option<Vector2D>,
+    /// Column on screen kept while moving up and down through lines of different lengths
     snap_x: u32,
-    blink_period: Duration,
     blink_timer: Instant,
-    color: Color,
     blink_on: bool,
     tampered_flag: bool,
+    /// Line clicked in the gutter, dragging from there selects whole lines
+    line_anchor: Option<u32>,
+    /// Column on screen kept while moving up and down through wrapped rows
+    wrap_col: Option<u32>,
 }
 
 impl Cursor {
@@ -45,13 +47,13 @@ impl Cursor {
     }
 
     pub fn jump_to(&mut self, x: u32, y: u32, input: &InputState, text_data: &TextRope, window: &mut WindowState) {
-        self.snap_x = x;
+        self.snap_x = col_at(x, y, text_data, window);
         self.reset_select_pos(input);
         self.move_to(x, y, window, text_data)
     }
 
     pub fn text_jump_to(&mut self, x: u32, y: u32, text_data: &TextRope, window: &mut WindowState) {
-        self.snap_x = x;
+        self.snap_x = col_at(x, y, text_data, window);
         self.select_start_pos = None;
         self.move_to_no_tamper_flag(x, y, window, text_data)
     }
@@ -63,14 +65,14 @@ impl Cursor {
     }
 
     pub fn text_shift_x(&mut self, amt: isize, text_data: &TextRope, window: &mut WindowState) {
-        let (new_x, new_y) = self.align_x(amt, text_data);
+        let (new_x, new_y) = self.align_x(amt, text_data, window);
         self.select_start_pos = None;
         self.move_to_no_tamper_flag(new_x, new_y, window, text_data)
     }
 
     pub fn shift_x(&mut self, amt: isize, input: &InputState, text_data: &TextRope, window: &mut WindowState) {
         let (new_x, new_y) = match (input.keyboard.ctrl_down(), input.keyboard.shift_down(), self.select_start_pos()) {
-            (true, ..) => self.align_word_x(amt, text_data),
+            (true, ..) => self.align_word_x(amt, text_data, window),
             (_, false, Some(select_start_pos)) => {
                 if amt >= 0 {
                     select_start_pos.max(self.pos).into()
@@ -78,49 +80,62 @@ impl Cursor {
                     select_start_pos.min(self.pos).into()
                 }
             }
-            _ => self.align_x(amt, text_data),
+            _ => self.align_x(amt, text_data, window),
         };
         self.reset_select_pos(input);
         self.move_to(new_x, new_y, window, text_data)
     }
 
     pub fn shift_y(&mut self, amt: isize, input: &InputState, text_data: &TextRope, window: &mut WindowState) {
-        let (new_x, new_y) = self.align_y(amt, text_data);
         self.reset_select_pos(input);
-        self.move_to(new_x, new_y, window, text_data)
+        if window.is_wrapped() {
+            let (new_x, new_y, col) = self.align_wrapped_y(amt, text_data, window);
+            self.move_to(new_x, new_y, window, text_data);
+            self.wrap_col = Some(col);
+        } else {
+            let (new_x, new_y) = self.align_y(amt, text_data, window);
+            self.move_to(new_x, new_y, window, text_data)
+        }
     }
 
     pub fn mouse_move(&mut self, click_x: f32, click_y: f32, input: &InputState, text_data: &TextRope, window: &mut WindowState) {
-        if input.mouse.left_down() {
+        if !input.mouse.left_down() {
+            return;
+        }
+        if let Some(anchor) = self.line_anchor {
+            let (_, line_num) = snap_click_pos(click_x, click_y, window, text_data);
+            self.select_lines(anchor, line_num as u32, text_data, window);
+        } else {
             self.jump_to_mouse(click_x, click_y, text_data, window);
         }
     }
 
     pub fn select_all(&mut self, text_data: &TextRope, window: &mut WindowState) {
-        self.select_start_pos = Some(Vector2D::new(0, 0));
         let last_line_index = text_data.line_count() - 1;
-        let last_line = text_data.lines().nth(last_line_index).unwrap();
-        let last_line_len = last_line.chars().count();
+        let Some(last_line_len) = text_data.line_len(last_line_index) else {
+            return;
+        };
+        self.select_start_pos = Some(Vector2D::new(0, 0));
         self.move_to_no_adjust(last_line_len as u32, last_line_index as u32, window);
     }
 
-    pub fn draw(&mut self, active: bool, canvas: &mut Canvas<Window>, window: &WindowState) -> Result<(), Box<dyn Error>> {
+    pub fn draw(&mut self, active: bool, canvas: &mut Canvas<Window>, window: &WindowState, text_data: &TextRope, color: Color) -> Result<(), Box<dyn Error>> {
         if !active || !self.blink_on {
             return Ok(());
         }
 
-        let Some(Vector2D {x: shifted_x, y: shifted_y}) = window.in_screen_bound(self.pos.x, self.pos.y) else {
+        let Some(Vector2D {x: shifted_x, y: shifted_y}) = window.screen_pos(self.pos.x, self.pos.y, text_data) else {
             return Ok(());
         };
 
-        canvas.set_draw_color(self.color);
+        canvas.set_draw_color(color);
         let (text_pad, line_pad) = window.get_padding();
         let pos = window.pos();
         let text_pad = text_pad as f32;
         let line_pad = line_pad as f32;
         let (width, height) = window.get_text_dim();
 
-        let x = shifted_x as f32 * width + text_pad + pos.x as f32;
+        let x = shifted_x as f32 * width + text_pad + (pos.x + window.gutter_width()) as f32;
         let y = shifted_y as f32 * (height + line_pad) + text_pad + pos.y as f32;
 
         let start = FPoint::new(x, y);
@@ -130,14 +145,20 @@ impl Cursor {
         Ok(())
     }
 
-    pub fn update(&mut self, window: &mut WindowState) {
-        if self.blink_timer.elapsed() > self.blink_period {
+    pub fn update(&mut self, window: &mut WindowState, blink_period: Duration) {
+        if self.blink_timer.elapsed() >= blink_period {
             self.blink_on = !self.blink_on;
             self.blink_timer = Instant::now();
-            window.set_render_flag()
+            let line = self.pos.y as usize;
+            window.damage_lines(line, line);
         }
     }
 
+    /// When the cursor next turns on or off
+    pub fn next_blink(&self, blink_period: Duration) -> Instant {
+        self.blink_timer + blink_period
+    }
+
     pub fn home(&mut self, input: &InputState, text_data: &TextRope, window: &mut WindowState) {
         let y = if input.keyboard.ctrl_down() {
             0
@@ -149,7 +170,7 @@ impl Cursor {
 
     pub fn snap_to_pos(&mut self, new_x: u32, new_y: u32, text_data: &TextRope, window: &mut WindowState) {
         let new_y = new_y.min(text_data.line_count() as u32 - 1);
-        let new_x = new_x.min(text_data.lines().nth(new_y as usize).unwrap().chars().count() as u32);
+        let new_x = new_x.min(text_data.line_len(new_y as usize).unwrap_or(0) as u32);
         self.select_start_pos = None;
         self.move_to(new_x, new_y, window, text_data)
     }
@@ -157,6 +178,7 @@ impl Cursor {
 
 impl Cursor {
     pub fn left_click_press(&mut self, click_x: f32, click_y: f32, clicks: u8, text_data: &TextRope, window: &mut WindowState) {
+        self.line_anchor = None;
         match clicks {
             1 => {
                 self.jump_to_mouse(click_x, click_y, text_data, window);
@@ -168,7 +190,9 @@ impl Cursor {
             },
             3 => {
                 let (_, line_num) = snap_click_pos(click_x, click_y, window, text_data);
-                let line_len = text_data.lines().nth(line_num).unwrap().chars().count();
+                let Some(line_len) = text_data.line_len(line_num) else {
+                    return;
+                };
                 self.select_start_pos = Some(Vector2D::new(0, line_num as u32));
                 self.move_to(line_len as u32, line_num as u32, window, text_data);
             },
@@ -176,6 +200,13 @@ impl Cursor {
         }
     }
 
+    /// Selects the clicked line, dragging afterwards extends the selection by whole lines
+    pub fn gutter_click(&mut self, click_x: f32, click_y: f32, text_data: &TextRope, window: &mut WindowState) {
+        let (_, line_num) = snap_click_pos(click_x, click_y, window, text_data);
+        self.line_anchor = Some(line_num as u32);
+        self.select_lines(line_num as u32, line_num as u32, text_data, window);
+    }
+
     pub fn select_around_cursor(&mut self, text_data: &TextRope, window: &mut WindowState) {
         let Vector2D{x: char_num, y: line_num} = self.pos;
         self.select_word_or_chunk(line_num, char_num, text_data, window);
@@ -190,13 +221,14 @@ impl Cursor {
 
     fn jump_to_mouse(&mut self, mouse_x: f32, mouse_y: f32, text_data: &TextRope, window: &mut WindowState) {
         let (new_x, new_y) = snap_click_pos(mouse_x, mouse_y, window, text_data);
-        self.snap_x = new_x as u32;
+        self.snap_x = col_at(new_x as u32, new_y as u32, text_data, window);
 
         self.move_to(new_x as u32, new_y as u32, window, text_data)
     }
 
     fn move_to(&mut self, x: u32, y: u32, window: &mut WindowState, text_data: &TextRope) {
         self.tampered_flag = true;
+        self.wrap_col = None;
         window.adjust_focus(x as usize, y as usize, text_data);
         self.pos.x = x;
         self.pos.y = y;
@@ -205,18 +237,40 @@ impl Cursor {
 
     fn move_to_no_adjust(&mut self, x: u32, y: u32, window: &mut WindowState) {
         self.tampered_flag = true;
+        self.wrap_col = None;
         self.pos.x = x;
         self.pos.y = y;
         window.set_render_flag();
         self.reset_blink()
     }
     fn move_to_no_tamper_flag(&mut self, x: u32, y: u32, window: &mut WindowState, text_data: &TextRope) {
+        self.wrap_col = None;
         window.adjust_focus(x as usize, y as usize, text_data);
         self.pos.x = x;
         self.pos.y = y;
         self.reset_blink()
     }
 
+    /// Selects every line from anchor to line, the cursor ends on line's side of the selection
+    fn select_lines(&mut self, anchor: u32, line: u32, text_data: &TextRope, window: &mut WindowState) {
+        let last_line = anchor.max(line).min(text_data.line_count() as u32 - 1);
+        let after_last = if last_line as usize + 1 < text_data.line_count() {
+            Vector2D::new(0, last_line + 1)
+        } else {
+            let line_len = text_data.line_len(last_line as usize).unwrap_or(0);
+            Vector2D::new(line_len as u32, last_line)
+        };
+        let before_first = Vector2D::new(0, anchor.min(line));
+        let (select_start, end) = if line >= anchor {
+            (before_first, after_last)
+        } else {
+            (after_last, before_first)
+        };
+        self.select_start_pos = Some(select_start);
+        self.snap_x = col_at(end.x, end.y, text_data, window);
+        self.move_to(end.x, end.y, window, text_data);
+    }
+
     fn reset_select_pos(&mut self, input: &InputState) {
         if !input.keyboard.shift_down() {
             self.select_start_pos = None;
@@ -228,38 +282,34 @@ impl Cursor {
     }
 
     /// Returns (new_x, new_y)
-    fn align_x(&mut self, amt: isize, text_data: &TextRope) -> (u32, u32) {
+    fn align_x(&mut self, amt: isize, text_data: &TextRope, window: &WindowState) -> (u32, u32) {
         let shifted_x = (self.pos.x as isize).saturating_add(amt);
         let mut new_x = shifted_x as u32;
         let mut new_y = self.pos.y;
         if shifted_x < 0 {
             if let Some(shifted_y) = self.pos.y.checked_sub(1) {
                 new_y = shifted_y;
-                new_x = text_data.lines()
-                    .nth(new_y as usize)
-                    .map(|line_str| line_str.chars().count())
-                    .unwrap_or(0) as u32;
+                new_x = text_data.line_len(new_y as usize).unwrap_or(0) as u32;
             } else {
                 new_x = 0;
             }
         } else {
-            let mut line_iter = text_data.lines().skip(new_y as usize);
-            let mut line_len = line_iter.next().map(|line_str| line_str.chars().count() as u32).unwrap();
+            let mut line_len = text_data.line_len(new_y as usize).unwrap_or(0) as u32;
             while new_x > line_len {
-                let Some(next_line_len) = line_iter.next().map(|line_str| line_str.chars().count() as u32) else {
+                if new_y as usize + 1 >= text_data.line_count() {
                     new_x = line_len;
                     break;
-                };
+                }
                 new_y += 1;
                 new_x -= line_len + 1;
-                line_len = next_line_len;
+                line_len = text_data.line_len(new_y as usize).unwrap_or(0) as u32;
             }
         };
-        self.snap_x = new_x;
+        self.snap_x = col_at(new_x, new_y, text_data, window);
         (new_x, new_y)
     }
 
-    fn align_word_x(&mut self, amt: isize, text_data: &TextRope) -> (u32, u32) {
+    fn align_word_x(&mut self, amt: isize, text_data: &TextRope, window: &WindowState) -> (u32, u32) {
         let (start_x, start_y) = self.pos.into();
         let (new_x, new_y) = if amt >= 0{
             match find_end_of_chunk(start_y, start_x, text_data) {
@@ -284,27 +334,71 @@ impl Cursor {
                 }
             }
         };
-        self.snap_x = new_x;
+        self.snap_x = col_at(new_x, new_y, text_data, window);
         (new_x, new_y)
     }
 
-    /// Returns (new_x, new_y)
-    fn align_y(&self, amt: isize, text_data: &TextRope) -> (u32, u32) {
+    /// Returns (new_x, new_y), the character under the kept column or the end of a shorter line
+    fn align_y(&self, amt: isize, text_data: &TextRope, window: &WindowState) -> (u32, u32) {
         let new_y = (self.pos.y as isize).saturating_add(amt).clamp(0, text_data.line_count() as isize  - 1) as u32;
-        let line_len = text_data.lines().nth(new_y as usize).unwrap().chars().count() as u32;
-        let new_x = self.pos.x.max(self.snap_x).min(line_len);
+        let Some(line_text) = text_data.line(new_y as usize) else {
+            return self.pos.into();
+        };
+        let col = col_at(self.pos.x, self.pos.y, text_data, window).max(self.snap_x);
+        let new_x = tabs::char_at(line_text.chars(), col as usize, window.tab_width()) as u32;
         (new_x, new_y)
     }
 
+    /// Returns (new_x, new_y, column) one wrapped row up or down per step of amt, keeping the column on screen
+    fn align_wrapped_y(&self, amt: isize, text_data: &TextRope, window: &WindowState) -> (u32, u32, u32) {
+        let (width, tab_width) = (window.chars(), window.tab_width());
+        let (x, mut y) = (self.pos.x as usize, self.pos.y as usize);
+        let Some(mut line_text) = text_data.line(y) else {
+            return (self.pos.x, self.pos.y, self.wrap_col.unwrap_or(0));
+        };
+        let mut starts = wrap::row_starts(line_text.chars(), width, tab_width);
+        let mut row = wrap::row_of(&starts, x);
+        let col = self.wrap_col.map_or_else(
+            || tabs::col_of(line_text.chars(), x, tab_width) - tabs::col_of(line_text.chars(), starts[row], tab_width),
+            |col| col as usize,
+        );
+
+        for _ in 0..amt.unsigned_abs() {
+            if amt < 0 && row > 0 {
+                row -= 1;
+            } else if amt < 0 && y > 0 && let Some(prev_line) = text_data.line(y - 1) {
+                y -= 1;
+                line_text = prev_line;
+                starts = wrap::row_starts(line_text.chars(), width, tab_width);
+                row = starts.len() - 1;
+            } else if amt > 0 && row + 1 < starts.len() {
+                row += 1;
+            } else if amt > 0 && let Some(next_line) = text_data.line(y + 1) {
+                y += 1;
+                line_text = next_line;
+                starts = wrap::row_starts(line_text.chars(), width, tab_width);
+                row = 0;
+            }
+        }
+
+        // The last character of a row that continues belongs to the row, not the one after it
+        let row_end = starts.get(row + 1).map_or(line_text.len(), |next_start| next_start - 1);
+        let row_col = tabs::col_of(line_text.chars(), starts[row], tab_width);
+        let new_x = tabs::char_at(line_text.chars(), row_col + col, tab_width).min(row_end);
+        (new_x as u32, y as u32, col as u32)
+    }
+
     fn select_word_or_chunk(&mut self, line_num: u32, char_num: u32, text_data: &TextRope, window: &mut WindowState) {
-        let line_text = text_data.lines().nth(line_num as usize).unwrap();
+        let Some(line_text) = text_data.line(line_num as usize) else {
+            return;
+        };
         
         let mut first_left_space: Option<usize> = None;
         let mut first_alpha_space: Option<usize> = None;
         let mut first_left_symbol: Option<usize> = None;
         let mut char_iter = line_text.chars().enumerate();
         for (i, c) in char_iter.by_ref().take(char_num as usize) {
-            if c == ' ' {
+            if is_space(c) {
                 first_left_space = first_left_space.or(Some(i));
                 first_alpha_space = None;
                 first_left_symbol = None;
@@ -319,9 +413,9 @@ impl Cursor {
             }
         }
         let (target_offset, target_char) = char_iter.by_ref().next().map_or((0, ' '), |(_, c)| (1, c));
-        let (start_x, end_x) = if target_char == ' ' {
+        let (start_x, end_x) = if is_space(target_char) {
             let last_space_index = char_iter.by_ref()
-                .take_while(|&(_, c)| c == ' ')
+                .take_while(|&(_, c)| is_space(c))
                 .last()
                 .map_or(
                     (char_num + target_offset) as usize,
@@ -356,11 +450,11 @@ impl Default for Cursor {
             },
             select_start_pos: None,
             snap_x: 0,
-            blink_period: DEFAULT_BLINK_PERIOD,
-            color: DEFAULT_CURSOR_COLOR,
             blink_timer: Instant::now(),
             blink_on: true,
             tampered_flag: false,
+            line_anchor: None,
+            wrap_col: None,
         }
     }
 }
@@ -369,7 +463,7 @@ impl Default for Cursor {
 fn snap_click_pos(mouse_x: f32, mouse_y: f32, window: &WindowState, text_data: &TextRope) -> (usize, usize) {
     let pos = window.pos();
 
-    let mouse_x = mouse_x - pos.x as f32;
+    let mouse_x = mouse_x - (pos.x + window.gutter_width()) as f32;
     let mouse_y = mouse_y - pos.y as f32;
 
     let (text_pad, line_pad) = window.get_padding();
@@ -377,26 +471,38 @@ fn snap_click_pos(mouse_x: f32, mouse_y: f32, window: &WindowState, text_data: &
     let line_pad = line_pad as f32;
     let (width, height) = window.get_text_dim();
 
-    let new_x = (mouse_x - text_pad + width / 2.0) / width;
-    let new_x = new_x.max(0.0) as usize + window.get_first_char();
-    let new_y = (mouse_y - text_pad) / (height + line_pad);
-    let new_y = new_y.max(0.0) as usize + window.get_first_line();
+    let col = ((mouse_x - text_pad) / width).max(0.0);
+    let screen_row = ((mouse_y - text_pad) / (height + line_pad)).max(0.0) as usize;
 
-    snap_pos(new_x, new_y, text_data)
+    if window.is_wrapped() {
+        let rows = window.rows(text_data);
+        let Some(row) = rows.get(screen_row).or(rows.last()) else {
+            return snap_pos(0.0, window.get_first_line(), text_data, window);
+        };
+        let row_end = if row.last { row.start + row.len } else { row.start + row.len - 1 };
+        let (new_x, new_y) = snap_pos(row.col as f32 + col, row.line, text_data, window);
+        return (new_x.clamp(row.start, row_end), new_y);
+    }
+
+    let new_col = col + window.get_first_col() as f32;
+    let new_y = screen_row + window.get_first_line();
+    snap_pos(new_col, new_y, text_data, window)
 }
 
 fn find_start_of_chunk(line_num: u32, start_char: u32, text_data: &TextRope) -> Result<u32, u32> {
     if start_char == 0 {
         return Err(0);
     }
-    let curr_line = text_data.lines().nth(line_num as usize).unwrap();
+    let Some(curr_line) = text_data.line(line_num as usize) else {
+        return Err(0);
+    };
     let (first_alpha, first_symbol, _) = curr_line
         .chars()
         .enumerate()
         .take(start_char as usize)
         .fold((None, None, None), |(first_alpha, first_symbol, last_space), (i, c)| {
             match c {
-                ' ' => (first_alpha, first_symbol, Some(i)),
+                c if is_space(c) => (first_alpha, first_symbol, Some(i)),
                 c if is_identifier(c) => (first_alpha.filter(|_| last_space.is_none()).or(Some(i)), None, None),
                 _ => (None, first_symbol.filter(|_| last_space.is_none()).or(Some(i)), None),
             }
@@ -405,17 +511,19 @@ fn find_start_of_chunk(line_num: u32, start_char: u32, text_data: &TextRope) ->
 }
 
 fn find_end_of_chunk(line_num: u32, start_char: u32, text_data: &TextRope) -> Result<u32, u32> {
-    let curr_line = text_data.lines().nth(line_num as usize).unwrap();
+    let Some(curr_line) = text_data.line(line_num as usize) else {
+        return Err(start_char);
+    };
     let mut char_iter = curr_line.chars().enumerate().skip(start_char as usize);
     let Some((target_char_index, target_char)) = char_iter.by_ref().next() else {
         return Err(start_char);
     };
 
     let end_index = match target_char {
-        ' ' => {
+        c if is_space(c) => {
             let after_space_skip = char_iter
                 .by_ref()
-                .filter(|&(_, c)| c != ' ')
+                .filter(|&(_, c)| !is_space(c))
                 .next()
                 .map_or((target_char_index, None), |(i, c)| (i, Some(c)));
             match after_space_skip {
@@ -440,10 +548,10 @@ fn find_end_of_chunk(line_num: u32, start_char: u32, text_data: &TextRope) -> Re
     Ok(end_index as u32 + 1)
 }
 
-/// Returns (char, line)
-fn snap_pos(new_x: usize, new_y: usize, text_data: &TextRope) -> (usize, usize) {
+/// Returns (char, line) of the character boundary nearest to column col, kept inside the text
+fn snap_pos(col: f32, new_y: usize, text_data: &TextRope, window: &WindowState) -> (usize, usize) {
     let new_y = new_y.min(text_data.line_count() - 1);
-    let new_x = new_x.min(text_data.lines().nth(new_y as usize).unwrap().chars().count());
+    let new_x = text_data.line(new_y).map_or(0, |line_text| tabs::char_near(line_text.chars(), col, window.tab_width()));
     (new_x, new_y)
 }
 
@@ -452,5 +560,15 @@ fn is_identifier(c: char) -> bool {
 }
 
 fn is_symbol(c: char) -> bool {
-    !is_identifier(c) && c != ' '
+    !is_identifier(c) && !is_space(c)
+}
+
+fn is_space(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+/// Column on screen of char x of line y
+fn col_at(x: u32, y: u32, text_data: &TextRope, window: &WindowState) -> u32 {
+    text_data.line(y as usize)
+        .map_or(0, |line_text| tabs::col_of(line_text.chars(), x as usize, window.tab_width()) as u32)
 }
\ No newline at end of file
@@ -0,0 +1,193 @@
+mod lineending;
+mod encoding;
+mod atomicwrite;
+mod swap;
+
+use std::{fs::{self, File}, io::{self, BufRead, BufReader}, path::{Path, PathBuf}, thread::{self, JoinHandle}, time::SystemTime};
+
+use crate::editor::textrope::Rope;
+
+pub use lineending::LineEnding;
+pub use encoding::Encoding;
+
+const UNTITLED_NAME: &str = "Untitled";
+
+/// Settings for how documents are written, changed at runtime with `:set`
+#[derive(Clone, Default)]
+pub struct SaveOptions {
+    /// Keep the previous version of the file as `file~`
+    pub backup: bool,
+}
+
+/// What the file looked like on disk the last time it was read or written
+#[derive(Clone, Copy, PartialEq)]
+struct DiskStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl DiskStamp {
+    fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            len: metadata.len(),
+        })
+    }
+}
+
+/// File backed information about the text in a buffer
+#[derive(Clone, Default)]
+pub struct Document {
+    path: Option<PathBuf>,
+    line_ending: LineEnding,
+    encoding: Encoding,
+    /// Set when the on disk format changed without touching the text
+    format_changed: bool,
+    disk_stamp: Option<DiskStamp>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self {
+            path: Some(path),
+            ..Default::default()
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.disk_stamp = DiskStamp::read(&path);
+        self.path = Some(path);
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if line_ending != self.line_ending {
+            self.line_ending = line_ending;
+            self.format_changed = true;
+        }
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        if encoding != self.encoding {
+            self.encoding = encoding;
+            self.format_changed = true;
+        }
+    }
+
+    /// True if saving would change the file even though the text is unchanged
+    pub fn format_changed(&self) -> bool {
+        self.format_changed
+    }
+
+    /// Stops counting the current format as a change, it is still what the next save writes
+    pub fn clear_format_changed(&mut self) {
+        self.format_changed = false;
+    }
+
+    /// Extra information for the status line
+    pub fn status(&self) -> String {
+        format!("{}  {}", self.line_ending.name(), self.encoding.name())
+    }
+
+    /// File name shown to the user, "Untitled" if the document was never saved
+    pub fn name(&self) -> String {
+        self.path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or_else(|| String::from(UNTITLED_NAME), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Returns "name.rs* — app_name", the star marks unsaved changes
+    pub fn title(&self, modified: bool, app_name: &str) -> String {
+        let modified_marker = if modified { "*" } else { "" };
+        format!("{}{} \u{2014} {}", self.name(), modified_marker, app_name)
+    }
+
+    /// Reads the file at path, returns the new document and its text
+    pub fn open(path: &Path) -> std::io::Result<(Self, Rope)> {
+        let disk_stamp = DiskStamp::read(path);
+        let (text, encoding) = Self::read_text(path)?;
+        let line_ending = LineEnding::detect(text.slice(..).chunks()).unwrap_or_default();
+        let document = Self {
+            line_ending,
+            encoding,
+            disk_stamp,
+            ..Self::from_path(path.to_path_buf())
+        };
+        Ok((document, line_ending.to_buffer(text)))
+    }
+
+    /// Plain UTF-8 goes into the rope a block at a time, without holding the whole file in memory twice.
+    /// Files with a byte order mark or invalid UTF-8 are read whole and decoded
+    fn read_text(path: &Path) -> io::Result<(Rope, Encoding)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if !Encoding::has_bom(reader.fill_buf()?) {
+            match Rope::from_reader(reader) {
+                Ok(text) => return Ok((text, Encoding::UTF8)),
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {},
+                Err(err) => return Err(err),
+            }
+        }
+        let (data, encoding) = Encoding::decode(fs::read(path)?);
+        Ok((Rope::from_str(&data), encoding))
+    }
+
+    /// Writes data to the document path, returns the number of bytes written
+    pub fn save(&mut self, data: String, options: &SaveOptions) -> std::io::Result<usize> {
+        let Some(path) = &self.path else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "Document has no file path"));
+        };
+        let disk_data = self.encoding.encode(&self.line_ending.to_disk(data))?;
+        atomicwrite::write(path, &disk_data, options.backup)?;
+        self.disk_stamp = DiskStamp::read(path);
+        self.format_changed = false;
+        Ok(disk_data.len())
+    }
+
+    /// True if another program wrote the file since it was last checked, read or saved
+    ///
+    /// A deleted file is not reported, saving will simply create it again
+    pub fn poll_disk_change(&mut self) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        let disk_stamp = DiskStamp::read(path);
+        let changed = disk_stamp.is_some() && disk_stamp != self.disk_stamp;
+        self.disk_stamp = disk_stamp;
+        changed
+    }
+
+    /// Writes the unsaved text next to the file on another thread so it survives a crash, None without a path
+    pub fn write_swap(&self, text: Rope) -> Option<JoinHandle<std::io::Result<()>>> {
+        let path = self.path.clone()?;
+        Some(thread::spawn(move || swap::write(&path, &text)))
+    }
+
+    pub fn remove_swap(&self) -> std::io::Result<()> {
+        match &self.path {
+            Some(path) => swap::remove(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns a swap file left behind by a crash, if it is newer than the file
+    pub fn newer_swap(&self) -> Option<PathBuf> {
+        self.path.as_deref().and_then(swap::find_newer)
+    }
+
+    pub fn read_swap(swap_path: &Path) -> std::io::Result<String> {
+        swap::read(swap_path)
+    }
+}
\ No newline at end of file
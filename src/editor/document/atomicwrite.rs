@@ -0,0 +1,183 @@
+use std::{fs::{self, File, OpenOptions}, io::{self, Write}, path::{Path, PathBuf}, sync::atomic::{AtomicU32, Ordering}};
+
+const BACKUP_SUFFIX: &str = "~";
+/// Names tried for the temporary file before giving up, old ones can be left behind by a killed process
+const TEMP_ATTEMPTS: u32 = 100;
+
+/// Tells temporary files apart within this process, the process id alone may have been used before
+static TEMP_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Replaces the file at path with data without ever leaving a half written file behind
+///
+/// The data goes to a temporary file in the same directory, is flushed to disk and then renamed
+/// over the target. With backup set the previous contents are first copied to `path~`
+pub fn write(path: &Path, data: &[u8], backup: bool) -> io::Result<()> {
+    let path = resolve_symlink(path);
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file_name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?
+        .to_string_lossy();
+    let (temp_path, temp_file) = create_temp(&dir, &file_name)?;
+
+    let result = write_temp(temp_file, &path, data).and_then(|_| {
+        if backup && path.exists() {
+            backup_file(&path)?;
+        }
+        fs::rename(&temp_path, &path)
+    });
+    if result.is_err() {
+        _ = fs::remove_file(&temp_path);
+    }
+    result?;
+
+    sync_dir(&dir);
+    Ok(())
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup_path = path.as_os_str().to_owned();
+    backup_path.push(BACKUP_SUFFIX);
+    PathBuf::from(backup_path)
+}
+
+/// Creates a new hidden file next to the target, e.g. `.main.rs.1234.0.tmp`, never opening an existing one
+fn create_temp(dir: &Path, file_name: &str) -> io::Result<(PathBuf, File)> {
+    for _ in 0..TEMP_ATTEMPTS {
+        let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = dir.join(format!(".{file_name}.{}.{count}.tmp", std::process::id()));
+        match OpenOptions::new().write(true).create_new(true).open(&temp_path) {
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            result => return result.map(|temp_file| (temp_path, temp_file)),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "Could not find a free temporary file name"))
+}
+
+fn write_temp(mut temp_file: File, target: &Path, data: &[u8]) -> io::Result<()> {
+    temp_file.write_all(data)?;
+    if let Ok(metadata) = fs::metadata(target) {
+        temp_file.set_permissions(metadata.permissions())?;
+    }
+    temp_file.sync_all()
+}
+
+fn backup_file(path: &Path) -> io::Result<u64> {
+    fs::copy(path, backup_path(path))
+}
+
+/// Saving through a symlink should update the file it points at, not replace the link
+fn resolve_symlink(path: &Path) -> PathBuf {
+    let is_symlink = fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink());
+    if is_symlink {
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Makes the rename itself durable, directories can't be opened for this on windows
+fn sync_dir(dir: &Path) {
+    if cfg!(unix) && let Ok(dir) = File::open(dir) {
+        _ = dir.sync_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty directory for one test, under the system temp directory
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("text_editor_atomicwrite_{name}_{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn file_names(dir: &Path) -> Vec<String> {
+        let mut names = fs::read_dir(dir).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<String>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let dir = test_dir("round_trip");
+        let path = dir.join("notes.txt");
+        write(&path, b"first", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"first");
+        write(&path, b"second", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(file_names(&dir), ["notes.txt"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn backup_test() {
+        let dir = test_dir("backup");
+        let path = dir.join("notes.txt");
+        write(&path, b"first", true).unwrap();
+        assert_eq!(file_names(&dir), ["notes.txt"]);
+        write(&path, b"second", true).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert_eq!(fs::read(dir.join("notes.txt~")).unwrap(), b"first");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn leftover_temp_test() {
+        let dir = test_dir("leftover_temp");
+        let path = dir.join("notes.txt");
+        let next = TEMP_COUNTER.load(Ordering::Relaxed);
+        for count in next..next + 3 {
+            fs::write(dir.join(format!(".notes.txt.{}.{count}.tmp", std::process::id())), "killed").unwrap();
+        }
+        write(&path, b"saved", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"saved");
+        assert_eq!(file_names(&dir).len(), 4);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_write_test() {
+        let dir = test_dir("failed_write");
+        let path = dir.join("notes.txt");
+        fs::create_dir(&path).unwrap();
+        assert!(write(&path, b"saved", false).is_err());
+        assert_eq!(file_names(&dir), ["notes.txt"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions_test() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = test_dir("permissions");
+        let path = dir.join("run.sh");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o750)).unwrap();
+        write(&path, b"new", false).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o750);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_test() {
+        let dir = test_dir("symlink");
+        let target = dir.join("target.txt");
+        let link = dir.join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        write(&link, b"new", false).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read(&target).unwrap(), b"new");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
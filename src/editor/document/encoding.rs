@@ -0,0 +1,137 @@
+use std::io;
+
+const UTF8_BOM: [u8; 3] = [0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: [u8; 2] = [0xFF, 0xFE];
+const UTF16BE_BOM: [u8; 2] = [0xFE, 0xFF];
+
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Encoding {
+    #[default]
+    UTF8,
+    UTF8BOM,
+    UTF16LE,
+    UTF16BE,
+    /// Fallback for anything else, every byte maps to one char so it always round trips
+    LATIN1,
+}
+
+impl Encoding {
+    /// Detects the encoding from the byte order mark, falling back to Latin-1 for invalid UTF-8
+    pub fn decode(bytes: Vec<u8>) -> (String, Self) {
+        let bom_decoded = if let Some(rest) = bytes.strip_prefix(&UTF8_BOM) {
+            std::str::from_utf8(rest).ok().map(|text| (text.to_string(), Self::UTF8BOM))
+        } else if let Some(rest) = bytes.strip_prefix(&UTF16LE_BOM) {
+            decode_utf16(rest, u16::from_le_bytes).map(|text| (text, Self::UTF16LE))
+        } else if let Some(rest) = bytes.strip_prefix(&UTF16BE_BOM) {
+            decode_utf16(rest, u16::from_be_bytes).map(|text| (text, Self::UTF16BE))
+        } else {
+            None
+        };
+        if let Some(decoded) = bom_decoded {
+            return decoded;
+        }
+
+        match String::from_utf8(bytes) {
+            Ok(text) => (text, Self::UTF8),
+            Err(err) => (err.into_bytes().into_iter().map(char::from).collect(), Self::LATIN1),
+        }
+    }
+
+    /// True if bytes start with a byte order mark, the text then can't be read as plain UTF-8
+    pub fn has_bom(bytes: &[u8]) -> bool {
+        bytes.starts_with(&UTF8_BOM) || bytes.starts_with(&UTF16LE_BOM) || bytes.starts_with(&UTF16BE_BOM)
+    }
+
+    pub fn encode(self, text: &str) -> io::Result<Vec<u8>> {
+        let bytes = match self {
+            Self::UTF8 => text.as_bytes().to_vec(),
+            Self::UTF8BOM => UTF8_BOM.iter().chain(text.as_bytes()).copied().collect(),
+            Self::UTF16LE => UTF16LE_BOM.into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_le_bytes))
+                .collect(),
+            Self::UTF16BE => UTF16BE_BOM.into_iter()
+                .chain(text.encode_utf16().flat_map(u16::to_be_bytes))
+                .collect(),
+            Self::LATIN1 => text.chars()
+                .map(|c| u8::try_from(c).map_err(|_| unencodable_char(text, c)))
+                .collect::<io::Result<Vec<u8>>>()?,
+        };
+        Ok(bytes)
+    }
+
+    /// Parses the value of `:set encoding=`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(Self::UTF8),
+            "utf-8-bom" | "utf8-bom" => Some(Self::UTF8BOM),
+            "utf-16le" | "utf16le" => Some(Self::UTF16LE),
+            "utf-16be" | "utf16be" => Some(Self::UTF16BE),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(Self::LATIN1),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::UTF8 => "UTF-8",
+            Self::UTF8BOM => "UTF-8 BOM",
+            Self::UTF16LE => "UTF-16 LE",
+            Self::UTF16BE => "UTF-16 BE",
+            Self::LATIN1 => "Latin-1",
+        }
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Option<String> {
+    if !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let units = bytes.chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect::<Vec<u16>>();
+    String::from_utf16(&units).ok()
+}
+
+fn unencodable_char(text: &str, c: char) -> io::Error {
+    let line = text.chars().take_while(|&other| other != c).filter(|&other| other == '\n').count() + 1;
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{:?} on line {} can't be encoded as Latin-1, use :set encoding=utf-8", c, line),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_test() {
+        assert_eq!(Encoding::decode(b"plain".to_vec()), (String::from("plain"), Encoding::UTF8));
+        assert_eq!(Encoding::decode(b"\xEF\xBB\xBFbom".to_vec()), (String::from("bom"), Encoding::UTF8BOM));
+        assert_eq!(Encoding::decode(b"\xFF\xFEh\x00i\x00".to_vec()), (String::from("hi"), Encoding::UTF16LE));
+        assert_eq!(Encoding::decode(b"\xFE\xFF\x00h\x00i".to_vec()), (String::from("hi"), Encoding::UTF16BE));
+        assert_eq!(Encoding::decode(b"caf\xE9".to_vec()), (String::from("café"), Encoding::LATIN1));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        let samples: [&[u8]; 6] = [
+            b"hello\nworld\n",
+            "爆発しませんように\n".as_bytes(),
+            b"\xEF\xBB\xBFwith bom\r\n",
+            b"\xFF\xFEh\x00\n\x00",
+            b"\xFE\xFF\x00h\x00\n",
+            b"\xFF\x00\x80 not utf-8",
+        ];
+        for bytes in samples {
+            let (text, encoding) = Encoding::decode(bytes.to_vec());
+            assert_eq!(encoding.encode(&text).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn latin1_unencodable_test() {
+        assert!(Encoding::LATIN1.encode("ok é").is_ok());
+        assert!(Encoding::LATIN1.encode("line\n爆").is_err());
+    }
+}
\ No newline at end of file
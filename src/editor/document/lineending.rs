@@ -0,0 +1,99 @@
+use crate::editor::textrope::Rope;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineEnding {
+    LF,
+    CRLF,
+    /// Both styles appear, the buffer keeps the '\r's so the file is written back as it was read
+    MIXED,
+}
+
+impl LineEnding {
+    /// Returns None if the text, given in chunks, has no line breaks
+    pub fn detect<'a>(chunks: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let (mut crlf_count, mut lf_count) = (0, 0);
+        let mut prev_byte = 0;
+        for byte in chunks.into_iter().flat_map(str::bytes) {
+            if byte == b'\n' && prev_byte == b'\r' {
+                crlf_count += 1;
+            } else if byte == b'\n' {
+                lf_count += 1;
+            }
+            prev_byte = byte;
+        }
+
+        match (crlf_count, lf_count) {
+            (0, 0) => None,
+            (0, _) => Some(Self::LF),
+            (_, 0) => Some(Self::CRLF),
+            _ => Some(Self::MIXED),
+        }
+    }
+
+    /// Parses the value of `:set ff=`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "unix" | "lf" => Some(Self::LF),
+            "dos" | "crlf" => Some(Self::CRLF),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LF => "LF",
+            Self::CRLF => "CRLF",
+            Self::MIXED => "Mixed",
+        }
+    }
+
+    /// Converts text read from disk into the form stored in the buffer
+    pub fn to_buffer(self, text: Rope) -> Rope {
+        match self {
+            Self::CRLF => Rope::from_str(&text.slice(..).to_string().replace("\r\n", "\n")),
+            Self::LF | Self::MIXED => text,
+        }
+    }
+
+    /// Converts buffer text into the form written to disk
+    pub fn to_disk(self, text: String) -> String {
+        match self {
+            Self::CRLF => text.replace("\n", "\r\n"),
+            Self::LF | Self::MIXED => text,
+        }
+    }
+}
+
+impl Default for LineEnding {
+    fn default() -> Self {
+        if cfg!(target_os = "windows") {
+            Self::CRLF
+        } else {
+            Self::LF
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_test() {
+        assert_eq!(LineEnding::detect(["no breaks"]), None);
+        assert_eq!(LineEnding::detect(["a\nb\n"]), Some(LineEnding::LF));
+        assert_eq!(LineEnding::detect(["a\r\nb\r\n"]), Some(LineEnding::CRLF));
+        assert_eq!(LineEnding::detect(["a\r\nb\n"]), Some(LineEnding::MIXED));
+        assert_eq!(LineEnding::detect(["\n\r\n"]), Some(LineEnding::MIXED));
+        assert_eq!(LineEnding::detect(["a\r", "\nb\r\n"]), Some(LineEnding::CRLF));
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for text in ["a\nb\n", "a\r\nb\r\n", "a\r\nb\nc\r\n", "\r\r\n\n"] {
+            let line_ending = LineEnding::detect([text]).unwrap();
+            let buffer = line_ending.to_buffer(Rope::from_str(text));
+            assert_eq!(line_ending.to_disk(buffer.slice(..).to_string()), text);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use crate::editor::{document::atomicwrite, textrope::Rope};
+
+/// Not Vim's `swp`, so the two editors never take each other's swap files for their own
+const SWAP_EXTENSION: &str = "te-swap";
+
+/// Hidden file next to the document that holds unsaved text, e.g. `.main.rs.te-swap`
+pub fn swap_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    path.with_file_name(format!(".{file_name}.{SWAP_EXTENSION}"))
+}
+
+pub fn write(path: &Path, text: &Rope) -> io::Result<()> {
+    let mut data = Vec::with_capacity(text.byte_len());
+    for chunk in text.slice(..).chunks() {
+        data.extend_from_slice(chunk.as_bytes());
+    }
+    atomicwrite::write(&swap_path(path), &data, false)
+}
+
+pub fn read(swap_path: &Path) -> io::Result<String> {
+    fs::read_to_string(swap_path)
+}
+
+pub fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(swap_path(path)) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the swap file for path if it was written after the file itself
+pub fn find_newer(path: &Path) -> Option<PathBuf> {
+    let swap_path = swap_path(path);
+    let swap_modified = fs::metadata(&swap_path).and_then(|metadata| metadata.modified()).ok()?;
+    match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+        Ok(file_modified) if file_modified >= swap_modified => None,
+        _ => Some(swap_path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, time::{Duration, SystemTime}};
+
+    use super::*;
+
+    /// Empty directory for one test, under the system temp directory
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("text_editor_swap_{name}_{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn set_modified(path: &Path, modified: SystemTime) {
+        File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn swap_path_test() {
+        assert_eq!(swap_path(Path::new("dir/main.rs")), Path::new("dir/.main.rs.te-swap"));
+    }
+
+    #[test]
+    fn write_read_remove_test() {
+        let dir = test_dir("write_read_remove");
+        let path = dir.join("notes.txt");
+        let text = "unsaved\n".repeat(2_000);
+        write(&path, &Rope::from_str(&text)).unwrap();
+        assert_eq!(read(&swap_path(&path)).unwrap(), text);
+        assert!(!path.exists());
+
+        remove(&path).unwrap();
+        assert!(!swap_path(&path).exists());
+        remove(&path).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn find_newer_test() {
+        let dir = test_dir("find_newer");
+        let path = dir.join("notes.txt");
+        assert_eq!(find_newer(&path), None);
+
+        write(&path, &Rope::from_str("unsaved")).unwrap();
+        assert_eq!(find_newer(&path), Some(swap_path(&path)));
+
+        let now = SystemTime::now();
+        fs::write(&path, "saved").unwrap();
+        set_modified(&swap_path(&path), now - Duration::from_secs(60));
+        set_modified(&path, now);
+        assert_eq!(find_newer(&path), None);
+
+        set_modified(&swap_path(&path), now + Duration::from_secs(60));
+        assert_eq!(find_newer(&path), Some(swap_path(&path)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,8 +4,9 @@ use std::error::Error;
 use sdl3::pixels::Color;
 use sdl3::render::FRect;
 use sdl3::{rect::Rect, render::Canvas, video::Window};
-use crate::editor::{windowstate::WindowState, TextAlignment};
+use crate::editor::{tabs, textrope::RopeSlice, windowstate::WindowState, TextAlignment};
 use crate::editor::cursor::Cursor;
+use crate::editor::wrap::Row;
 use crate::vector::Vector2D;
 
 pub fn text_target_aligned(alignment: &TextAlignment, padding: u32, start_x: u32, start_y: u32, text_w: u32, text_h: u32, screen_w: u32) -> Rect {
@@ -31,12 +32,14 @@ fn text_target_right(padding: u32, start_x: u32, start_y: u32, text_w: u32, text
     Rect::new(x as i32, start_y as i32, text_w as u32, text_h as u32)
 }
 
+/// Highlights the selected part of one screen row of line
 pub fn selection_box(
     canvas: &mut Canvas<Window>,
     cursor: &Cursor,
     window: &WindowState,
-    line_num: usize,
-    focused_line_len: usize,
+    row: &Row,
+    line: RopeSlice<'_>,
+    screen_row: u32,
     select_color: Color,
 ) -> Result<(), Box<dyn Error>> {
     let Some(Vector2D { x: select_char, y: select_line}) = cursor.select_start_pos() else {
@@ -54,40 +57,44 @@ pub fn selection_box(
         Ordering::Greater => (select_char, select_line, cursor_char, cursor_line),
     };
 
+    let line_num = row.line;
     if line_num < start_line as usize || line_num > end_line as usize {
         return Ok(());
     }
 
-    let line_len = focused_line_len + window.get_first_char();
+    let row_start = row.start as u32;
+    let row_end = (row.start + row.len) as u32;
     let (current_line_start_char, current_line_end_char) = if line_num == start_line as usize {
         if start_line != end_line {
-            (start_char, line_len as u32)
+            (start_char, row_end)
         } else {
             (start_char, end_char)
         }
     } else if line_num == end_line as usize {
         (0, end_char)
     } else {
-        (0, line_len as u32)
+        (0, row_end)
     };
+    // Selections ending at a row break are drawn on the row after it, like the cursor
+    let past_row_end = current_line_start_char > row_end || (current_line_start_char == row_end && !row.last);
+    if past_row_end || current_line_end_char < row_start {
+        return Ok(());
+    }
 
     let window_pos = window.pos();
     let (text_pad, line_pad) = window.get_padding();
     let (char_width, char_height) = window.get_text_dim();
     let char_width = char_width as u32;
     let line_height = char_height as u32 + line_pad;
-    let adjusted_char = current_line_start_char.saturating_sub(window.get_first_char() as u32);
-    let adjusted_line = line_num.saturating_sub(window.get_first_line()) as u32;
-    let x = adjusted_char * char_width + text_pad + window_pos.x;
-    let y = adjusted_line * line_height + text_pad + window_pos.y;
-
-    let window_char_offset = window.get_first_char() as u32;
-    let current_line_start_char = current_line_start_char.max(window_char_offset);
-    let last_window_char = window_char_offset + window.chars() as u32;
-    let current_line_end_char = current_line_end_char.min(last_window_char);
+    // Tabs make a character wider than one cell, the box is measured in columns on screen
+    let screen_col = |x: u32| tabs::col_of(line.chars(), x as usize, window.tab_width()).saturating_sub(row.col) as u32;
+    let start_col = screen_col(current_line_start_char.max(row_start));
+    let end_col = screen_col(current_line_end_char).min(window.chars() as u32);
+    let x = start_col * char_width + text_pad + window_pos.x + window.gutter_width();
+    let y = screen_row * line_height + text_pad + window_pos.y;
 
-    let chars = (current_line_end_char - current_line_start_char).min(line_len as u32);
-    let width = (chars * char_width).max(4);
+    let cols = end_col.saturating_sub(start_col);
+    let width = (cols * char_width).max(4);
 
     let frect = FRect::new(x as f32, y as f32, width as f32, char_height);
     canvas.set_draw_color(select_color);
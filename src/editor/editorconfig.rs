@@ -0,0 +1,396 @@
+use std::{fs, path::Path};
+
+use crate::editor::document::{Encoding, LineEnding};
+
+const EDITORCONFIG_FILE: &str = ".editorconfig";
+const MAX_INDENT: u32 = 16;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IndentStyle {
+    SPACE,
+    TAB,
+}
+
+impl IndentStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "space" | "spaces" => Some(Self::SPACE),
+            "tab" | "tabs" => Some(Self::TAB),
+            _ => None,
+        }
+    }
+}
+
+/// Formatting rules for one file, from `.editorconfig` files and the `[*.ext]` sections of the user config
+///
+/// None leaves the setting to the sections before, Some(None) is `unset` and hands it back to the editor wide default
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct FileSettings {
+    pub indent_style: Option<Option<IndentStyle>>,
+    /// Columns per indent level, Some(None) with `indent_size = tab` to indent by the tab width
+    pub indent_size: Option<Option<u32>>,
+    pub tab_width: Option<Option<u32>>,
+    pub end_of_line: Option<Option<LineEnding>>,
+    pub charset: Option<Option<Encoding>>,
+    pub trim_trailing_whitespace: Option<Option<bool>>,
+    pub insert_final_newline: Option<Option<bool>>,
+}
+
+impl FileSettings {
+    /// Sets one of the EditorConfig properties, `unset` clears it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.to_ascii_lowercase();
+        let unset = value == "unset";
+        match key.to_ascii_lowercase().as_str() {
+            "indent_style" if unset => self.indent_style = Some(None),
+            "indent_style" => {
+                let style = IndentStyle::from_name(&value).ok_or_else(|| format!("Expected space or tab for indent_style, got \"{value}\""))?;
+                self.indent_style = Some(Some(style));
+            },
+            "indent_size" if value == "tab" || unset => self.indent_size = Some(None),
+            "indent_size" => self.indent_size = Some(Some(parse_width(key, &value)?)),
+            "tab_width" if unset => self.tab_width = Some(None),
+            "tab_width" => self.tab_width = Some(Some(parse_width(key, &value)?)),
+            "end_of_line" if unset => self.end_of_line = Some(None),
+            "end_of_line" => {
+                let line_ending = LineEnding::from_name(&value).ok_or_else(|| format!("Expected lf or crlf for end_of_line, got \"{value}\""))?;
+                self.end_of_line = Some(Some(line_ending));
+            },
+            "charset" if unset => self.charset = Some(None),
+            "charset" => {
+                let encoding = Encoding::from_name(&value).ok_or_else(|| format!("Unknown charset \"{value}\""))?;
+                self.charset = Some(Some(encoding));
+            },
+            "trim_trailing_whitespace" => self.trim_trailing_whitespace = Some(parse_bool(key, &value, unset)?),
+            "insert_final_newline" => self.insert_final_newline = Some(parse_bool(key, &value, unset)?),
+            _ => return Err(format!("Unknown file setting \"{key}\"")),
+        }
+        Ok(())
+    }
+
+    /// Takes every setting other has or unsets
+    pub fn merge(&mut self, other: &FileSettings) {
+        self.indent_style = other.indent_style.or(self.indent_style);
+        self.indent_size = other.indent_size.or(self.indent_size);
+        self.tab_width = other.tab_width.or(self.tab_width);
+        self.end_of_line = other.end_of_line.or(self.end_of_line);
+        self.charset = other.charset.or(self.charset);
+        self.trim_trailing_whitespace = other.trim_trailing_whitespace.or(self.trim_trailing_whitespace);
+        self.insert_final_newline = other.insert_final_newline.or(self.insert_final_newline);
+    }
+
+    /// Settings for the file at path: the user's sections first, then every `.editorconfig` from the
+    /// file's directory upward, so the project's rules win
+    ///
+    /// The user's sections belong to no directory, a glob with a `/` in them matches the end of the path
+    pub fn for_path(path: &Path, user_sections: &[Section]) -> Self {
+        let mut settings = Self::default();
+        let file_name = path.to_string_lossy().replace('\\', "/");
+        let tails = file_name.match_indices('/').map(|(i, _)| &file_name[i + 1..]).chain([file_name.as_str()]).collect::<Vec<&str>>();
+        for section in user_sections.iter().filter(|section| tails.iter().any(|tail| section.matches(tail))) {
+            settings.merge(&section.settings);
+        }
+        settings.merge(&lookup(path));
+        settings
+    }
+
+    /// Text as it should be written, with trailing whitespace and the final newline fixed up
+    pub fn tidy(&self, text: &str) -> String {
+        let mut tidied = if self.trim_trailing_whitespace == Some(Some(true)) {
+            text.split_inclusive('\n')
+                .map(|line| {
+                    let content = line.trim_end_matches(['\r', '\n']);
+                    let ending = &line[content.len()..];
+                    format!("{}{ending}", content.trim_end_matches([' ', '\t']))
+                })
+                .collect()
+        } else {
+            text.to_string()
+        };
+        match self.insert_final_newline.flatten() {
+            Some(true) if !tidied.is_empty() && !tidied.ends_with('\n') => tidied.push('\n'),
+            Some(false) => tidied.truncate(tidied.trim_end_matches(['\r', '\n']).len()),
+            _ => {},
+        }
+        tidied
+    }
+}
+
+/// Settings for the files matching a glob like `*.rs` or `{Makefile,*.mk}`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Section {
+    pub pattern: String,
+    pub settings: FileSettings,
+}
+
+impl Section {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+            settings: FileSettings::default(),
+        }
+    }
+
+    /// Globs without a `/` match the file name in any directory, others are anchored to the directory of the
+    /// `.editorconfig` and match the whole path relative to it
+    pub fn matches(&self, path: &str) -> bool {
+        let (pattern, path) = match self.pattern.strip_prefix('/') {
+            Some(pattern) => (pattern.to_string(), path),
+            None if self.pattern.contains('/') => (self.pattern.clone(), path),
+            None => (self.pattern.clone(), path.rsplit('/').next().unwrap_or(path)),
+        };
+        let path = path.chars().collect::<Vec<char>>();
+        expand_braces(&pattern).iter().any(|pattern| glob(&pattern.chars().collect::<Vec<char>>(), &path))
+    }
+}
+
+/// Contents of one `.editorconfig` file, properties it does not know or cannot read are skipped
+struct EditorConfig {
+    root: bool,
+    sections: Vec<Section>,
+}
+
+impl EditorConfig {
+    fn parse(source: &str) -> Self {
+        let mut root = false;
+        let mut sections: Vec<Section> = Vec::new();
+        for line in source.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(pattern) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                sections.push(Section::new(pattern));
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match sections.last_mut() {
+                Some(section) => _ = section.settings.set(key, value),
+                None if key.eq_ignore_ascii_case("root") => root = value.eq_ignore_ascii_case("true"),
+                None => {},
+            }
+        }
+        Self { root, sections }
+    }
+}
+
+/// Merges the `.editorconfig` files from the file's directory up to the first one marked `root = true`
+fn lookup(path: &Path) -> FileSettings {
+    let Ok(path) = std::path::absolute(path) else {
+        return FileSettings::default();
+    };
+    let mut found = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(source) = fs::read_to_string(dir.join(EDITORCONFIG_FILE)) else {
+            continue;
+        };
+        let editorconfig = EditorConfig::parse(&source);
+        let root = editorconfig.root;
+        found.push((dir, editorconfig));
+        if root {
+            break;
+        }
+    }
+
+    let mut settings = FileSettings::default();
+    for (dir, editorconfig) in found.iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        for section in editorconfig.sections.iter().filter(|section| section.matches(&relative)) {
+            settings.merge(&section.settings);
+        }
+    }
+    settings
+}
+
+/// Turns `{a,b}c` into `ac` and `bc`
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some(open) = pattern.find('{') else {
+        return vec![pattern.to_string()];
+    };
+    let mut depth = 0;
+    let mut options = Vec::new();
+    let mut option_start = open + 1;
+    for (i, c) in pattern[open..].char_indices().map(|(i, c)| (i + open, c)) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 1 => {
+                options.push(&pattern[option_start..i]);
+                let (prefix, suffix) = (&pattern[..open], &pattern[i + 1..]);
+                return options.iter()
+                    .flat_map(|option| expand_braces(&format!("{prefix}{option}{suffix}")))
+                    .collect();
+            },
+            '}' => depth -= 1,
+            ',' if depth == 1 => {
+                options.push(&pattern[option_start..i]);
+                option_start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    // An unclosed brace is matched literally
+    vec![pattern.to_string()]
+}
+
+/// `*` stops at `/`, `**` does not, `?` is any one character and `[a-z]` or `[!abc]` a set of them
+fn glob(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => (0..=path.len()).any(|i| glob(rest, &path[i..])),
+        ['*', rest @ ..] => {
+            let segment_end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| glob(rest, &path[i..]))
+        },
+        ['?', rest @ ..] => path.first().is_some_and(|&c| c != '/') && glob(rest, &path[1..]),
+        ['[', rest @ ..] if rest.contains(&']') => {
+            let end = rest.iter().position(|&c| c == ']').unwrap();
+            let (negated, set) = match &rest[..end] {
+                ['!', set @ ..] => (true, set),
+                set => (false, set),
+            };
+            let Some(&c) = path.first() else {
+                return false;
+            };
+            in_set(set, c) != negated && c != '/' && glob(&rest[end + 1..], &path[1..])
+        },
+        ['\\', c, rest @ ..] => path.first() == Some(c) && glob(rest, &path[1..]),
+        [c, rest @ ..] => path.first() == Some(c) && glob(rest, &path[1..]),
+    }
+}
+
+/// True if c is one of the chars or ranges like `a-z` in set
+fn in_set(set: &[char], c: char) -> bool {
+    let mut i = 0;
+    while i < set.len() {
+        if let Some(['-', end]) = set.get(i + 1..i + 3) {
+            if (set[i]..=*end).contains(&c) {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+fn parse_width(key: &str, value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(width) if (1..=MAX_INDENT).contains(&width) => Ok(width),
+        _ => Err(format!("{key} must be between 1 and {MAX_INDENT}, got \"{value}\"")),
+    }
+}
+
+fn parse_bool(key: &str, value: &str, unset: bool) -> Result<Option<bool>, String> {
+    match value {
+        _ if unset => Ok(None),
+        "true" => Ok(Some(true)),
+        "false" => Ok(Some(false)),
+        _ => Err(format!("Expected true or false for {key}, got \"{value}\"")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Section::new(pattern).matches(path)
+    }
+
+    #[test]
+    fn glob_test() {
+        assert!(matches("*", "src/main.rs"));
+        assert!(matches("*.rs", "src/editor/main.rs"));
+        assert!(!matches("*.rs", "src/main.rsx"));
+        assert!(matches("{Makefile,*.mk}", "build/Makefile"));
+        assert!(matches("*.{yml,yaml}", "ci.yaml"));
+        assert!(matches("/src/*.rs", "src/main.rs"));
+        assert!(!matches("/src/*.rs", "src/editor/main.rs"));
+        assert!(matches("/src/**.rs", "src/editor/main.rs"));
+        assert!(matches("lib/**/test?.c", "lib/a/b/test1.c"));
+        assert!(!matches("lib/**/test?.c", "vendor/lib/a/b/test1.c"));
+        assert!(matches("[Mm]akefile", "makefile"));
+        assert!(matches("file[!0-9]", "fileA"));
+        assert!(!matches("file[!0-9]", "file7"));
+    }
+
+    #[test]
+    fn parse_test() {
+        let source = "\
+            root = true\n\
+            \n\
+            [*]\n\
+            indent_style = space\n\
+            indent_size = 4\n\
+            end_of_line = lf\n\
+            ; two spaces for data files\n\
+            [*.{yml,yaml}]\n\
+            indent_size = 2\n\
+            \n\
+            [Makefile]\n\
+            indent_style = tab\n\
+            indent_size = tab\n\
+            unknown_property = whatever\n\
+            \n\
+            [*.txt]\n\
+            indent_style = unset\n";
+        let editorconfig = EditorConfig::parse(source);
+        assert!(editorconfig.root);
+        assert_eq!(editorconfig.sections.len(), 4);
+
+        let settings_for = |path: &str| {
+            let mut settings = FileSettings::default();
+            for section in editorconfig.sections.iter().filter(|section| section.matches(path)) {
+                settings.merge(&section.settings);
+            }
+            settings
+        };
+        let yaml = settings_for("ci/deploy.yaml");
+        assert_eq!(yaml.indent_style, Some(Some(IndentStyle::SPACE)));
+        assert_eq!(yaml.indent_size, Some(Some(2)));
+        assert_eq!(yaml.end_of_line, Some(Some(LineEnding::LF)));
+        let makefile = settings_for("Makefile");
+        assert_eq!(makefile.indent_style, Some(Some(IndentStyle::TAB)));
+        assert_eq!(makefile.indent_size, Some(None));
+        let text = settings_for("notes.txt");
+        assert_eq!(text.indent_style, Some(None));
+        assert_eq!(text.indent_size, Some(Some(4)));
+    }
+
+    #[test]
+    fn set_test() {
+        let mut settings = FileSettings::default();
+        assert!(settings.set("Indent_Style", "TAB").is_ok());
+        assert_eq!(settings.indent_style, Some(Some(IndentStyle::TAB)));
+        assert!(settings.set("indent_size", "0").is_err());
+        assert!(settings.set("end_of_line", "cr").is_err());
+        assert!(settings.set("charset", "utf-8-bom").is_ok());
+        assert_eq!(settings.charset, Some(Some(Encoding::UTF8BOM)));
+        assert!(settings.set("indent_style", "unset").is_ok());
+        assert_eq!(settings.indent_style, Some(None));
+        assert!(settings.set("max_line_length", "80").is_err());
+    }
+
+    #[test]
+    fn tidy_test() {
+        let mut settings = FileSettings::default();
+        assert_eq!(settings.tidy("a  \nb\t\r\nc "), "a  \nb\t\r\nc ");
+        settings.trim_trailing_whitespace = Some(Some(true));
+        assert_eq!(settings.tidy("a  \nb\t\r\nc "), "a\nb\r\nc");
+        settings.insert_final_newline = Some(Some(true));
+        assert_eq!(settings.tidy("a \nb"), "a\nb\n");
+        assert_eq!(settings.tidy(""), "");
+        settings.insert_final_newline = Some(Some(false));
+        assert_eq!(settings.tidy("a\nb\n\n"), "a\nb");
+    }
+}
\ No newline at end of file
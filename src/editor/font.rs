@@ -0,0 +1,187 @@
+use std::{env, error::Error, path::{Path, PathBuf}};
+
+use crate::editor::config::Config;
+
+const FONT_PATH_VAR: &str = "TEXT_EDITOR_FONT";
+const FONT_FAMILY_VAR: &str = "TEXT_EDITOR_FONT_FAMILY";
+const FONT_EXTENSIONS: [&str; 3] = ["ttf", "otf", "ttc"];
+const MAX_SEARCH_DEPTH: usize = 4;
+const DEFAULT_FONT_FAMILIES: [&str; 8] = [
+    "Consolas",
+    "DejaVu Sans Mono",
+    "Liberation Mono",
+    "Noto Sans Mono",
+    "Ubuntu Mono",
+    "Menlo",
+    "Cascadia Mono",
+    "Courier New",
+];
+/// File stems that do not follow the family name, e.g. "consola.ttf" for Consolas
+const FAMILY_ALIASES: [(&str, &str); 3] = [
+    ("consolas", "consola"),
+    ("couriernew", "cour"),
+    ("lucidaconsole", "lucon"),
+];
+
+/// Where the editor should look for its font
+///
+/// An explicit path always wins, then the configured family, then the built in list of monospace families
+#[derive(Clone, Default)]
+pub struct FontSettings {
+    pub path: Option<PathBuf>,
+    pub family: Option<String>,
+}
+
+impl FontSettings {
+    /// The font chosen in config, the environment variables win where they are set
+    pub fn new(config: &Config) -> Self {
+        Self {
+            path: env::var_os(FONT_PATH_VAR).map(PathBuf::from).or_else(|| config.font.clone()),
+            family: env::var(FONT_FAMILY_VAR).ok().or_else(|| config.font_family.clone()),
+        }
+    }
+
+    pub fn resolve(&self) -> Result<PathBuf, Box<dyn Error>> {
+        if let Some(path) = &self.path {
+            if path.is_file() {
+                return Ok(path.clone());
+            }
+            return Err(format!("Configured font \"{}\" does not exist", path.display()).into());
+        }
+
+        let families = self.family.iter()
+            .map(|family| family.as_str())
+            .chain(DEFAULT_FONT_FAMILIES)
+            .collect::<Vec<&str>>();
+        let candidates = font_dirs().iter()
+            .flat_map(|dir| collect_font_files(dir, MAX_SEARCH_DEPTH))
+            .collect::<Vec<PathBuf>>();
+
+        families.iter()
+            .find_map(|family| match_family(family, &candidates))
+            .ok_or_else(|| {
+                let wanted = self.family.as_deref().unwrap_or(DEFAULT_FONT_FAMILIES[0]);
+                format!("Could not find font \"{wanted}\", set font in the config to a .ttf file").into()
+            })
+    }
+}
+
+/// Platform font directories, in search order
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let home = env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(PathBuf::from);
+
+    if cfg!(target_os = "windows") {
+        if let Some(local_app_data) = env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local_app_data).join(r"Microsoft\Windows\Fonts"));
+        }
+        let windir = env::var_os("WINDIR").map_or_else(|| PathBuf::from(r"C:\Windows"), PathBuf::from);
+        dirs.push(windir.join("Fonts"));
+    } else if cfg!(target_os = "macos") {
+        if let Some(home) = &home {
+            dirs.push(home.join("Library/Fonts"));
+        }
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+    } else {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.as_ref().map(|home| home.join(".local/share")));
+        if let Some(data_home) = data_home {
+            dirs.push(data_home.join("fonts"));
+        }
+        if let Some(home) = &home {
+            dirs.push(home.join(".fonts"));
+        }
+        let data_dirs = env::var("XDG_DATA_DIRS").unwrap_or_else(|_| String::from("/usr/local/share:/usr/share"));
+        dirs.extend(data_dirs.split(':').filter(|dir| !dir.is_empty()).map(|dir| Path::new(dir).join("fonts")));
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+    }
+    dirs.dedup();
+    dirs
+}
+
+fn collect_font_files(dir: &Path, depth: usize) -> Vec<PathBuf> {
+    let Ok(entries) = dir.read_dir() else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    let mut sub_dirs = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            sub_dirs.push(path);
+        } else if is_font_file(&path) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    sub_dirs.sort();
+    if depth > 0 {
+        for sub_dir in sub_dirs {
+            files.extend(collect_font_files(&sub_dir, depth - 1));
+        }
+    }
+    files
+}
+
+fn match_family(family: &str, candidates: &[PathBuf]) -> Option<PathBuf> {
+    let family = normalize(family);
+    let alias = FAMILY_ALIASES.iter()
+        .find(|&&(name, _)| name == family)
+        .map(|&(_, stem)| stem);
+    let wanted = [
+        Some(family.clone()),
+        Some(format!("{family}regular")),
+        alias.map(String::from),
+    ];
+
+    wanted.iter().flatten().find_map(|wanted| {
+        candidates.iter()
+            .find(|path| path.file_stem().is_some_and(|stem| normalize(&stem.to_string_lossy()) == *wanted))
+            .cloned()
+    })
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| FONT_EXTENSIONS.iter().any(|font_ext| ext.eq_ignore_ascii_case(font_ext)))
+}
+
+/// Lowercases and strips separators so "DejaVu Sans Mono" matches "DejaVuSansMono.ttf"
+fn normalize(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<PathBuf> {
+        ["DejaVuSansMono-Bold.ttf", "DejaVuSansMono.ttf", "LiberationMono-Regular.ttf", "consola.ttf", "cour.TTF"]
+            .iter()
+            .map(|name| Path::new("/fonts").join(name))
+            .collect()
+    }
+
+    #[test]
+    fn match_family_test() {
+        let candidates = candidates();
+        assert_eq!(match_family("DejaVu Sans Mono", &candidates), Some(PathBuf::from("/fonts/DejaVuSansMono.ttf")));
+        assert_eq!(match_family("liberation-mono", &candidates), Some(PathBuf::from("/fonts/LiberationMono-Regular.ttf")));
+        assert_eq!(match_family("Ubuntu Mono", &candidates), None);
+        assert_eq!(match_family("Mono", &candidates), None);
+    }
+
+    #[test]
+    fn alias_test() {
+        let candidates = candidates();
+        assert_eq!(match_family("Consolas", &candidates), Some(PathBuf::from("/fonts/consola.ttf")));
+        assert_eq!(match_family("Courier New", &candidates), Some(PathBuf::from("/fonts/cour.TTF")));
+        assert_eq!(match_family("Lucida Console", &candidates), None);
+    }
+}
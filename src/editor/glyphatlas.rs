@@ -0,0 +1,139 @@
+use std::{collections::HashMap, error::Error};
+
+use sdl3::{pixels::{Color, PixelFormat, PixelFormatEnum}, rect::Rect, render::{BlendMode, Canvas, FPoint, FRect, Texture, TextureCreator}, ttf::Font, video::{Window, WindowContext}};
+
+/// Width and height of each atlas texture
+const PAGE_SIZE: u32 = 512;
+const BYTES_PER_PIXEL: u32 = 4;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    c: char,
+    /// Bits of the point size, f32 is not hashable
+    font_size: u32,
+    style: u32,
+    color: Color,
+}
+
+#[derive(Clone, Copy)]
+struct Glyph {
+    page: usize,
+    area: Rect,
+}
+
+/// Glyphs rendered once into shared textures, so drawing text is a batch of copies
+///
+/// Glyphs are packed left to right in rows, a new page is started when the last one is full
+pub struct GlyphAtlas<'a> {
+    texture_creator: &'a TextureCreator<WindowContext>,
+    pages: Vec<Texture<'a>>,
+    /// None for characters with nothing to draw, like spaces
+    glyphs: HashMap<GlyphKey, Option<Glyph>>,
+    font_size: f32,
+    next_x: u32,
+    next_y: u32,
+    row_height: u32,
+}
+
+impl <'a> GlyphAtlas<'a> {
+    pub fn new(texture_creator: &'a TextureCreator<WindowContext>, font_size: f32) -> Self {
+        Self {
+            texture_creator,
+            pages: Vec::new(),
+            glyphs: HashMap::new(),
+            font_size,
+            next_x: 0,
+            next_y: 0,
+            row_height: 0,
+        }
+    }
+
+    /// Forgets every glyph after the font was reloaded at a new size, the pages are rebuilt on the next draw
+    pub fn reset(&mut self, font_size: f32) {
+        self.font_size = font_size;
+        self.pages.clear();
+        self.glyphs.clear();
+        self.next_x = 0;
+        self.next_y = 0;
+        self.row_height = 0;
+    }
+
+    /// Copies the text to the canvas one character per cell, starting at (x, y)
+    pub fn draw_text(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        font: &Font,
+        text: &str,
+        pos: FPoint,
+        cell_width: f32,
+        color: Color,
+    ) -> Result<(), Box<dyn Error>> {
+        for (i, c) in text.chars().enumerate() {
+            let Some(Glyph { page, area }) = self.glyph(font, c, color)? else {
+                continue;
+            };
+            let target = FRect::new(pos.x + i as f32 * cell_width, pos.y, area.width() as f32, area.height() as f32);
+            canvas.copy(&self.pages[page], FRect::from(area), target)?;
+        }
+        Ok(())
+    }
+
+    fn glyph(&mut self, font: &Font, c: char, color: Color) -> Result<Option<Glyph>, Box<dyn Error>> {
+        let key = GlyphKey {
+            c,
+            font_size: self.font_size.to_bits(),
+            style: font.get_style().bits(),
+            color,
+        };
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return Ok(*glyph);
+        }
+        let glyph = self.add_glyph(font, c, color)?;
+        self.glyphs.insert(key, glyph);
+        Ok(glyph)
+    }
+
+    fn add_glyph(&mut self, font: &Font, c: char, color: Color) -> Result<Option<Glyph>, Box<dyn Error>> {
+        if c.is_whitespace() || c.is_control() {
+            return Ok(None);
+        }
+        let Ok(surface) = font.render_char(c).blended(color) else {
+            return Ok(None);
+        };
+        let surface = surface.convert_format(PixelFormat::from(PixelFormatEnum::ARGB8888))?;
+        let (width, height) = surface.size();
+        if width == 0 || height == 0 || width > PAGE_SIZE || height > PAGE_SIZE {
+            return Ok(None);
+        }
+
+        if self.next_x + width > PAGE_SIZE {
+            self.next_x = 0;
+            self.next_y += self.row_height;
+            self.row_height = 0;
+        }
+        if self.pages.is_empty() || self.next_y + height > PAGE_SIZE {
+            self.add_page()?;
+        }
+        let page = self.pages.len() - 1;
+        let area = Rect::new(self.next_x as i32, self.next_y as i32, width, height);
+        let pitch = surface.pitch() as usize;
+        surface.with_lock(|pixels| self.pages[page].update(area, pixels, pitch))?;
+
+        self.next_x += width;
+        self.row_height = self.row_height.max(height);
+        Ok(Some(Glyph { page, area }))
+    }
+
+    fn add_page(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut page = self.texture_creator.create_texture_static(PixelFormat::from(PixelFormatEnum::ARGB8888), PAGE_SIZE, PAGE_SIZE)?;
+        page.set_blend_mode(BlendMode::Blend);
+        let blank = vec![0; (PAGE_SIZE * PAGE_SIZE * BYTES_PER_PIXEL) as usize];
+        page.update(None, &blank, (PAGE_SIZE * BYTES_PER_PIXEL) as usize)?;
+
+        self.pages.push(page);
+        self.next_x = 0;
+        self.next_y = 0;
+        self.row_height = 0;
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+/// Fewest digits the gutter makes room for, so it does not grow on the first few lines
+const MIN_DIGITS: usize = 3;
+
+/// How the gutter left of the text numbers its lines
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum LineNumbers {
+    OFF,
+    #[default]
+    ABSOLUTE,
+    /// Distance from the cursor's line, which shows 0
+    RELATIVE,
+    /// Distance from the cursor's line, which shows its own number
+    HYBRID,
+}
+
+impl LineNumbers {
+    /// Parses the value of `:set nu=`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "off" => Some(Self::OFF),
+            "absolute" | "on" => Some(Self::ABSOLUTE),
+            "relative" => Some(Self::RELATIVE),
+            "hybrid" => Some(Self::HYBRID),
+            _ => None,
+        }
+    }
+
+    /// True if the numbers change whenever the cursor moves to another line
+    pub fn follows_cursor(&self) -> bool {
+        matches!(self, Self::RELATIVE | Self::HYBRID)
+    }
+
+    /// Width of the gutter in characters, including a space before the text
+    pub fn width(&self, line_count: usize) -> usize {
+        if *self == Self::OFF {
+            return 0;
+        }
+        let digits = line_count.max(1).ilog10() as usize + 1;
+        digits.max(MIN_DIGITS) + 1
+    }
+
+    /// Number shown next to line, both lines counted from 0
+    pub fn label(&self, line: usize, cursor_line: usize) -> String {
+        match self {
+            Self::OFF => String::new(),
+            Self::ABSOLUTE => (line + 1).to_string(),
+            Self::RELATIVE => line.abs_diff(cursor_line).to_string(),
+            Self::HYBRID if line == cursor_line => (line + 1).to_string(),
+            Self::HYBRID => line.abs_diff(cursor_line).to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_test() {
+        assert_eq!(LineNumbers::OFF.width(5000), 0);
+        assert_eq!(LineNumbers::ABSOLUTE.width(0), 4);
+        assert_eq!(LineNumbers::ABSOLUTE.width(999), 4);
+        assert_eq!(LineNumbers::RELATIVE.width(1000), 5);
+        assert_eq!(LineNumbers::HYBRID.width(123456), 7);
+    }
+
+    #[test]
+    fn label_test() {
+        assert_eq!(LineNumbers::ABSOLUTE.label(4, 9), "5");
+        assert_eq!(LineNumbers::RELATIVE.label(4, 9), "5");
+        assert_eq!(LineNumbers::RELATIVE.label(9, 9), "0");
+        assert_eq!(LineNumbers::HYBRID.label(12, 9), "3");
+        assert_eq!(LineNumbers::HYBRID.label(9, 9), "10");
+        assert_eq!(LineNumbers::from_name("Hybrid"), Some(LineNumbers::HYBRID));
+        assert_eq!(LineNumbers::from_name("numbers"), None);
+    }
+}
\ No newline at end of file
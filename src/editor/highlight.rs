@@ -0,0 +1,209 @@
+mod grammar;
+
+pub use grammar::Language;
+
+/// What a piece of text is, each kind gets its own color
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum TokenKind {
+    KEYWORD,
+    TYPE,
+    FUNCTION,
+    STRING,
+    NUMBER,
+    COMMENT,
+    /// Keys of TOML tables and JSON objects
+    KEY,
+    HEADING,
+    EMPHASIS,
+    LINK,
+    VARIABLE,
+}
+
+impl TokenKind {
+    pub const ALL: [TokenKind; 11] = [
+        Self::KEYWORD, Self::TYPE, Self::FUNCTION, Self::STRING, Self::NUMBER, Self::COMMENT,
+        Self::KEY, Self::HEADING, Self::EMPHASIS, Self::LINK, Self::VARIABLE,
+    ];
+
+    /// Key of the token's color in a theme file
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::KEYWORD => "keyword",
+            Self::TYPE => "type",
+            Self::FUNCTION => "function",
+            Self::STRING => "string",
+            Self::NUMBER => "number",
+            Self::COMMENT => "comment",
+            Self::KEY => "key",
+            Self::HEADING => "heading",
+            Self::EMPHASIS => "emphasis",
+            Self::LINK => "link",
+            Self::VARIABLE => "variable",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// Colored span of a line, in char offsets from the start of the line
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Token {
+    pub start: usize,
+    pub len: usize,
+    pub kind: TokenKind,
+}
+
+/// Construct left open at the end of a line, the next line is lexed from inside it
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum LineState {
+    #[default]
+    NORMAL,
+    /// Nesting depth of a block comment
+    COMMENT(u32),
+    /// Quote character and how many of them close the string
+    STRING(char, u8),
+    /// Number of `#` that close a Rust raw string
+    RAW(u8),
+    /// Inside a fenced Markdown code block
+    CODE,
+}
+
+struct LexedLine {
+    tokens: Vec<Token>,
+    end_state: LineState,
+}
+
+/// Tokens of every line from the top of the text down to the last one drawn
+///
+/// Edits only drop the lines from the edited one onward, those are lexed again the next time they are drawn
+#[derive(Default)]
+pub struct Highlighter {
+    language: Option<Language>,
+    lines: Vec<LexedLine>,
+    /// Lines before this one are up to date, the rest are kept to spot when an edit changes the lines below it
+    valid: usize,
+}
+
+impl Highlighter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        self.language
+    }
+
+    /// Switches grammars, everything is lexed again
+    pub fn set_language(&mut self, language: Option<Language>) {
+        if language != self.language {
+            self.language = language;
+            self.lines.clear();
+            self.valid = 0;
+        }
+    }
+
+    /// Marks line and everything after it as out of date
+    pub fn invalidate_from(&mut self, line: usize) {
+        self.valid = self.valid.min(line);
+    }
+
+    /// First line that has to be lexed again before lines up to last can be drawn, None if they are up to date
+    pub fn first_stale(&self, last: usize) -> Option<usize> {
+        (self.language.is_some() && self.valid <= last).then_some(self.valid)
+    }
+
+    /// Lexes lines, which must start at the line returned by `first_stale`
+    ///
+    /// Returns the first line whose colors changed because a line above it now ends in a different state
+    pub fn lex_lines<S: AsRef<str>>(&mut self, lines: impl Iterator<Item = S>) -> Option<usize> {
+        let language = self.language?;
+        let mut state = self.valid.checked_sub(1).map_or(LineState::NORMAL, |prev| self.lines[prev].end_state);
+        let mut changed_from = None;
+        for line in lines {
+            let (tokens, end_state) = language.lex_line(line.as_ref(), state);
+            let lexed = LexedLine { tokens, end_state };
+            match self.lines.get_mut(self.valid) {
+                Some(old) => {
+                    if old.end_state != end_state && changed_from.is_none() {
+                        changed_from = Some(self.valid + 1);
+                    }
+                    *old = lexed;
+                }
+                None => self.lines.push(lexed),
+            }
+            state = end_state;
+            self.valid += 1;
+        }
+        // Lines below the ones just lexed started from states that may be gone, only their positions were worth keeping
+        self.lines.truncate(self.valid);
+        changed_from
+    }
+
+    /// Tokens of line, empty if it has not been lexed
+    pub fn tokens(&self, line: usize) -> &[Token] {
+        match self.lines.get(line) {
+            Some(lexed) if line < self.valid => &lexed.tokens,
+            _ => &[],
+        }
+    }
+}
+
+/// Kind of each char in start..start + len of a line, None where no token covers it
+pub fn kinds_in(tokens: &[Token], start: usize, len: usize) -> Vec<Option<TokenKind>> {
+    let mut kinds = vec![None; len];
+    for token in tokens {
+        let from = token.start.max(start);
+        let to = (token.start + token.len).min(start + len);
+        for kind in kinds.iter_mut().take(to.saturating_sub(start)).skip(from.saturating_sub(start)) {
+            *kind = Some(token.kind);
+        }
+    }
+    kinds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(highlighter: &Highlighter, line: usize) -> Vec<TokenKind> {
+        highlighter.tokens(line).iter().map(|token| token.kind).collect()
+    }
+
+    #[test]
+    fn incremental_test() {
+        let mut text = ["let a = 1;", "/* open", "still open", "fn b() {}"];
+        let mut highlighter = Highlighter::new();
+        highlighter.set_language(Some(Language::RUST));
+        assert_eq!(highlighter.first_stale(3), Some(0));
+        highlighter.lex_lines(text.iter());
+        assert_eq!(highlighter.first_stale(3), None);
+        assert_eq!(kinds(&highlighter, 0), vec![TokenKind::KEYWORD, TokenKind::NUMBER]);
+        assert_eq!(kinds(&highlighter, 3), vec![TokenKind::COMMENT]);
+
+        // Closing the comment changes the state at the end of line 2, so line 3 changes color
+        text[2] = "closed */";
+        highlighter.invalidate_from(2);
+        assert_eq!(highlighter.first_stale(3), Some(2));
+        assert_eq!(highlighter.lex_lines(text.iter().skip(2)), Some(3));
+        assert_eq!(kinds(&highlighter, 3), vec![TokenKind::KEYWORD, TokenKind::FUNCTION]);
+
+        // An edit that keeps every state the same leaves the lines below alone
+        text[0] = "let abc = 22;";
+        highlighter.invalidate_from(0);
+        assert_eq!(highlighter.lex_lines(text.iter().take(1)), None);
+        assert_eq!(highlighter.first_stale(3), Some(1));
+        assert!(highlighter.tokens(1).is_empty());
+    }
+
+    #[test]
+    fn kinds_in_test() {
+        let tokens = [
+            Token { start: 0, len: 3, kind: TokenKind::KEYWORD },
+            Token { start: 6, len: 4, kind: TokenKind::STRING },
+        ];
+        let kinds = kinds_in(&tokens, 2, 6);
+        assert_eq!(kinds, vec![Some(TokenKind::KEYWORD), None, None, None, Some(TokenKind::STRING), Some(TokenKind::STRING)]);
+    }
+}
\ No newline at end of file
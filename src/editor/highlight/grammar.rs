@@ -0,0 +1,602 @@
+use std::path::Path;
+
+use super::{LineState, Token, TokenKind};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static",
+    "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while", "yield",
+];
+const RUST_TYPES: &[&str] = &[
+    "bool", "char", "str", "f32", "f64", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac", "in", "function", "select",
+    "return", "local", "export", "readonly", "declare", "break", "continue", "exit",
+];
+
+/// Grammars built into the editor
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    RUST,
+    TOML,
+    MARKDOWN,
+    JSON,
+    SHELL,
+}
+
+impl Language {
+    /// Picks the grammar from the file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Self::RUST),
+            "toml" => Some(Self::TOML),
+            "md" | "markdown" => Some(Self::MARKDOWN),
+            "json" => Some(Self::JSON),
+            "sh" | "bash" | "zsh" => Some(Self::SHELL),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::RUST => "Rust",
+            Self::TOML => "TOML",
+            Self::MARKDOWN => "Markdown",
+            Self::JSON => "JSON",
+            Self::SHELL => "Shell",
+        }
+    }
+
+    /// Splits line into tokens, starting inside whatever construct the line before left open
+    pub fn lex_line(&self, line: &str, state: LineState) -> (Vec<Token>, LineState) {
+        let mut lexer = Lexer::new(line);
+        let end_state = match self {
+            Self::RUST => lex_rust(&mut lexer, state),
+            Self::TOML => lex_toml(&mut lexer, state),
+            Self::MARKDOWN => lex_markdown(&mut lexer, state),
+            Self::JSON => lex_json(&mut lexer),
+            Self::SHELL => lex_shell(&mut lexer, state),
+        };
+        (lexer.tokens, end_state)
+    }
+}
+
+/// Position in one line and the tokens found so far
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    tokens: Vec<Token>,
+}
+
+impl Lexer {
+    fn new(line: &str) -> Self {
+        Self {
+            chars: line.trim_end_matches(['\r', '\n']).chars().collect(),
+            pos: 0,
+            tokens: Vec::new(),
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        let mut chars = self.chars[self.pos.min(self.chars.len())..].iter();
+        pattern.chars().all(|c| chars.next() == Some(&c))
+    }
+
+    /// True if only whitespace comes before the current position
+    fn at_line_start(&self) -> bool {
+        self.chars[..self.pos].iter().all(|c| c.is_whitespace())
+    }
+
+    fn skip_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek(0).is_some_and(&f) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.skip_while(char::is_whitespace);
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.pos;
+        self.skip_while(|c| c.is_alphanumeric() || c == '_');
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Adds a token from start to the current position
+    fn push(&mut self, start: usize, kind: TokenKind) {
+        self.pos = self.pos.min(self.chars.len());
+        if self.pos > start {
+            self.tokens.push(Token { start, len: self.pos - start, kind });
+        }
+    }
+
+    fn rest_of_line(&mut self, start: usize, kind: TokenKind) {
+        self.pos = self.chars.len();
+        self.push(start, kind);
+    }
+
+    /// Scans to count quotes in a row, only strings in double quotes have escapes
+    fn string(&mut self, start: usize, quote: char, count: u8) -> LineState {
+        let closing = quote.to_string().repeat(count as usize);
+        loop {
+            if self.at_end() {
+                self.push(start, TokenKind::STRING);
+                return LineState::STRING(quote, count);
+            }
+            if quote == '"' && self.peek(0) == Some('\\') {
+                self.pos += 2;
+            } else if self.starts_with(&closing) {
+                self.pos += closing.len();
+                self.push(start, TokenKind::STRING);
+                return LineState::NORMAL;
+            } else {
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn number(&mut self, start: usize) {
+        self.pos += 1;
+        loop {
+            match (self.peek(0), self.peek(1)) {
+                (Some(c), _) if c.is_alphanumeric() || c == '_' => self.pos += 1,
+                (Some('.'), Some(next)) if next.is_ascii_digit() => self.pos += 1,
+                _ => break,
+            }
+        }
+        self.push(start, TokenKind::NUMBER);
+    }
+}
+
+fn lex_rust(lexer: &mut Lexer, mut state: LineState) -> LineState {
+    loop {
+        let start = lexer.pos;
+        state = match state {
+            LineState::COMMENT(depth) => rust_block_comment(lexer, start, depth),
+            LineState::STRING(quote, count) => lexer.string(start, quote, count),
+            LineState::RAW(hashes) => rust_raw_string(lexer, start, hashes),
+            _ => LineState::NORMAL,
+        };
+        if state != LineState::NORMAL || lexer.at_end() {
+            return state;
+        }
+
+        let start = lexer.pos;
+        let c = lexer.chars[start];
+        if lexer.starts_with("//") {
+            lexer.rest_of_line(start, TokenKind::COMMENT);
+        } else if lexer.starts_with("/*") {
+            state = rust_block_comment(lexer, start, 0);
+        } else if c == '"' || lexer.starts_with("b\"") {
+            lexer.pos += if c == 'b' { 2 } else { 1 };
+            state = lexer.string(start, '"', 1);
+        } else if let Some(hashes) = rust_raw_string_start(lexer) {
+            state = rust_raw_string(lexer, start, hashes);
+        } else if c == '\'' {
+            rust_quote(lexer, start);
+        } else if c.is_ascii_digit() {
+            lexer.number(start);
+        } else if c.is_alphabetic() || c == '_' {
+            let word = lexer.word();
+            let kind = if RUST_KEYWORDS.contains(&word.as_str()) {
+                Some(TokenKind::KEYWORD)
+            } else if RUST_TYPES.contains(&word.as_str()) || word.starts_with(char::is_uppercase) {
+                Some(TokenKind::TYPE)
+            } else if lexer.peek(0) == Some('!') {
+                lexer.pos += 1;
+                Some(TokenKind::FUNCTION)
+            } else if lexer.peek(0) == Some('(') {
+                Some(TokenKind::FUNCTION)
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                lexer.push(start, kind);
+            }
+        } else if lexer.starts_with("#[") || lexer.starts_with("#![") {
+            lexer.skip_while(|c| c != ']');
+            lexer.pos += 1;
+            lexer.push(start, TokenKind::EMPHASIS);
+        } else {
+            lexer.pos += 1;
+        }
+    }
+}
+
+fn rust_block_comment(lexer: &mut Lexer, start: usize, mut depth: u32) -> LineState {
+    loop {
+        if lexer.at_end() {
+            lexer.push(start, TokenKind::COMMENT);
+            return LineState::COMMENT(depth);
+        }
+        if lexer.starts_with("/*") {
+            depth += 1;
+            lexer.pos += 2;
+        } else if lexer.starts_with("*/") {
+            depth -= 1;
+            lexer.pos += 2;
+            if depth == 0 {
+                lexer.push(start, TokenKind::COMMENT);
+                return LineState::NORMAL;
+            }
+        } else {
+            lexer.pos += 1;
+        }
+    }
+}
+
+/// Moves past the opening of a raw string like `r#"` and returns its number of `#`
+fn rust_raw_string_start(lexer: &mut Lexer) -> Option<u8> {
+    let prefix = if lexer.starts_with("br") { 2 } else if lexer.starts_with("r") { 1 } else { return None };
+    let hashes = lexer.chars[lexer.pos + prefix..].iter().take_while(|&&c| c == '#').count();
+    if lexer.peek(prefix + hashes) != Some('"') {
+        return None;
+    }
+    lexer.pos += prefix + hashes + 1;
+    Some(hashes as u8)
+}
+
+fn rust_raw_string(lexer: &mut Lexer, start: usize, hashes: u8) -> LineState {
+    let closing = format!("\"{}", "#".repeat(hashes as usize));
+    loop {
+        if lexer.at_end() {
+            lexer.push(start, TokenKind::STRING);
+            return LineState::RAW(hashes);
+        }
+        if lexer.starts_with(&closing) {
+            lexer.pos += closing.len();
+            lexer.push(start, TokenKind::STRING);
+            return LineState::NORMAL;
+        }
+        lexer.pos += 1;
+    }
+}
+
+/// Char literal like `'a'` or `'\n'`, or a lifetime like `'a`
+fn rust_quote(lexer: &mut Lexer, start: usize) {
+    lexer.pos += 1;
+    if lexer.peek(0) == Some('\\') {
+        lexer.pos += 2;
+        lexer.skip_while(|c| c != '\'');
+        lexer.pos += 1;
+        lexer.push(start, TokenKind::STRING);
+    } else if lexer.peek(1) == Some('\'') {
+        lexer.pos += 2;
+        lexer.push(start, TokenKind::STRING);
+    } else {
+        lexer.word();
+        lexer.push(start, TokenKind::TYPE);
+    }
+}
+
+fn lex_toml(lexer: &mut Lexer, mut state: LineState) -> LineState {
+    if let LineState::STRING(quote, count) = state {
+        state = lexer.string(0, quote, count);
+        if state != LineState::NORMAL {
+            return state;
+        }
+    }
+    let mut expect_key = lexer.pos == 0;
+    loop {
+        lexer.skip_whitespace();
+        if lexer.at_end() {
+            return state;
+        }
+        let start = lexer.pos;
+        let c = lexer.chars[start];
+        if c == '#' {
+            lexer.rest_of_line(start, TokenKind::COMMENT);
+        } else if c == '[' && lexer.at_line_start() {
+            lexer.skip_while(|c| c != '#');
+            while lexer.pos > start && lexer.chars[lexer.pos - 1].is_whitespace() {
+                lexer.pos -= 1;
+            }
+            lexer.push(start, TokenKind::HEADING);
+        } else if c == '"' || c == '\'' {
+            let count = if lexer.starts_with(&c.to_string().repeat(3)) { 3 } else { 1 };
+            lexer.pos += count as usize;
+            state = lexer.string(start, c, count);
+            if expect_key && state == LineState::NORMAL && toml_key_follows(lexer) {
+                lexer.tokens.last_mut().unwrap().kind = TokenKind::KEY;
+            }
+            if state != LineState::NORMAL {
+                return state;
+            }
+        } else if expect_key && (c.is_alphanumeric() || c == '_' || c == '-') {
+            lexer.skip_while(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.');
+            lexer.push(start, TokenKind::KEY);
+        } else if c.is_ascii_digit() || ((c == '-' || c == '+') && lexer.peek(1).is_some_and(|c| c.is_ascii_digit())) {
+            lexer.skip_while(|c| c.is_alphanumeric() || "_.:-+".contains(c));
+            lexer.push(start, TokenKind::NUMBER);
+        } else if c.is_alphabetic() {
+            let word = lexer.word();
+            if matches!(word.as_str(), "true" | "false" | "inf" | "nan") {
+                lexer.push(start, TokenKind::KEYWORD);
+            }
+        } else {
+            lexer.pos += 1;
+        }
+        expect_key = matches!(c, '{' | ',') || (expect_key && c == '.');
+    }
+}
+
+/// True if an `=` comes after the quoted part of a key
+fn toml_key_follows(lexer: &Lexer) -> bool {
+    lexer.chars[lexer.pos..].iter().find(|c| !c.is_whitespace()).is_some_and(|&c| c == '=' || c == '.')
+}
+
+fn lex_json(lexer: &mut Lexer) -> LineState {
+    loop {
+        lexer.skip_whitespace();
+        if lexer.at_end() {
+            return LineState::NORMAL;
+        }
+        let start = lexer.pos;
+        let c = lexer.chars[start];
+        if c == '"' {
+            lexer.pos += 1;
+            lexer.string(start, '"', 1);
+            let key = lexer.chars[lexer.pos..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+            if key {
+                lexer.tokens.last_mut().unwrap().kind = TokenKind::KEY;
+            }
+        } else if c.is_ascii_digit() || c == '-' {
+            lexer.skip_while(|c| c.is_ascii_digit() || "-+.eE".contains(c));
+            lexer.push(start, TokenKind::NUMBER);
+        } else if c.is_alphabetic() {
+            let word = lexer.word();
+            if matches!(word.as_str(), "true" | "false" | "null") {
+                lexer.push(start, TokenKind::KEYWORD);
+            }
+        } else {
+            lexer.pos += 1;
+        }
+    }
+}
+
+fn lex_markdown(lexer: &mut Lexer, state: LineState) -> LineState {
+    lexer.skip_whitespace();
+    let fence = lexer.starts_with("```") || lexer.starts_with("~~~");
+    if state == LineState::CODE {
+        lexer.rest_of_line(0, TokenKind::STRING);
+        return if fence { LineState::NORMAL } else { LineState::CODE };
+    }
+    let start = lexer.pos;
+    if fence {
+        lexer.rest_of_line(start, TokenKind::STRING);
+        return LineState::CODE;
+    }
+    match lexer.peek(0) {
+        Some('#') => {
+            lexer.rest_of_line(start, TokenKind::HEADING);
+            return LineState::NORMAL;
+        }
+        Some('>') => {
+            lexer.rest_of_line(start, TokenKind::COMMENT);
+            return LineState::NORMAL;
+        }
+        Some('-' | '*' | '+') if lexer.peek(1) == Some(' ') => {
+            lexer.pos += 1;
+            lexer.push(start, TokenKind::KEYWORD);
+        }
+        Some(c) if c.is_ascii_digit() => {
+            lexer.skip_while(|c| c.is_ascii_digit());
+            if lexer.peek(0) == Some('.') && lexer.peek(1) == Some(' ') {
+                lexer.pos += 1;
+                lexer.push(start, TokenKind::KEYWORD);
+            }
+        }
+        _ => {}
+    }
+    markdown_inline(lexer);
+    LineState::NORMAL
+}
+
+/// Code spans, emphasis and links inside a paragraph
+fn markdown_inline(lexer: &mut Lexer) {
+    while !lexer.at_end() {
+        let start = lexer.pos;
+        let c = lexer.chars[start];
+        let closing = match c {
+            '`' => Some(("`", TokenKind::STRING)),
+            '*' if lexer.starts_with("**") => Some(("**", TokenKind::EMPHASIS)),
+            '_' if lexer.starts_with("__") => Some(("__", TokenKind::EMPHASIS)),
+            '*' | '_' => Some((if c == '*' { "*" } else { "_" }, TokenKind::EMPHASIS)),
+            '[' => Some(("]", TokenKind::LINK)),
+            _ => None,
+        };
+        let Some((closing, kind)) = closing else {
+            lexer.pos += 1;
+            continue;
+        };
+        let opening = if kind == TokenKind::LINK { 1 } else { closing.len() };
+        let body = lexer.chars[start + opening..].iter().collect::<String>();
+        match body.find(closing).filter(|&end| end > 0) {
+            Some(end) => {
+                lexer.pos = start + opening + body[..end].chars().count() + closing.len();
+                if kind == TokenKind::LINK && lexer.peek(0) == Some('(') {
+                    lexer.skip_while(|c| c != ')');
+                    lexer.pos += 1;
+                }
+                lexer.push(start, kind);
+            }
+            None => lexer.pos += opening,
+        }
+    }
+}
+
+fn lex_shell(lexer: &mut Lexer, mut state: LineState) -> LineState {
+    let mut command_start = state == LineState::NORMAL;
+    loop {
+        if let LineState::STRING(quote, count) = state {
+            let start = lexer.pos;
+            state = lexer.string(start, quote, count);
+            if state != LineState::NORMAL {
+                return state;
+            }
+        }
+        lexer.skip_whitespace();
+        if lexer.at_end() {
+            return state;
+        }
+        let start = lexer.pos;
+        let c = lexer.chars[start];
+        let after_space = start == 0 || lexer.chars[start - 1].is_whitespace();
+        if c == '#' && after_space {
+            lexer.rest_of_line(start, TokenKind::COMMENT);
+        } else if c == '"' || c == '\'' {
+            lexer.pos += 1;
+            state = lexer.string(start, c, 1);
+        } else if c == '$' {
+            lexer.pos += 1;
+            match lexer.peek(0) {
+                Some('{') => {
+                    lexer.skip_while(|c| c != '}');
+                    lexer.pos += 1;
+                }
+                Some(c) if c.is_alphanumeric() || c == '_' => {
+                    lexer.word();
+                }
+                Some(_) => lexer.pos += 1,
+                None => {}
+            }
+            lexer.push(start, TokenKind::VARIABLE);
+        } else if c.is_alphabetic() || c == '_' {
+            let word = lexer.word();
+            if lexer.peek(0) == Some('=') && command_start {
+                lexer.push(start, TokenKind::VARIABLE);
+            } else if SHELL_KEYWORDS.contains(&word.as_str()) && after_space {
+                lexer.push(start, TokenKind::KEYWORD);
+                command_start = true;
+                continue;
+            } else if command_start {
+                lexer.push(start, TokenKind::FUNCTION);
+            }
+        } else if c.is_ascii_digit() && after_space {
+            lexer.skip_while(|c| !c.is_whitespace() && !";|&)".contains(c));
+            if lexer.chars[start..lexer.pos].iter().all(char::is_ascii_digit) {
+                lexer.push(start, TokenKind::NUMBER);
+            }
+        } else {
+            lexer.pos += 1;
+        }
+        command_start = matches!(c, ';' | '|' | '&' | '(' | '{');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(language: Language, line: &str, state: LineState) -> (Vec<(String, TokenKind)>, LineState) {
+        let chars = line.chars().collect::<Vec<char>>();
+        let (tokens, state) = language.lex_line(line, state);
+        let tokens = tokens.iter()
+            .map(|token| (chars[token.start..token.start + token.len].iter().collect(), token.kind))
+            .collect();
+        (tokens, state)
+    }
+
+    fn text(tokens: &[(String, TokenKind)]) -> Vec<&str> {
+        tokens.iter().map(|(text, _)| text.as_str()).collect()
+    }
+
+    #[test]
+    fn rust_test() {
+        let (tokens, state) = lex(Language::RUST, "pub fn main() -> Vec<u8> { let s = \"a\\\"b\"; // done", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["pub", "fn", "main", "Vec", "u8", "let", "\"a\\\"b\"", "// done"]);
+        assert_eq!(tokens[2].1, TokenKind::FUNCTION);
+        assert_eq!(tokens[6].1, TokenKind::STRING);
+        assert_eq!(state, LineState::NORMAL);
+
+        let (tokens, state) = lex(Language::RUST, "x /* a /* b */ c", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["/* a /* b */ c"]);
+        assert_eq!(state, LineState::COMMENT(1));
+        let (tokens, state) = lex(Language::RUST, "end */ 0x1F", state);
+        assert_eq!(text(&tokens), vec!["end */", "0x1F"]);
+        assert_eq!(state, LineState::NORMAL);
+
+        let (tokens, state) = lex(Language::RUST, "r#\"raw \" still", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["r#\"raw \" still"]);
+        assert_eq!(state, LineState::RAW(1));
+        let (_, state) = lex(Language::RUST, "\"# 'a' fn f<'a>", state);
+        assert_eq!(state, LineState::NORMAL);
+        let (tokens, _) = lex(Language::RUST, "'a' '\\n' &'a str", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["'a'", "'\\n'", "'a", "str"]);
+    }
+
+    #[test]
+    fn toml_test() {
+        let (tokens, _) = lex(Language::TOML, "[package] # main", LineState::NORMAL);
+        assert_eq!(tokens, vec![("[package]".into(), TokenKind::HEADING), ("# main".into(), TokenKind::COMMENT)]);
+        let (tokens, _) = lex(Language::TOML, "sdl3 = { version = \"0.14\", features = [\"ttf\"] }", LineState::NORMAL);
+        assert_eq!(tokens.iter().map(|(_, kind)| *kind).collect::<Vec<_>>(), vec![
+            TokenKind::KEY, TokenKind::KEY, TokenKind::STRING, TokenKind::KEY, TokenKind::STRING,
+        ]);
+        let (tokens, state) = lex(Language::TOML, "text = \"\"\"first", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["text", "\"\"\"first"]);
+        assert_eq!(state, LineState::STRING('"', 3));
+        let (tokens, state) = lex(Language::TOML, "last\"\"\" # note", state);
+        assert_eq!(text(&tokens), vec!["last\"\"\"", "# note"]);
+        assert_eq!(state, LineState::NORMAL);
+    }
+
+    #[test]
+    fn json_test() {
+        let (tokens, _) = lex(Language::JSON, "{\"name\": \"x\", \"n\": -1.5e3, \"ok\": null}", LineState::NORMAL);
+        assert_eq!(tokens, vec![
+            ("\"name\"".into(), TokenKind::KEY),
+            ("\"x\"".into(), TokenKind::STRING),
+            ("\"n\"".into(), TokenKind::KEY),
+            ("-1.5e3".into(), TokenKind::NUMBER),
+            ("\"ok\"".into(), TokenKind::KEY),
+            ("null".into(), TokenKind::KEYWORD),
+        ]);
+    }
+
+    #[test]
+    fn markdown_test() {
+        let (tokens, _) = lex(Language::MARKDOWN, "## Title", LineState::NORMAL);
+        assert_eq!(tokens, vec![("## Title".into(), TokenKind::HEADING)]);
+        let (tokens, _) = lex(Language::MARKDOWN, "- use `cargo` and **bold** [docs](http://x) * not", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["-", "`cargo`", "**bold**", "[docs](http://x)"]);
+        let (_, state) = lex(Language::MARKDOWN, "```rust", LineState::NORMAL);
+        assert_eq!(state, LineState::CODE);
+        let (tokens, state) = lex(Language::MARKDOWN, "# not a heading", state);
+        assert_eq!(tokens[0].1, TokenKind::STRING);
+        let (_, state) = lex(Language::MARKDOWN, "```", state);
+        assert_eq!(state, LineState::NORMAL);
+    }
+
+    #[test]
+    fn shell_test() {
+        let (tokens, _) = lex(Language::SHELL, "if [ -n \"$HOME\" ]; then echo ${PATH} $1 # note", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["if", "\"$HOME\"", "then", "echo", "${PATH}", "$1", "# note"]);
+        let (tokens, state) = lex(Language::SHELL, "NAME=value cmd 'open", LineState::NORMAL);
+        assert_eq!(text(&tokens), vec!["NAME", "'open"]);
+        assert_eq!(state, LineState::STRING('\'', 1));
+        let (tokens, state) = lex(Language::SHELL, "closed' a#b", state);
+        assert_eq!(text(&tokens), vec!["closed'"]);
+        assert_eq!(state, LineState::NORMAL);
+    }
+
+    #[test]
+    fn from_path_test() {
+        assert_eq!(Language::from_path(Path::new("src/main.rs")), Some(Language::RUST));
+        assert_eq!(Language::from_path(Path::new("Cargo.toml")), Some(Language::TOML));
+        assert_eq!(Language::from_path(Path::new("README.MD")), Some(Language::MARKDOWN));
+        assert_eq!(Language::from_path(Path::new("notes.txt")), None);
+        assert_eq!(Language::from_path(Path::new("Makefile")), None);
+    }
+}
\ No newline at end of file
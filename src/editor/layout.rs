@@ -0,0 +1,166 @@
+use crate::vector::Vector2D;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SplitDirection {
+    /// One pane above the other, `:split`
+    HORIZONTAL,
+    /// Panes side by side, `:vsplit`
+    VERTICAL,
+}
+
+/// Part of the window given to a pane
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Area {
+    pub pos: Vector2D,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Area {
+    pub fn new(pos: Vector2D, width: u32, height: u32) -> Self {
+        Self { pos, width, height }
+    }
+
+    /// Divides the area in half, leaving gap pixels between the halves
+    fn split(self, direction: SplitDirection, gap: u32) -> (Self, Self) {
+        let Vector2D { x, y } = self.pos;
+        match direction {
+            SplitDirection::HORIZONTAL => {
+                let first_height = self.height.saturating_sub(gap) / 2;
+                let second_height = self.height.saturating_sub(first_height + gap);
+                (
+                    Self::new(self.pos, self.width, first_height),
+                    Self::new(Vector2D::new(x, y + first_height + gap), self.width, second_height),
+                )
+            },
+            SplitDirection::VERTICAL => {
+                let first_width = self.width.saturating_sub(gap) / 2;
+                let second_width = self.width.saturating_sub(first_width + gap);
+                (
+                    Self::new(self.pos, first_width, self.height),
+                    Self::new(Vector2D::new(x + first_width + gap, y), second_width, self.height),
+                )
+            },
+        }
+    }
+}
+
+/// Binary tree of panes, each split divides its area in half
+///
+/// Panes are referred to by their index in the editor's pane list
+#[derive(Debug, PartialEq)]
+pub enum Layout {
+    PANE(usize),
+    SPLIT(SplitDirection, Box<Layout>, Box<Layout>),
+}
+
+impl Layout {
+    pub fn new(pane: usize) -> Self {
+        Self::PANE(pane)
+    }
+
+    /// Splits pane in two, new_pane gets the bottom or right half
+    ///
+    /// Returns false if pane is not in the layout
+    pub fn split(&mut self, pane: usize, new_pane: usize, direction: SplitDirection) -> bool {
+        match self {
+            Self::PANE(id) if *id == pane => {
+                *self = Self::SPLIT(direction, Box::new(Self::PANE(pane)), Box::new(Self::PANE(new_pane)));
+                true
+            },
+            Self::PANE(_) => false,
+            Self::SPLIT(_, first, second) => first.split(pane, new_pane, direction) || second.split(pane, new_pane, direction),
+        }
+    }
+
+    /// Removes pane and gives its space to the other half of the split,
+    /// panes after it move down one index to match the pane list
+    pub fn remove(&mut self, pane: usize) {
+        self.collapse(pane);
+        self.renumber(pane);
+    }
+
+    /// Returns the area of every pane inside area, in pane order from left to right and top to bottom
+    pub fn arrange(&self, area: Area, gap: u32) -> Vec<(usize, Area)> {
+        let mut areas = Vec::new();
+        self.arrange_into(area, gap, &mut areas);
+        areas
+    }
+
+    fn arrange_into(&self, area: Area, gap: u32, areas: &mut Vec<(usize, Area)>) {
+        match self {
+            Self::PANE(id) => areas.push((*id, area)),
+            Self::SPLIT(direction, first, second) => {
+                let (first_area, second_area) = area.split(*direction, gap);
+                first.arrange_into(first_area, gap, areas);
+                second.arrange_into(second_area, gap, areas);
+            },
+        }
+    }
+
+    fn collapse(&mut self, pane: usize) -> bool {
+        let Self::SPLIT(_, first, second) = self else {
+            return false;
+        };
+        let kept = if **first == Self::PANE(pane) {
+            second
+        } else if **second == Self::PANE(pane) {
+            first
+        } else {
+            return first.collapse(pane) || second.collapse(pane);
+        };
+        let kept = std::mem::replace(kept.as_mut(), Self::PANE(pane));
+        *self = kept;
+        true
+    }
+
+    fn renumber(&mut self, removed: usize) {
+        match self {
+            Self::PANE(id) => {
+                if *id > removed {
+                    *id -= 1;
+                }
+            },
+            Self::SPLIT(_, first, second) => {
+                first.renumber(removed);
+                second.renumber(removed);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Area = Area { pos: Vector2D { x: 0, y: 10 }, width: 100, height: 51 };
+
+    #[test]
+    fn arrange_test() {
+        let mut layout = Layout::new(0);
+        assert_eq!(layout.arrange(AREA, 1), vec![(0, AREA)]);
+
+        assert!(layout.split(0, 1, SplitDirection::VERTICAL));
+        assert!(layout.split(1, 2, SplitDirection::HORIZONTAL));
+        assert!(!layout.split(3, 4, SplitDirection::HORIZONTAL));
+        assert_eq!(layout.arrange(AREA, 1), vec![
+            (0, Area::new(Vector2D::new(0, 10), 49, 51)),
+            (1, Area::new(Vector2D::new(50, 10), 50, 25)),
+            (2, Area::new(Vector2D::new(50, 36), 50, 25)),
+        ]);
+    }
+
+    #[test]
+    fn remove_test() {
+        let mut layout = Layout::new(0);
+        layout.split(0, 1, SplitDirection::VERTICAL);
+        layout.split(0, 2, SplitDirection::HORIZONTAL);
+
+        layout.remove(1);
+        assert_eq!(layout, Layout::SPLIT(SplitDirection::HORIZONTAL, Box::new(Layout::PANE(0)), Box::new(Layout::PANE(1))));
+
+        layout.remove(0);
+        assert_eq!(layout, Layout::PANE(0));
+        assert_eq!(layout.arrange(AREA, 1), vec![(0, AREA)]);
+    }
+}
\ No newline at end of file
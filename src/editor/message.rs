@@ -0,0 +1,93 @@
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+const INFO_DURATION: Duration = Duration::from_secs(3);
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MessageLevel {
+    INFO,
+    ERROR,
+}
+
+pub struct Message {
+    text: String,
+    level: MessageLevel,
+}
+
+impl Message {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn level(&self) -> MessageLevel {
+        self.level
+    }
+
+    fn duration(&self) -> Duration {
+        match self.level {
+            MessageLevel::INFO => INFO_DURATION,
+            MessageLevel::ERROR => ERROR_DURATION,
+        }
+    }
+}
+
+/// Notifications shown to the user one at a time in the console area
+#[derive(Default)]
+pub struct Messages {
+    queue: VecDeque<Message>,
+    shown_at: Option<Instant>,
+}
+
+impl Messages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(text.into(), MessageLevel::INFO);
+    }
+
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(text.into(), MessageLevel::ERROR);
+    }
+
+    pub fn current(&self) -> Option<&Message> {
+        self.queue.front()
+    }
+
+    /// Advances to the next message once the current one has been shown long enough
+    ///
+    /// Returns true if the shown message changed
+    pub fn update(&mut self) -> bool {
+        let Some(current) = self.queue.front() else {
+            return false;
+        };
+        let shown_at = *self.shown_at.get_or_insert_with(Instant::now);
+        if shown_at.elapsed() < current.duration() {
+            return false;
+        }
+        self.queue.pop_front();
+        self.shown_at = self.queue.front().map(|_| Instant::now());
+        true
+    }
+
+    /// When the shown message is due to be replaced
+    pub fn deadline(&self) -> Option<Instant> {
+        let current = self.queue.front()?;
+        Some(self.shown_at.unwrap_or_else(Instant::now) + current.duration())
+    }
+
+    fn push(&mut self, text: String, level: MessageLevel) {
+        let error = level == MessageLevel::ERROR;
+        let shown_dropped = error && self.queue.front().is_some_and(|current| current.level == MessageLevel::INFO);
+        // Errors jump ahead of pending info so a failure is never hidden behind older news
+        if error {
+            self.queue.retain(|message| message.level == MessageLevel::ERROR);
+        }
+        // Whatever takes the place of the shown message gets its full time
+        if self.queue.is_empty() || shown_dropped {
+            self.shown_at = Some(Instant::now());
+        }
+        self.queue.push_back(Message { text, level });
+    }
+}
\ No newline at end of file
@@ -0,0 +1,22 @@
+use crate::editor::{layout::Area, textbox::TextView};
+
+/// Part of the text area showing one buffer with its own cursor and scroll position
+pub struct Pane {
+    pub buffer: usize,
+    /// Parked cursor and scroll position, the focused pane's own view lives in its buffer's text box
+    pub view: TextView,
+    pub area: Area,
+    /// Text revision of the buffer when the pane was last drawn, edits made through another pane repaint it
+    pub revision: usize,
+}
+
+impl Pane {
+    pub fn new(buffer: usize, view: TextView, area: Area) -> Self {
+        Self {
+            buffer,
+            view,
+            area,
+            revision: 0,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,166 @@
+use std::path::PathBuf;
+
+use sdl3::{event::Event, keyboard::Keycode};
+
+/// Something that would throw away the current buffer
+pub enum PendingAction {
+    /// Close the editor, the indices are buffers whose unsaved changes the user already chose to discard
+    QUIT(Vec<usize>),
+    /// Replace the buffer at the index with the contents of the swap file at the path
+    RECOVER(usize, PathBuf),
+}
+
+pub enum PromptChoice {
+    SAVE,
+    DISCARD,
+    RECOVER,
+    CANCEL,
+}
+
+/// A question shown in the console that blocks input until answered
+pub struct Prompt {
+    /// Index of the buffer the question is about, clicking another tab does not change it
+    buffer: usize,
+    action: PendingAction,
+    message: String,
+}
+
+impl Prompt {
+    pub fn unsaved_changes(document_name: &str, buffer: usize, action: PendingAction) -> Self {
+        let verb = match action {
+            PendingAction::QUIT(_) => "quitting",
+            PendingAction::RECOVER(..) => "recovering",
+        };
+        Self {
+            buffer,
+            action,
+            message: format!("{document_name} has unsaved changes before {verb}: [S]ave, [D]iscard or [C]ancel?"),
+        }
+    }
+
+    pub fn recover_swap(document_name: &str, buffer: usize, swap_path: PathBuf) -> Self {
+        Self {
+            buffer,
+            action: PendingAction::RECOVER(buffer, swap_path),
+            message: format!("{document_name} has unsaved changes from a crash: [R]ecover, [D]elete them or [C]ancel?"),
+        }
+    }
+
+    pub fn choice(&self, keycode: Keycode) -> Option<PromptChoice> {
+        match (&self.action, keycode) {
+            (PendingAction::RECOVER(..), Keycode::R) => Some(PromptChoice::RECOVER),
+            (PendingAction::QUIT(_), Keycode::S) => Some(PromptChoice::SAVE),
+            (_, Keycode::D) => Some(PromptChoice::DISCARD),
+            (_, Keycode::C | Keycode::Escape) => Some(PromptChoice::CANCEL),
+            _ => None,
+        }
+    }
+
+    pub fn buffer(&self) -> usize {
+        self.buffer
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn into_action(self) -> PendingAction {
+        self.action
+    }
+}
+
+/// Drops the text typed by the key that answered a prompt
+///
+/// SDL sends a key's TextInput after its KeyDown, so it arrives once the prompt is already gone
+#[derive(Default)]
+pub struct AnswerFilter {
+    swallow_text: bool,
+}
+
+impl AnswerFilter {
+    /// Call once a key answered the prompt
+    pub fn answered(&mut self) {
+        self.swallow_text = true;
+    }
+
+    /// Returns true if event is the text of the answer, any other key event ends the wait for it
+    pub fn swallows(&mut self, event: &Event) -> bool {
+        match event {
+            Event::TextInput { .. } => std::mem::take(&mut self.swallow_text),
+            Event::KeyDown { .. } | Event::KeyUp { .. } => {
+                self.swallow_text = false;
+                false
+            },
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sdl3::keyboard::Mod;
+
+    use super::*;
+
+    fn key_down(keycode: Keycode) -> Event {
+        Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+            which: 0,
+            raw: 0,
+        }
+    }
+
+    fn key_up(keycode: Keycode) -> Event {
+        Event::KeyUp {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: None,
+            keymod: Mod::NOMOD,
+            repeat: false,
+            which: 0,
+            raw: 0,
+        }
+    }
+
+    fn text_input(text: &str) -> Event {
+        Event::TextInput { timestamp: 0, window_id: 0, text: text.to_string() }
+    }
+
+    /// Feeds events the way the editor does, the prompt takes its answer and the rest is typed into buffer
+    fn type_events(prompt: &mut Option<Prompt>, filter: &mut AnswerFilter, buffer: &mut String, events: Vec<Event>) {
+        for event in events {
+            if filter.swallows(&event) {
+                continue;
+            }
+            if let Some(current) = prompt {
+                if let Event::KeyDown { keycode: Some(keycode), .. } = event && current.choice(keycode).is_some() {
+                    *prompt = None;
+                    filter.answered();
+                }
+                continue;
+            }
+            if let Event::TextInput { text, .. } = event {
+                buffer.push_str(&text);
+            }
+        }
+    }
+
+    #[test]
+    fn answer_not_typed_test() {
+        let mut prompt = Some(Prompt::unsaved_changes("notes.txt", 0, PendingAction::QUIT(Vec::new())));
+        let mut filter = AnswerFilter::default();
+        let mut buffer = String::from("saved");
+        type_events(&mut prompt, &mut filter, &mut buffer, vec![key_down(Keycode::S), text_input("s")]);
+        assert!(prompt.is_none());
+        assert_eq!(buffer, "saved");
+
+        type_events(&mut prompt, &mut filter, &mut buffer, vec![key_up(Keycode::S), key_down(Keycode::A), text_input("a")]);
+        assert_eq!(buffer, "saveda");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,81 @@
+use std::{error::Error, path::Path};
+
+use sdl3::{render::{Canvas, FRect}, video::Window};
+
+use crate::{editor::{config::Config, textbox::TextBox, theme::Theme}, vector::Vector2D};
+
+/// Strip above the text area with one tab per open buffer
+pub struct TabBar<'a> {
+    bar: TextBox<'a>,
+    labels: Vec<String>,
+    current: usize,
+    /// Area covered by each tab the last time the bar was drawn
+    tabs: Vec<FRect>,
+}
+
+impl <'a> TabBar<'a> {
+    pub fn new(bar: TextBox<'a>) -> Self {
+        Self {
+            bar,
+            labels: Vec::new(),
+            current: 0,
+            tabs: Vec::new(),
+        }
+    }
+
+    /// Redraws the bar if a buffer was opened, switched to or changed its label
+    pub fn update(&mut self, labels: Vec<String>, current: usize) {
+        if labels != self.labels || current != self.current {
+            self.labels = labels;
+            self.current = current;
+            self.bar.request_render();
+        }
+    }
+
+    pub fn set_config(&mut self, config: &Config) -> Result<(), Box<dyn Error>> {
+        self.bar.set_config(config)
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.bar.set_theme(theme);
+        self.bar.set_background_color(theme.tab_bar);
+    }
+
+    pub fn request_render(&mut self) {
+        self.bar.request_render();
+    }
+
+    pub fn should_render(&self) -> bool {
+        self.bar.should_render()
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>) -> Result<(), Box<dyn Error>> {
+        self.tabs = self.bar.draw_tabs(canvas, &self.labels, self.current)?;
+        Ok(())
+    }
+
+    /// Returns the index of the tab under the point
+    pub fn tab_at(&self, x: f32, y: f32) -> Option<usize> {
+        self.tabs.iter().position(|tab| x >= tab.x && x < tab.x + tab.w && y >= tab.y && y < tab.y + tab.h)
+    }
+
+    pub fn click_in_window(&self, x: f32, y: f32) -> bool {
+        self.bar.click_in_window(x, y)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.bar.height_of_one_line()
+    }
+
+    pub fn resize(&mut self, pos: Vector2D, width: i32, height: i32) {
+        self.bar.resize(pos, width, height);
+    }
+
+    pub fn set_font_path(&mut self, font_path: &Path) -> Result<(), Box<dyn Error>> {
+        self.bar.set_font_path(font_path)
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) -> Result<(), Box<dyn Error>> {
+        self.bar.set_font_size(font_size)
+    }
+}
\ No newline at end of file
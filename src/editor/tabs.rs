@@ -0,0 +1,133 @@
+use crate::editor::editorconfig::IndentStyle;
+
+/// Lines looked at when guessing how a file is indented
+const DETECT_LINES: usize = 1000;
+
+/// Cells taken by c when it starts at column col, a tab reaches the next tab stop
+pub fn char_width(c: char, col: usize, tab_width: usize) -> usize {
+    match c {
+        '\t' => tab_width.max(1) - col % tab_width.max(1),
+        _ => 1,
+    }
+}
+
+/// Column on screen of the character at char offset x of the line made of chars, x may be the end of the line
+pub fn col_of(chars: impl IntoIterator<Item = char>, x: usize, tab_width: usize) -> usize {
+    chars.into_iter()
+        .take(x)
+        .fold(0, |col, c| col + char_width(c, col, tab_width))
+}
+
+/// Columns taken by the whole line
+pub fn width(chars: impl IntoIterator<Item = char>, tab_width: usize) -> usize {
+    col_of(chars, usize::MAX, tab_width)
+}
+
+/// Char offset of the character covering column col, the end of the line if col is past it
+pub fn char_at(chars: impl IntoIterator<Item = char>, col: usize, tab_width: usize) -> usize {
+    let mut current = 0;
+    let mut len = 0;
+    for c in chars {
+        current += char_width(c, current, tab_width);
+        if current > col {
+            return len;
+        }
+        len += 1;
+    }
+    len
+}
+
+/// Char offset of the character boundary nearest to the fractional column col, as when clicking
+pub fn char_near(chars: impl IntoIterator<Item = char>, col: f32, tab_width: usize) -> usize {
+    let mut current = 0;
+    let mut len = 0;
+    for c in chars {
+        let next = current + char_width(c, current, tab_width);
+        if col < (current + next) as f32 / 2.0 {
+            return len;
+        }
+        current = next;
+        len += 1;
+    }
+    len
+}
+
+/// Guesses the indentation of a text from the first of its lines that start with whitespace
+///
+/// Returns the style and, for spaces, the most common step between a line and a deeper one after it
+pub fn detect_indent<S: AsRef<str>>(lines: impl Iterator<Item = S>) -> Option<(IndentStyle, Option<u32>)> {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut steps = [0; 9];
+    let mut prev_spaces = 0;
+    for line in lines.take(DETECT_LINES) {
+        let line = line.as_ref();
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+        let spaces = line.chars().take_while(|&c| c == ' ').count();
+        if spaces > 1 {
+            space_lines += 1;
+        }
+        if spaces > prev_spaces && spaces - prev_spaces < steps.len() {
+            steps[spaces - prev_spaces] += 1;
+        }
+        prev_spaces = spaces;
+    }
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines > space_lines {
+        return Some((IndentStyle::TAB, None));
+    }
+    // Single space steps are usually alignment, not indentation
+    let step = (2..steps.len()).filter(|&step| steps[step] > 0).max_by_key(|&step| (steps[step], step));
+    Some((IndentStyle::SPACE, step.map(|step| step as u32)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn col_test() {
+        assert_eq!(col_of("\tab".chars(), 1, 4), 4);
+        assert_eq!(col_of("ab\tc".chars(), 3, 4), 4);
+        assert_eq!(col_of("ab\tc".chars(), 4, 8), 9);
+        assert_eq!(col_of("abcd\t".chars(), 5, 4), 8);
+        assert_eq!(width("a\t\tb".chars(), 4), 9);
+        assert_eq!(width("".chars(), 4), 0);
+    }
+
+    #[test]
+    fn char_at_test() {
+        assert_eq!(char_at("\tab".chars(), 0, 4), 0);
+        assert_eq!(char_at("\tab".chars(), 3, 4), 0);
+        assert_eq!(char_at("\tab".chars(), 4, 4), 1);
+        assert_eq!(char_at("\tab".chars(), 5, 4), 2);
+        assert_eq!(char_at("\tab".chars(), 9, 4), 3);
+        for x in 0..=4 {
+            assert_eq!(char_at("a\tbc".chars(), col_of("a\tbc".chars(), x, 4), 4), x);
+        }
+    }
+
+    #[test]
+    fn char_near_test() {
+        assert_eq!(char_near("\tab".chars(), 1.9, 4), 0);
+        assert_eq!(char_near("\tab".chars(), 2.1, 4), 1);
+        assert_eq!(char_near("\tab".chars(), 4.6, 4), 2);
+        assert_eq!(char_near("\tab".chars(), 20.0, 4), 3);
+    }
+
+    #[test]
+    fn detect_test() {
+        assert_eq!(detect_indent("fn main() {\n\tlet a = 1;\n\tif a {\n\t\tb();\n\t}\n}".lines()), Some((IndentStyle::TAB, None)));
+        assert_eq!(detect_indent("a:\n  b:\n    c: 1\n  d: 2\n".lines()), Some((IndentStyle::SPACE, Some(2))));
+        assert_eq!(detect_indent("fn f() {\n    g(a,\n      b);\n    if x {\n        y();\n    }\n}".lines()), Some((IndentStyle::SPACE, Some(4))));
+        assert_eq!(detect_indent("no\nindentation\n".lines()), None);
+    }
+}
\ No newline at end of file
@@ -1,22 +1,48 @@
-use std::error::Error;
+use std::{borrow::Cow, error::Error, path::{Path, PathBuf}, time::Instant};
 
-use sdl3::{event::Event, keyboard::Keycode, pixels::Color, render::{Canvas, FRect, TextureCreator, TextureQuery}, ttf::{Font, FontStyle, Sdl3TtfContext}, video::{Window, WindowContext}, VideoSubsystem};
+use sdl3::{event::Event, keyboard::Keycode, pixels::Color, render::{Canvas, FPoint, FRect, TextureCreator}, ttf::{Font, FontStyle, Sdl3TtfContext}, video::{Window, WindowContext}, VideoSubsystem};
 
-use crate::{editor::{command::Command, cursor::Cursor, draw, inputstate::InputState, textrope::TextRope, windowstate::WindowState, TextAlignment}, vector::Vector2D};
+use crate::{editor::{command::Command, config::Config, cursor::Cursor, draw, editorconfig::IndentStyle, glyphatlas::GlyphAtlas, gutter::LineNumbers, highlight::{self, Highlighter, Language}, inputstate::InputState, tabs, textrope::{Rope, RopeSlice, TextRope}, theme::Theme, windowstate::{Damage, WindowState}, wrap::Row, TextAlignment}, vector::Vector2D};
 
-const DEFAULT_FONT_PATH: &str = r"C:\Windows\Fonts\consola.ttf";
-const DEFAULT_FONT_SIZE: f32 = 24.0;
-const MAX_FONT_SIZE: f32 = 126.0;
-const MIN_FONT_SIZE: f32 = 12.0;
-const FONT_ZOOM_INCREMENT: f32 = 2.0;
 const DEFAULT_FONT_STYLE: FontStyle = FontStyle::NORMAL;
-const DEFAULT_BACKGROUND_COLOR: Color = Color::RGB(20, 20, 20);
-pub const DEFAULT_FONT_COLOR: Color = Color::RGB(180, 225, 225);
-const DEFAULT_FONT_SELECT_COLOR: Color = Color::RGB(80, 80, 80);
-const DEFAULT_TEXT_PADDING: u32 = 16;
-const DEFAULT_LINE_PADDING: u32 = 2;
-const TAB_SPACE_COUNT: u32 = 4;
-const TAB_SPACE_STRING: &str = "    ";
+
+/// Cursor and scroll position of a pane, swapped into a text box to edit or draw through it
+#[derive(Clone)]
+pub struct TextView {
+    cursor: Cursor,
+    window: WindowState,
+}
+
+impl TextView {
+    pub fn should_render(&self) -> bool {
+        self.window.has_damage()
+    }
+
+    pub fn request_render(&mut self) {
+        self.window.set_render_flag();
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.window.set_wrap(wrap);
+    }
+
+    pub fn resize(&mut self, pos: Vector2D, width: i32, height: i32) {
+        self.window.resize(pos, width, height);
+    }
+
+    pub fn click_in_window(&self, x: f32, y: f32) -> bool {
+        self.window.is_in_screen_bound(x.ceil() as u32, y.ceil() as u32)
+    }
+}
+
+/// What an input can change, compared before and after it to find the lines to repaint
+#[derive(PartialEq)]
+struct EditState {
+    cursor: Vector2D,
+    select: Option<Vector2D>,
+    revision: usize,
+    line_count: usize,
+}
 
 pub struct TextBox<'a> {
     active: bool,
@@ -24,11 +50,21 @@ pub struct TextBox<'a> {
     window: WindowState,
     cursor: Cursor,
     font: Font<'a>,
+    font_path: PathBuf,
     font_size: f32,
-
+    glyphs: GlyphAtlas<'a>,
+    line_numbers: LineNumbers,
+    highlighter: Highlighter,
+    config: Config,
+    /// Indentation of this file where it differs from the config, from `.editorconfig` or a guess at the text
+    indent_style: Option<IndentStyle>,
+    /// Columns per indent level, None for the tab width
+    indent_width: Option<u32>,
+    tab_width: Option<u32>,
+
+    theme: Theme,
+    /// Theme background, or the color of the console or tab bar for the boxes drawing those
     background_color: Color,
-    font_color: Color,
-    font_select_color: Color,
 
     /// Context
     video_subsystem: &'a VideoSubsystem,
@@ -40,23 +76,26 @@ impl <'a> TextBox<'a> {
         pos: Vector2D,
         window_width: u32,
         window_height: u32,
-        background_color: Option<Color>,
+        config: &Config,
+        theme: &Theme,
+        font_path: &Path,
         video_subsystem: &'a VideoSubsystem,
         ttf_context: &'a Sdl3TtfContext,
+        texture_creator: &'a TextureCreator<WindowContext>,
     ) -> Result<Self, Box<dyn Error>> {
-        let mut default_font = ttf_context.load_font(DEFAULT_FONT_PATH, DEFAULT_FONT_SIZE)?;
-        default_font.set_style(DEFAULT_FONT_STYLE);
+        let default_font = load_font(ttf_context, font_path, config.font_size, DEFAULT_FONT_STYLE)?;
         let (text_width, text_height) = default_font.size_of_char('|')?;
 
-        let window = WindowState::new(
+        let mut window = WindowState::new(
             pos,
             window_width,
             window_height,
             text_width,
             text_height,
-            DEFAULT_TEXT_PADDING,
-            DEFAULT_LINE_PADDING,
+            config.text_padding,
+            config.line_padding,
         );
+        window.set_tab_width(config.tab_width as usize);
 
         Ok(
             Self {
@@ -65,11 +104,18 @@ impl <'a> TextBox<'a> {
                 window,
                 cursor: Cursor::new(),
                 font: default_font,
-                font_size: DEFAULT_FONT_SIZE,
-
-                background_color: background_color.unwrap_or(DEFAULT_BACKGROUND_COLOR),
-                font_color: DEFAULT_FONT_COLOR,
-                font_select_color: DEFAULT_FONT_SELECT_COLOR,
+                font_path: font_path.to_path_buf(),
+                font_size: config.font_size,
+                glyphs: GlyphAtlas::new(texture_creator, config.font_size),
+                line_numbers: LineNumbers::OFF,
+                highlighter: Highlighter::new(),
+                config: config.clone(),
+                indent_style: None,
+                indent_width: None,
+                tab_width: None,
+
+                theme: theme.clone(),
+                background_color: theme.background,
 
                 video_subsystem,
                 ttf_context,
@@ -80,15 +126,18 @@ impl <'a> TextBox<'a> {
 
 impl <'a> TextBox<'a> {
     pub fn execute_cmd(&mut self, cmd: Command) {
+        let before = self.edit_state();
         match cmd {
             Command::JUMP(col, line) => self.cursor.snap_to_pos(col, line, &self.text, &mut self.window),
             _ => {},
         }
+        self.damage_changes(before);
     }
 }
 
 impl <'a> TextBox<'a> {
     pub fn handle_input(&mut self, event: Event, input: &InputState) -> Result<(), Box<dyn Error>> {
+        let before = self.edit_state();
         match event {
             // Keyboard input
             Event::KeyDown { keycode: Some(Keycode::Home), .. } => self.cursor.home(&input, &self.text, &mut self.window),
@@ -125,110 +174,138 @@ impl <'a> TextBox<'a> {
             
             _ => {},
         }
+        self.damage_changes(before);
 
         Ok(())
     }
 
     pub fn activate(&mut self) {
         self.active = true;
+        self.damage_cursor();
     }
 
     pub fn deactivate(&mut self) {
         self.active = false;
+        self.damage_cursor();
     }
 
     pub fn update(&mut self) {
         if self.active {
-            self.cursor.update(&mut self.window);
+            self.cursor.update(&mut self.window, self.config.blink_period);
         }
     }
 
-    pub fn should_render(&mut self) -> bool {
-        self.window.check_render()
+    /// When the cursor blinks next, None if the box is not focused
+    pub fn next_blink(&self) -> Option<Instant> {
+        self.active.then(|| self.cursor.next_blink(self.config.blink_period))
+    }
+
+    pub fn should_render(&self) -> bool {
+        self.window.has_damage()
     }
 
     pub fn resize(&mut self, pos: Vector2D, width: i32, height: i32) {
         self.window.resize(pos, width, height);
     }
 
-    pub fn draw(&mut self, canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>) -> Result<(), Box<dyn Error>> {
-        canvas.set_draw_color(self.background_color);
+    /// Repaints the lines that changed since the last draw, or the whole box after a scroll or resize
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>) -> Result<(), Box<dyn Error>> {
+        self.update_gutter();
+        self.window.clamp_start_row(&self.text);
+        let rows = self.window.rows(&self.text);
+        self.update_highlights(&rows);
         let (x, y) = self.window.get_pos().into();
         let (screen_w, screen_h) = self.window.get_window_dim();
-        canvas.fill_rect(FRect::new(x as f32, y as f32, screen_w as f32, screen_h as f32))?;
-
+        let (first_damaged, last_damaged) = match self.window.take_damage() {
+            Damage::NONE => return Ok(()),
+            Damage::LINES(first, last) => (first, last),
+            Damage::FULL => {
+                canvas.set_draw_color(self.background_color);
+                canvas.fill_rect(FRect::new(x as f32, y as f32, screen_w as f32, screen_h as f32))?;
+                (0, usize::MAX)
+            },
+        };
 
         let (text_padding, line_padding) = self.window.get_padding();
         let pos = self.window.pos();
         let mut start_y = text_padding + pos.y;
-        let (_, height) = self.window.get_text_dim();
+        let (text_width, height) = self.window.get_text_dim();
         let height = height as u32;
 
-        for (line_num, line_text) in self.text.lines().enumerate().skip(self.window.get_first_line()).take(self.window.lines()) {
-            let focused_text = line_text.chars().skip(self.window.get_first_char()).take(self.window.chars()).collect::<String>();
+        let gutter_width = self.window.gutter_width();
+        let cursor_line = self.cursor.pos().y as usize;
+        let first_line = rows.first().map_or(0, |row| row.line);
+        let line_count = rows.last().map_or(0, |row| row.line + 1 - first_line);
+        // Drawing needs the text box mutably, the lines are borrowed from a snapshot of the text instead
+        let text = self.text.snapshot();
+        let lines = text.lines().skip(first_line).take(line_count).collect::<Vec<_>>();
+        for screen_row in 0..self.window.lines() {
+            let row = rows.get(screen_row);
+            // Rows past the end of the text only repaint when every line below a change does
+            let line_num = row.map_or(usize::MAX, |row| row.line);
+            if line_num < first_damaged || line_num > last_damaged {
+                start_y += height + line_padding;
+                continue;
+            }
+            // Rows past the end of the text are cleared too, they may hold lines that were just removed
+            canvas.set_draw_color(self.background_color);
+            canvas.fill_rect(FRect::new(x as f32, start_y as f32, screen_w as f32, (height + line_padding) as f32))?;
+            let Some(row) = row else {
+                start_y += height + line_padding;
+                continue;
+            };
+
+            if gutter_width > 0 && (row.start == 0 || !self.window.is_wrapped()) {
+                let label = self.line_numbers.label(line_num, cursor_line);
+                let label_x = (pos.x + text_padding + gutter_width).saturating_sub(text_width as u32 + self.text_width(&label));
+                let color = if line_num == cursor_line { self.theme.foreground } else { self.theme.gutter };
+                self.glyphs.draw_text(canvas, &self.font, &label, FPoint::new(label_x as f32, start_y as f32), text_width, color)?;
+            }
+
+            let line_text = lines[line_num - first_line];
             draw::selection_box(
                 canvas,
                 &self.cursor,
                 &self.window,
-                line_num,
-                focused_text.chars().count(),
-                self.font_select_color,
+                row,
+                line_text,
+                screen_row as u32,
+                self.theme.selection,
             )?;
 
-            let text_to_render = if focused_text.len() != 0 {
-                focused_text
-            } else {
-                String::from(" ")
-            };
-
-            let surface = self
-                .font
-                .render(&text_to_render)
-                .blended(self.font_color)
-                .map_err(|err| format!("On line: {:?}: {}", text_to_render, err))?;
-            let texture = texture_creator
-                .create_texture_from_surface(&surface)?;
-
-            let TextureQuery {width, .. } = texture.query();
-
             let target = draw::text_target_aligned(
                 &TextAlignment::LEFT,
                 text_padding,
-                pos.x,
+                pos.x + gutter_width,
                 start_y,
-                width,
+                (self.window.chars() as f32 * text_width) as u32,
                 height,
                 screen_w
             );
-            canvas.copy(&texture, None, Some(target.into()))?;
+            let text_pos = FPoint::new(target.x() as f32, target.y() as f32);
+            self.draw_row_text(canvas, row, line_text, text_pos)?;
 
             start_y += height + line_padding;
         }
-        self.cursor.draw(self.active, canvas, &self.window)?;
+        self.cursor.draw(self.active, canvas, &self.window, &self.text, self.theme.cursor)?;
 
         Ok(())
     }
 
-    pub fn draw_console(&mut self, canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>) -> Result<(), Box<dyn Error>> {
+    pub fn draw_console(&mut self, canvas: &mut Canvas<Window>, status: &str) -> Result<(), Box<dyn Error>> {
         let cursor_pos @ Vector2D { x, y } = self.cursor.pos();
+        // Columns count tabs as far as the tab stop they reach, like the text is drawn
+        let col = self.text.line(y as usize).map_or(x as usize, |line| tabs::col_of(line.chars(), x as usize, self.tab_width() as usize));
         let cursor_pos_str = if let Some(select_pos) = self.cursor.select_start_pos() {
             let select_start = calculate_index_from_pos(&self.text, select_pos);
             let current_index = calculate_index_from_pos(&self.text, cursor_pos);
             let len = select_start.abs_diff(current_index);
             let selected_str_count = len;
-            format!("Ln: {}, Col {} ({} Selected)", y + 1, x + 1, selected_str_count)
+            format!("Ln: {}, Col {} ({} Selected)   {}", y + 1, col + 1, selected_str_count, status)
         } else {
-            format!("Ln: {}, Col {}", y + 1, x + 1)
+            format!("Ln: {}, Col {}   {}", y + 1, col + 1, status)
         };
-        let surface = self
-                .font
-                .render(&cursor_pos_str)
-                .blended(self.font_color)
-                .map_err(|err| format!("On line: {:?}: {}", cursor_pos_str, err))?;
-        let texture = texture_creator
-            .create_texture_from_surface(&surface)?;
-
-        let TextureQuery {width, .. } = texture.query();
+        let width = self.text_width(&cursor_pos_str);
         let (_, height) = self.window.get_text_dim();
         let height = height as u32;
         let (text_padding, _) = self.window.get_padding();
@@ -243,28 +320,203 @@ impl <'a> TextBox<'a> {
             height,
             screen_w,
         );
-        canvas.copy(&texture, None, Some(target.into()))?;
+        self.draw_text(canvas, &cursor_pos_str, target.x(), target.y(), self.theme.status)?;
 
         Ok(())
     }
 
-    pub fn set_text(&mut self, text_data: String) {
-        let old_text = std::mem::take(&mut self.text);
-        let total_len = old_text.len();
-        let jump_pos = Vector2D::new(0, 0);
-        self.text = old_text.replace(0, total_len, text_data, jump_pos, &mut self.cursor, &mut self.window);
+    /// Draws a single line of text over the box, used by the console for prompts
+    pub fn draw_message(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        message: &str,
+        color: Option<Color>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.window.take_damage();
+        canvas.set_draw_color(self.background_color);
+        let (x, y) = self.window.get_pos().into();
+        let (screen_w, screen_h) = self.window.get_window_dim();
+        canvas.fill_rect(FRect::new(x as f32, y as f32, screen_w as f32, screen_h as f32))?;
+
+        let width = self.text_width(message);
+        let (_, height) = self.window.get_text_dim();
+        let (text_padding, _) = self.window.get_padding();
+        let target = draw::text_target_aligned(
+            &TextAlignment::LEFT,
+            text_padding,
+            x,
+            y + text_padding,
+            width,
+            height as u32,
+            screen_w,
+        );
+        self.draw_text(canvas, message, target.x(), target.y(), color.unwrap_or(self.theme.foreground))?;
+
+        Ok(())
+    }
+
+    /// Draws one label per tab from left to right, returns the area each tab covers
+    pub fn draw_tabs(
+        &mut self,
+        canvas: &mut Canvas<Window>,
+        labels: &[String],
+        current: usize,
+    ) -> Result<Vec<FRect>, Box<dyn Error>> {
+        self.window.take_damage();
+        canvas.set_draw_color(self.background_color);
+        let (x, y) = self.window.get_pos().into();
+        let (screen_w, screen_h) = self.window.get_window_dim();
+        canvas.fill_rect(FRect::new(x as f32, y as f32, screen_w as f32, screen_h as f32))?;
+
+        let (text_padding, _) = self.window.get_padding();
+        let mut tabs = Vec::with_capacity(labels.len());
+        let mut start_x = x;
+        for (i, label) in labels.iter().enumerate() {
+            let width = self.text_width(label);
+            let tab = FRect::new(start_x as f32, y as f32, (width + text_padding * 2) as f32, screen_h as f32);
+            if i == current {
+                canvas.set_draw_color(self.theme.selection);
+                canvas.fill_rect(tab)?;
+            }
+            self.draw_text(canvas, label, (start_x + text_padding) as i32, (y + text_padding) as i32, self.theme.foreground)?;
+
+            tabs.push(tab);
+            start_x += width + text_padding * 2;
+        }
+
+        Ok(tabs)
+    }
+
+    /// Draws the text as seen through another pane's view, without a cursor
+    pub fn draw_view(&mut self, view: &mut TextView, canvas: &mut Canvas<Window>) -> Result<(), Box<dyn Error>> {
+        let active = self.active;
+        self.active = false;
+        self.swap_view(view);
+        let result = self.draw(canvas);
+        self.swap_view(view);
+        self.active = active;
+        result
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn request_render(&mut self) {
+        self.window.set_render_flag();
+    }
+
+    /// Replaces the text with a freshly opened document, there is nothing to undo afterwards
+    pub fn set_text(&mut self, text: Rope) {
+        self.text.reset(text);
         self.cursor.snap_to_pos(0, 0, &self.text, &mut self.window);
+        self.highlighter.invalidate_from(0);
+        self.window.set_render_flag();
+    }
+
+    /// Turns soft wrapping of long lines on or off, keeping the cursor in view
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.window.set_wrap(wrap);
+        self.cursor.focus_on(&self.text, &mut self.window);
+    }
+
+    /// Takes the zoom limits, padding, tab width, scroll speed and blink rate from config
+    pub fn set_config(&mut self, config: &Config) -> Result<(), Box<dyn Error>> {
+        self.config = config.clone();
+        self.window.set_padding(config.text_padding, config.line_padding);
+        self.window.set_tab_width(self.tab_width() as usize);
+        // Reloading at the same size clamps it to the new limits and lays the text out with the new padding
+        self.set_font_size(self.font_size)
+    }
+
+    /// Sets how this file is indented and how far apart its tab stops are, None keeps the config's choice
+    pub fn set_indent(&mut self, style: Option<IndentStyle>, width: Option<u32>, tab_width: Option<u32>) {
+        self.indent_style = style;
+        self.indent_width = width;
+        self.tab_width = tab_width;
+        self.window.set_tab_width(self.tab_width() as usize);
+    }
+
+    pub fn tab_width(&self) -> u32 {
+        self.tab_width.unwrap_or(self.config.tab_width)
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+        self.background_color = theme.background;
+        self.window.set_render_flag();
+    }
+
+    /// Paints the box in its own color instead of the theme background, used by the console and tab bar
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = color;
+        self.window.set_render_flag();
+    }
+
+    /// Switches the grammar used to color the text, None draws it all in the font color
+    pub fn set_language(&mut self, language: Option<Language>) {
+        self.highlighter.set_language(language);
+        self.window.set_render_flag();
+    }
+
+    pub fn language(&self) -> Option<Language> {
+        self.highlighter.language()
+    }
+
+    /// Shows or hides the line number gutter, and picks how it counts
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+        self.update_gutter();
+        self.window.set_render_flag();
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.text.mark_saved();
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.text.is_modified()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.len() == 0
+    }
+
+    pub fn revision(&self) -> usize {
+        self.text.revision()
+    }
+
+    /// Replaces the whole text as one change that can be undone, the cursor stays where it was
+    pub fn replace_text(&mut self, text_data: String) {
+        let Vector2D { x, y } = self.cursor.pos();
+        let total_len = self.text.len();
+        let jump_pos = Vector2D::new(0, 0);
+        self.text.replace(0, total_len, text_data, jump_pos, &mut self.cursor, &mut self.window);
+        self.cursor.snap_to_pos(x, y, &self.text, &mut self.window);
+        self.highlighter.invalidate_from(0);
+        self.window.set_render_flag();
     }
 
     pub fn export(&self) -> String {
-        self.text.chars().collect()
+        self.text.slice(..).to_string()
+    }
+
+    /// The text as it is now, taken in O(1) and safe to hand to another thread
+    pub fn snapshot(&self) -> Rope {
+        self.text.snapshot()
+    }
+
+    /// Copies out one line at a time, without the newlines
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.text.lines().map(|line| line.to_string())
     }
 
     pub fn extract_text(&mut self) -> String {
         let contents = self.export();
         let len = self.text.len();
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.remove(0, len, &mut self.cursor, &mut self.window);
+        self.text.remove(0, len, &mut self.cursor, &mut self.window);
+        self.highlighter.invalidate_from(0);
+        self.window.set_render_flag();
         contents
     }
 
@@ -272,6 +524,37 @@ impl <'a> TextBox<'a> {
         self.window.is_in_screen_bound(x.ceil() as u32, y.ceil() as u32)
     }
 
+    pub fn view(&self) -> TextView {
+        TextView {
+            cursor: self.cursor.clone(),
+            window: self.window.clone(),
+        }
+    }
+
+    /// Exchanges the cursor and scroll position with view
+    ///
+    /// The cursor is pulled back inside the text if another pane removed the text under it
+    pub fn swap_view(&mut self, view: &mut TextView) {
+        std::mem::swap(&mut self.cursor, &mut view.cursor);
+        std::mem::swap(&mut self.window, &mut view.window);
+        self.window.set_tab_width(self.tab_width() as usize);
+
+        let in_text = |Vector2D { x, y }: Vector2D| self.text.line_len(y as usize).is_some_and(|line_len| x as usize <= line_len);
+        let cursor_pos = self.cursor.pos();
+        if !in_text(cursor_pos) || self.cursor.select_start_pos().is_some_and(|select_pos| !in_text(select_pos)) {
+            self.cursor.snap_to_pos(cursor_pos.x, cursor_pos.y, &self.text, &mut self.window);
+        }
+    }
+
+    /// Matches the view's padding, line height and character width to the current font
+    pub fn fit_view(&self, view: &mut TextView) -> Result<(), Box<dyn Error>> {
+        let (text_padding, line_padding) = self.window.get_padding();
+        view.window.set_padding(text_padding, line_padding);
+        let (text_width, text_height) = self.font.size_of_char('|')?;
+        view.window.resize_text(text_width, text_height);
+        Ok(())
+    }
+
     pub fn height_of_one_line(&self) -> u32 {
         let (_, text_height) = self.window.get_text_dim();
         let (text_padding, line_padding) = self.window.get_padding();
@@ -279,16 +562,27 @@ impl <'a> TextBox<'a> {
     }
 
     pub fn enlarge_text(&mut self) -> Result<(), Box<dyn Error>> {
-        self.font_size = (self.font_size + FONT_ZOOM_INCREMENT).min(MAX_FONT_SIZE);
-        self.font = load_font(&self.ttf_context, DEFAULT_FONT_PATH, self.font_size, DEFAULT_FONT_STYLE)?;
-        let (text_width, text_height) = self.font.size_of_char('|')?;
-        self.window.resize_text(text_width, text_height);
-        Ok(())
+        self.set_font_size(self.font_size + self.config.zoom_step)
     }
 
     pub fn shrink_text(&mut self) -> Result<(), Box<dyn Error>> {
-        self.font_size = (self.font_size - FONT_ZOOM_INCREMENT).max(MIN_FONT_SIZE);
-        self.font = load_font(&self.ttf_context, DEFAULT_FONT_PATH, self.font_size, DEFAULT_FONT_STYLE)?;
+        self.set_font_size(self.font_size - self.config.zoom_step)
+    }
+
+    pub fn font_size(&self) -> f32 {
+        self.font_size
+    }
+
+    /// Switches to the font file at font_path, keeping the size
+    pub fn set_font_path(&mut self, font_path: &Path) -> Result<(), Box<dyn Error>> {
+        self.font_path = font_path.to_path_buf();
+        self.set_font_size(self.font_size)
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) -> Result<(), Box<dyn Error>> {
+        self.font_size = font_size.clamp(self.config.min_font_size, self.config.max_font_size);
+        self.font = load_font(&self.ttf_context, &self.font_path, self.font_size, DEFAULT_FONT_STYLE)?;
+        self.glyphs.reset(self.font_size);
         let (text_width, text_height) = self.font.size_of_char('|')?;
         self.window.resize_text(text_width, text_height);
         Ok(())
@@ -296,36 +590,13 @@ impl <'a> TextBox<'a> {
 
     pub fn find(&mut self, pattern: &str, start_index: usize) -> Option<usize> {
         let pattern_len = pattern.chars().count();
-
-        let after_index_chars_iter = self.text.chars().skip(start_index);
-        let mut pattern_iter = pattern.chars();
-        for (i, c) in after_index_chars_iter.enumerate() {
-            let Some(pattern_c) = pattern_iter.next() else {
-                let (x, y) = self.text.get_line_char_pos(start_index + i - pattern_len).into();
-                self.cursor.snap_to_pos(x, y, &self.text, &mut self.window);
-                self.cursor.select_around_cursor(&self.text, &mut self.window);
-                return Some(start_index + i);
-            };
-            if pattern_c != c {
-                pattern_iter = pattern.chars();
-            }
-        }
-
-        let before_index_chars_iter = self.text.chars().take(start_index + pattern_len);
-        let mut pattern_iter = pattern.chars();
-        for (i, c) in before_index_chars_iter.enumerate() {
-            let Some(pattern_c) = pattern_iter.next() else {
-                let (x, y) = self.text.get_line_char_pos(i - pattern_len).into();
-                self.cursor.snap_to_pos(x, y, &self.text, &mut self.window);
-                self.cursor.select_around_cursor(&self.text, &mut self.window);
-                return Some(i);
-            };
-            if pattern_c != c {
-                pattern_iter = pattern.chars();
-            }
-        }
-    
-        return None;
+        let after_index_chars_iter = self.text.slice(start_index..).chars();
+        let before_index_chars_iter = self.text.slice(..(start_index + pattern_len).min(self.text.len())).chars();
+        let match_end = find_match_end(after_index_chars_iter, pattern)
+            .map(|i| start_index + i)
+            .or_else(|| find_match_end(before_index_chars_iter, pattern))?;
+        self.select_match(match_end - pattern_len);
+        Some(match_end)
     }
 
     pub fn cursor_index(&self) -> usize {
@@ -336,6 +607,115 @@ impl <'a> TextBox<'a> {
 }
 
 impl <'a> TextBox<'a> {
+    fn select_match(&mut self, index: usize) {
+        let before = self.edit_state();
+        let (x, y) = self.text.get_line_char_pos(index).into();
+        self.cursor.snap_to_pos(x, y, &self.text, &mut self.window);
+        self.cursor.select_around_cursor(&self.text, &mut self.window);
+        self.damage_changes(before);
+    }
+
+    fn edit_state(&self) -> EditState {
+        EditState {
+            cursor: self.cursor.pos(),
+            select: self.cursor.select_start_pos(),
+            revision: self.text.revision(),
+            line_count: self.text.line_count(),
+        }
+    }
+
+    /// Damages the lines an input touched, and every line below them if it added or removed lines
+    fn damage_changes(&mut self, before: EditState) {
+        let after = self.edit_state();
+        if after == before {
+            return;
+        }
+        self.update_gutter();
+        if after.revision != before.revision {
+            self.highlighter.invalidate_from(self.text.edited_line());
+        }
+        if self.line_numbers.follows_cursor() && after.cursor.y != before.cursor.y {
+            self.window.set_render_flag();
+            return;
+        }
+        let (before_first, before_last) = lines_between(before.cursor, before.select);
+        let (after_first, after_last) = lines_between(after.cursor, after.select);
+        let first = before_first.min(after_first);
+        // A wrapped line can gain or lose rows with any edit, pushing the lines below it
+        let rows_moved = self.window.is_wrapped() && after.revision != before.revision;
+        if after.line_count != before.line_count || rows_moved {
+            self.window.damage_from(first);
+        } else {
+            self.window.damage_lines(first, before_last.max(after_last));
+        }
+    }
+
+    /// Widens or narrows the gutter to fit the largest line number
+    fn update_gutter(&mut self) {
+        let chars = self.line_numbers.width(self.text.line_count());
+        self.window.set_gutter_chars(chars);
+    }
+
+    /// Lexes the lines that changed down to the last row shown, and damages the lines below whose colors changed with them
+    fn update_highlights(&mut self, rows: &[Row]) {
+        let Some(last) = rows.last().map(|row| row.line) else {
+            return;
+        };
+        let Some(first_stale) = self.highlighter.first_stale(last) else {
+            return;
+        };
+        let lines = self.text.lines().skip(first_stale).take(last + 1 - first_stale).map(Cow::from);
+        if let Some(changed) = self.highlighter.lex_lines(lines) {
+            self.window.damage_from(changed);
+        }
+    }
+
+    /// Draws the part of line on one row, each token in its own color and each tab as spaces up to the next tab stop
+    fn draw_row_text(&mut self, canvas: &mut Canvas<Window>, row: &Row, line: RopeSlice<'_>, pos: FPoint) -> Result<(), Box<dyn Error>> {
+        let (text_width, _) = self.window.get_text_dim();
+        let tab_width = self.window.tab_width();
+        let chars = line.slice(row.start..row.start + row.len).chars().collect::<Vec<char>>();
+        let kinds = highlight::kinds_in(self.highlighter.tokens(row.line), row.start, chars.len());
+        let mut col = tabs::col_of(line.chars(), row.start, tab_width);
+        let mut start = 0;
+        while start < chars.len() {
+            let kind = kinds[start];
+            let len = kinds[start..].iter().take_while(|&&other| other == kind).count();
+            // A tab scrolled partly out of view only fills the columns still on screen
+            let segment_col = col.max(row.col);
+            let mut segment = String::new();
+            for &c in &chars[start..start + len] {
+                let width = tabs::char_width(c, col, tab_width);
+                match c {
+                    '\t' => segment.push_str(&" ".repeat(col + width - col.max(row.col))),
+                    _ => segment.push(c),
+                }
+                col += width;
+            }
+            let color = kind.map_or(self.theme.foreground, |kind| self.theme.syntax(kind));
+            let segment_pos = FPoint::new(pos.x + (segment_col - row.col) as f32 * text_width, pos.y);
+            self.glyphs.draw_text(canvas, &self.font, &segment, segment_pos, text_width, color)?;
+            start += len;
+        }
+        Ok(())
+    }
+
+    fn damage_cursor(&mut self) {
+        let (first, last) = lines_between(self.cursor.pos(), self.cursor.select_start_pos());
+        self.window.damage_lines(first, last);
+    }
+
+    /// Width of text drawn one character per cell
+    fn text_width(&self, text: &str) -> u32 {
+        let (text_width, _) = self.window.get_text_dim();
+        (text.chars().count() as f32 * text_width) as u32
+    }
+
+    fn draw_text(&mut self, canvas: &mut Canvas<Window>, text: &str, x: i32, y: i32, color: Color) -> Result<(), Box<dyn Error>> {
+        let (text_width, _) = self.window.get_text_dim();
+        self.glyphs.draw_text(canvas, &self.font, text, FPoint::new(x as f32, y as f32), text_width, color)
+    }
+
     fn replace_selected_text(&mut self, select_pos: Vector2D, replace_text: String) {
         let cursor_pos = self.cursor.pos();
         let select_start = calculate_index_from_pos(&mut self.text, select_pos);
@@ -347,8 +727,7 @@ impl <'a> TextBox<'a> {
         };
         let replace_len = select_start.abs_diff(current_index);
 
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.replace(index, replace_len, replace_text, jump_pos, &mut self.cursor, &mut self.window);
+        self.text.replace(index, replace_len, replace_text, jump_pos, &mut self.cursor, &mut self.window);
     }
 
     fn insert_text(&mut self, text_chunk: String) {
@@ -357,8 +736,7 @@ impl <'a> TextBox<'a> {
         }
 
         let index = calculate_index_from_pos(&self.text, self.cursor.pos());
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.insert(index, text_chunk, &mut self.cursor, &mut self.window);
+        self.text.insert(index, text_chunk, &mut self.cursor, &mut self.window);
     }
 
     fn delete_text(&mut self) {
@@ -373,8 +751,7 @@ impl <'a> TextBox<'a> {
             return;
         }
 
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.delete(index, 1, &mut self.cursor, &mut self.window);
+        self.text.delete(index, 1, &mut self.cursor, &mut self.window);
     }
 
     fn remove_text(&mut self, amt: usize) {
@@ -387,8 +764,7 @@ impl <'a> TextBox<'a> {
             return;
         };
 
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.remove(shift_index, amt, &mut self.cursor, &mut self.window);
+        self.text.remove(shift_index, amt, &mut self.cursor, &mut self.window);
     }
 
     fn return_text(&mut self) {
@@ -397,22 +773,26 @@ impl <'a> TextBox<'a> {
         }
         let index = calculate_index_from_pos(&self.text, self.cursor.pos());
 
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.push_and_insert(index, String::from("\n"), &mut self.cursor, &mut self.window);
+        self.text.push_and_insert(index, String::from("\n"), &mut self.cursor, &mut self.window);
     }
 
     fn tab_text(&mut self) {
-        let pos @ Vector2D {x, ..}: Vector2D = self.cursor.pos();
+        let pos @ Vector2D {x, y}: Vector2D = self.cursor.pos();
         let index = calculate_index_from_pos(&self.text, pos);
-        let spaces = TAB_SPACE_COUNT - x % TAB_SPACE_COUNT;
-        let insert_spaces = String::from(&TAB_SPACE_STRING[..spaces as usize]);
+        let indent_width = self.indent_width.unwrap_or(self.tab_width());
+        let indent = match self.indent_style.unwrap_or(self.config.indent_style) {
+            IndentStyle::TAB => String::from("\t"),
+            IndentStyle::SPACE => {
+                let col = self.text.line(y as usize).map_or(x, |line| tabs::col_of(line.chars(), x as usize, self.tab_width() as usize) as u32);
+                " ".repeat((indent_width - col % indent_width) as usize)
+            },
+        };
 
         if let Some(select_pos) =  self.cursor.select_start_pos() {
-            return self.replace_selected_text(select_pos, insert_spaces);
+            return self.replace_selected_text(select_pos, indent);
         }
 
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.push_and_insert(index, insert_spaces, &mut self.cursor, &mut self.window);
+        self.text.push_and_insert(index, indent, &mut self.cursor, &mut self.window);
     }
 
     fn copy_selected_text(&self) -> Result<(), Box<dyn Error>> {
@@ -430,8 +810,7 @@ impl <'a> TextBox<'a> {
         }
 
         let index = calculate_index_from_pos(&self.text, self.cursor.pos());
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.push_and_insert(index, normalized_clipboard_text, &mut self.cursor, &mut self.window);
+        self.text.push_and_insert(index, normalized_clipboard_text, &mut self.cursor, &mut self.window);
 
         Ok(())
     }
@@ -456,25 +835,24 @@ impl <'a> TextBox<'a> {
         let len = select_start.abs_diff(current_index);
         let index = select_start.min(current_index);
 
-        self.text.chars().skip(index).take(len).collect()
+        self.text.slice(index..index + len).to_string()
     }
 
     fn undo_action(&mut self) {
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.undo(&mut self.cursor, &mut self.window);
+        self.text.undo(&mut self.cursor, &mut self.window);
+        self.window.set_render_flag();
     }
 
     fn redo_action(&mut self) {
-        let old_text = std::mem::take(&mut self.text);
-        self.text = old_text.redo(&mut self.cursor, &mut self.window);
+        self.text.redo(&mut self.cursor, &mut self.window);
+        self.window.set_render_flag();
     }
 
     fn scroll(&mut self, amt: f32) {
         if amt > 0.0 {
-            self.window.scroll_up(amt as usize);
+            self.window.scroll_up(amt as usize * self.config.scroll_lines, &self.text);
         } else {
-            let max_lines = self.text.line_count();
-            self.window.scroll_down((-amt) as usize, max_lines);
+            self.window.scroll_down((-amt) as usize * self.config.scroll_lines, &self.text);
         }
     }
 
@@ -483,8 +861,34 @@ impl <'a> TextBox<'a> {
     }
 
     fn left_click(&mut self, click_x: f32, click_y: f32, clicks: u8) {
-        self.cursor.left_click_press(click_x, click_y, clicks, &self.text, &mut self.window);
+        let (text_padding, _) = self.window.get_padding();
+        let gutter_width = self.window.gutter_width();
+        if gutter_width > 0 && click_x < (self.window.pos().x + text_padding + gutter_width) as f32 {
+            self.cursor.gutter_click(click_x, click_y, &self.text, &mut self.window);
+        } else {
+            self.cursor.left_click_press(click_x, click_y, clicks, &self.text, &mut self.window);
+        }
+    }
+}
+
+/// Returns (first, last) line between the cursor and the selection start
+fn lines_between(cursor: Vector2D, select: Option<Vector2D>) -> (usize, usize) {
+    let select = select.unwrap_or(cursor);
+    (cursor.y.min(select.y) as usize, cursor.y.max(select.y) as usize)
+}
+
+/// Index just past the first match of pattern in chars
+fn find_match_end(chars: impl Iterator<Item = char>, pattern: &str) -> Option<usize> {
+    let mut pattern_iter = pattern.chars();
+    for (i, c) in chars.enumerate() {
+        let Some(pattern_c) = pattern_iter.next() else {
+            return Some(i);
+        };
+        if pattern_c != c {
+            pattern_iter = pattern.chars();
+        }
     }
+    None
 }
 
 fn calculate_index_from_pos(text: &TextRope, pos: Vector2D) -> usize {
@@ -493,8 +897,9 @@ fn calculate_index_from_pos(text: &TextRope, pos: Vector2D) -> usize {
     line_index + x as usize
 }
 
-fn load_font<'a>(ttf_context: &Sdl3TtfContext, font_path: &str, point_size: f32, style: FontStyle) -> Result<Font<'a>, Box<dyn Error>> {
-    let mut font = ttf_context.load_font(font_path, point_size)?;
+fn load_font<'a>(ttf_context: &Sdl3TtfContext, font_path: &Path, point_size: f32, style: FontStyle) -> Result<Font<'a>, Box<dyn Error>> {
+    let mut font = ttf_context.load_font(font_path, point_size)
+        .map_err(|err| format!("Failed to load font \"{}\": {}", font_path.display(), err))?;
     font.set_style(style);
     Ok(font)
 }
\ No newline at end of file
@@ -1,6 +1,8 @@
 mod rope;
 
-use rope::Rope;
+use std::ops::RangeBounds;
+
+pub use rope::{Rope, RopeSlice};
 use crate::{editor::{cursor::Cursor, windowstate::WindowState}, vector::Vector2D};
 
 pub struct TextRope {
@@ -11,6 +13,10 @@ pub struct TextRope {
     current_action: Option<Action>,
     redo_stack: Vec<Action>,
     space_flag: SpaceCount,
+    saved_depth: Option<usize>,
+    revision: usize,
+    /// First line touched by the latest change
+    edited_line: usize,
 }
 
 enum SpaceCount { NONE, ONE, MANY }
@@ -20,27 +26,43 @@ impl TextRope {
         Self::default()
     }
 
-    pub fn undo(mut self, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    /// A freshly opened document holding text, with nothing to undo
+    pub fn from_rope(root: Rope) -> Self {
+        Self {
+            len: root.len(),
+            line_count: root.line_count() - 1,
+            root,
+            ..Self::default()
+        }
+    }
+
+    /// Starts over as a freshly opened document holding text, dropping the undo history
+    ///
+    /// The revision still moves on so every view of the old text repaints
+    pub fn reset(&mut self, text: Rope) {
+        let revision = self.revision + 1;
+        *self = Self { revision, ..Self::from_rope(text) };
+    }
+
+    pub fn undo(&mut self, cursor: &mut Cursor, window: &mut WindowState) {
         let Some(undo_action) = self.current_action.take().or_else(|| self.undo_stack.pop()) else {
-            return self;
+            return;
         };
-        let (mut new_rope, inverted_action) = undo_action.execute(self, cursor, window);
-        new_rope.redo_stack.push(inverted_action);
-        new_rope
+        let inverted_action = undo_action.execute(self, cursor, window);
+        self.redo_stack.push(inverted_action);
     }
 
-    pub fn redo(mut self, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn redo(&mut self, cursor: &mut Cursor, window: &mut WindowState) {
         let Some(redo_action) = self.redo_stack.pop() else {
-            return self;
+            return;
         };
-        let (mut new_rope, inverted_action) = redo_action.execute(self, cursor, window);
-        new_rope.undo_stack.push(inverted_action);
-        new_rope
+        let inverted_action = redo_action.execute(self, cursor, window);
+        self.undo_stack.push(inverted_action);
     }
 
-    pub fn insert(mut self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn insert(&mut self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) {
         if insert_text.len() == 0 {
-            return self;
+            return;
         }
         if insert_text.len() == 1 && insert_text.as_bytes()[0] == b' ' {
             match self.space_flag {
@@ -69,35 +91,34 @@ impl TextRope {
     //     self.execute_new_insert(index, insert_text, cursor, window)
     // }
 
-    pub fn remove(self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn remove(&mut self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) {
         if len == 0 {
-            return self;
+            return;
         }
         self.execute_new_remove(index, len, cursor, window)
     }
 
-    pub fn delete(self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn delete(&mut self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) {
         if len == 0 {
-            return self;
+            return;
         }
         self.execute_new_delete(index, len, cursor, window)
     }
 
-    pub fn replace(self, index: usize, len: usize, replace_text: String, jump_pos: Vector2D, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn replace(&mut self, index: usize, len: usize, replace_text: String, jump_pos: Vector2D, cursor: &mut Cursor, window: &mut WindowState) {
         self.execute_new_replace(index, len, replace_text, jump_pos, cursor, window)
     }
 
-    pub fn push_and_insert(mut self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn push_and_insert(&mut self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) {
+        self.push_current_action();
+        self.execute_new_insert(index, insert_text, cursor, window);
         self.push_current_action();
-        let mut new_self = self.execute_new_insert(index, insert_text, cursor, window);
-        new_self.push_current_action();
-        new_self
     }
 
      #[allow(dead_code)]
-    pub fn pop(self, len: usize, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    pub fn pop(&mut self, len: usize, cursor: &mut Cursor, window: &mut WindowState) {
         if len == 0 {
-            return self;
+            return;
         }
         let index = self.len - len;
         self.execute_new_remove(index, len, cursor, window)
@@ -118,14 +139,32 @@ impl TextRope {
         Vector2D::new(x as u32, y as u32)
     }
 
-    pub fn chars(&self) -> rope::RopeIterator {
-        self.root.chars()
+    /// Borrows the chars in range, taking only as long as finding its two ends
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> rope::RopeSlice<'_> {
+        self.root.slice(range)
     }
 
-    pub fn lines(&self) -> rope::RopeLineIterator {
+    /// The text as it is now, in O(1), later edits do not show up in it
+    ///
+    /// The snapshot can be sent to another thread to be saved, searched or analysed while editing goes on
+    pub fn snapshot(&self) -> Rope {
+        self.root.clone()
+    }
+
+    pub fn lines(&self) -> rope::Lines<'_> {
         self.root.lines()
     }
 
+    /// Borrows line without its newline, in O(log n), None past the last line
+    pub fn line(&self, line: usize) -> Option<rope::RopeSlice<'_>> {
+        self.root.line(line)
+    }
+
+    /// Chars in line, not counting its newline, in O(log n), None past the last line
+    pub fn line_len(&self, line: usize) -> Option<usize> {
+        self.root.line_len(line)
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -133,6 +172,27 @@ impl TextRope {
     pub fn line_count(&self) -> usize {
         self.line_count + 1
     }
+
+    /// Records the current undo position as the saved state of the document
+    pub fn mark_saved(&mut self) {
+        self.push_current_action();
+        self.saved_depth = Some(self.undo_depth());
+    }
+
+    /// Counter bumped by every change to the text
+    pub fn revision(&self) -> usize {
+        self.revision
+    }
+
+    /// First line touched by the latest change, text below it may need to be looked at again
+    pub fn edited_line(&self) -> usize {
+        self.edited_line
+    }
+
+    /// True if undo/redo has not brought the text back to the last saved state
+    pub fn is_modified(&self) -> bool {
+        self.saved_depth != Some(self.undo_depth())
+    }
 }
 
 impl Default for TextRope {
@@ -145,109 +205,103 @@ impl Default for TextRope {
             current_action: None,
             redo_stack: Vec::new(),
             space_flag: SpaceCount::NONE,
+            saved_depth: Some(0),
+            revision: 0,
+            edited_line: 0,
         }
     }
 }
 
 impl TextRope {
-    fn execute_new_insert(self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    fn execute_new_insert(&mut self, index: usize, insert_text: String, cursor: &mut Cursor, window: &mut WindowState) {
         let cursor_start = cursor.pos();
 
-        let (mut new_text_data, len) = self._insert(index, &insert_text);
-        cursor.text_shift_x(len as isize, &new_text_data, window);
+        let len = self._insert(index, &insert_text);
+        cursor.text_shift_x(len as isize, self, window);
 
         let cursor_end = cursor.pos();
-        new_text_data.push_undo(
+        self.push_undo(
             Action::new_remove(index, cursor_end, cursor_start, len),
             cursor.take_tampered_flag(),
         );
-        new_text_data
     }
 
-    fn execute_new_remove(self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    fn execute_new_remove(&mut self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) {
         let cursor_start = cursor.pos();
 
         let shift_amt = len as isize;
-        cursor.text_shift_x(-shift_amt, &self, window);
-        let (mut new_text_data, insert_text) = self._remove(index, len);
+        cursor.text_shift_x(-shift_amt, self, window);
+        let insert_text = self._remove(index, len);
         if insert_text.len() == 1 && insert_text.as_bytes()[0] == b'\n' {
-            new_text_data.push_current_action();
+            self.push_current_action();
         }
 
         let cursor_end = cursor.pos();
-        new_text_data.push_undo(
+        self.push_undo(
             Action::new_insert(index, cursor_end, cursor_start, insert_text),
             cursor.take_tampered_flag(),
         );
-        new_text_data
     }
 
-    fn execute_new_replace(self, index: usize, len: usize, replace_text: String, jump_pos: Vector2D, cursor: &mut Cursor, window: &mut WindowState) -> Self {
-        cursor.text_jump_to(jump_pos.x, jump_pos.y, &self, window);
-        let (new_text_data, removed_text) = self._remove(index, len);
-        let (mut new_text_data, replace_len) = new_text_data._insert(index, &replace_text);
-        cursor.text_shift_x(replace_len as isize, &new_text_data, window);
+    fn execute_new_replace(&mut self, index: usize, len: usize, replace_text: String, jump_pos: Vector2D, cursor: &mut Cursor, window: &mut WindowState) {
+        cursor.text_jump_to(jump_pos.x, jump_pos.y, self, window);
+        let removed_text = self._remove(index, len);
+        let replace_len = self._insert(index, &replace_text);
+        cursor.text_shift_x(replace_len as isize, self, window);
 
         let cursor_end = cursor.pos();
-        new_text_data.push_undo(
+        self.push_undo(
             Action::new_replace(index, cursor_end, jump_pos, replace_len, removed_text),
             cursor.take_tampered_flag(),
         );
-        new_text_data
     }
 
-    fn execute_new_delete(self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) -> Self {
+    fn execute_new_delete(&mut self, index: usize, len: usize, cursor: &mut Cursor, window: &mut WindowState) {
         let cursor_pos = cursor.pos();
 
-        let (mut new_text_data, removed_text) = self._remove(index, len);
+        let removed_text = self._remove(index, len);
         if removed_text.len() == 1 && removed_text.as_bytes()[0] == b'\n' {
-            new_text_data.push_current_action();
+            self.push_current_action();
         }
 
-        cursor.focus_on(&new_text_data, window);
-        new_text_data.push_undo(
+        cursor.focus_on(self, window);
+        self.push_undo(
             Action::new_append(index, cursor_pos, removed_text),
             cursor.take_tampered_flag(),
         );
-        new_text_data
     }
 
-    fn _insert(self, index: usize, insert_text: &str) -> (Self, usize) {
+    /// Returns the number of chars inserted
+    fn _insert(&mut self, index: usize, insert_text: &str) -> usize {
         let len = insert_text.chars().count();
-        let line_count = Rope::get_line_count(insert_text);
-        let new_root = self.root.insert(index, insert_text);
-
-        (Self {
-            root: new_root,
-            len: self.len + len,
-            line_count: self.line_count + line_count,
-            undo_stack: self.undo_stack,
-            current_action: self.current_action,
-            redo_stack: self.redo_stack,
-            space_flag: self.space_flag
-        }, len)
+        self.edited_line = self.root.index_pos(index).0;
+        self.root.insert(index, insert_text);
+        self.len += len;
+        self.line_count += Rope::get_line_count(insert_text);
+        self.revision += 1;
+        len
     }
 
-    fn _remove(self, index: usize, len: usize) -> (Self, String) {
+    /// Returns the removed text
+    fn _remove(&mut self, index: usize, len: usize) -> String {
         if len == 0 {
-            return (self, String::from(""));
+            return String::new();
         }
-        let removed_text = self.chars().skip(index).take(len).collect::<String>();
-        let new_root = self.root.remove(index, len);
-        
-        (Self {
-            len: self.len - len,
-            line_count: new_root.line_count() - 1,
-            root: new_root,
-            undo_stack: self.undo_stack,
-            current_action: self.current_action,
-            redo_stack: self.redo_stack,
-            space_flag: self.space_flag
-        }, removed_text)
+        let removed_text = self.slice(index..index + len).to_string();
+        self.edited_line = self.root.index_pos(index).0;
+        self.root.remove(index, len);
+        self.len -= len;
+        self.line_count -= Rope::get_line_count(&removed_text);
+        self.revision += 1;
+        removed_text
     }
 
     /// Tamper flag must be set true if the cursor moved from the last index arrived from previous actions
     fn push_undo(&mut self, new_undo_action: Action, tamper_flag: bool) {
+        // A new edit drops the redo stack, so a saved state inside it can never be reached again
+        if self.saved_depth.is_some_and(|saved_depth| saved_depth > self.undo_depth()) {
+            self.saved_depth = None;
+        }
         self.redo_stack.clear();
 
         let Some(current_action) = self.current_action.take() else {
@@ -325,6 +379,11 @@ impl TextRope {
             self.undo_stack.push(current_action);
         }
     }
+
+    /// Number of actions that undo can revert
+    fn undo_depth(&self) -> usize {
+        self.undo_stack.len() + self.current_action.is_some() as usize
+    }
 }
 
 #[derive(Debug)]
@@ -357,39 +416,59 @@ impl Action {
         Self::Append { index, cursor_pos, append_text }
     }
 
-    fn execute(self, text_data: TextRope, cursor: &mut Cursor, window: &mut WindowState) -> (TextRope, Action) {
+    fn execute(self, text_data: &mut TextRope, cursor: &mut Cursor, window: &mut WindowState) -> Action {
         match self {
             Action::Insert { index, cursor_start, cursor_end, insert_text } => {
-                let (new_text_data, len) = text_data._insert(index, &insert_text);
-                let inverted_action = Action::new_remove(index, cursor_end, cursor_start, len);
-                cursor.text_jump_to(cursor_end.x, cursor_end.y, &new_text_data, window);
-                (new_text_data, inverted_action)
+                let len = text_data._insert(index, &insert_text);
+                cursor.text_jump_to(cursor_end.x, cursor_end.y, text_data, window);
+                Action::new_remove(index, cursor_end, cursor_start, len)
             },
             Action::Remove { index, cursor_start, cursor_end, len } => {
-                let (new_text_data, insert_text) = text_data._remove(index, len);
-                let inverted_action = Action::new_insert(index, cursor_end, cursor_start, insert_text);
-                cursor.text_jump_to(cursor_end.x, cursor_end.y, &new_text_data, window);
-                (new_text_data, inverted_action)
+                let insert_text = text_data._remove(index, len);
+                cursor.text_jump_to(cursor_end.x, cursor_end.y, text_data, window);
+                Action::new_insert(index, cursor_end, cursor_start, insert_text)
             },
             Action::Replace { index, cursor_start, cursor_end, len, replace_text } => {
-                let (new_text_data, removed_text) = text_data._remove(index, len);
-                let (new_text_data, len) = new_text_data._insert(index, &replace_text);
-                let inverted_action = Action::new_replace(index, cursor_end, cursor_start, len, removed_text);
-                cursor.text_jump_to(cursor_end.x, cursor_end.y, &new_text_data, window);
-                (new_text_data, inverted_action)
+                let removed_text = text_data._remove(index, len);
+                let len = text_data._insert(index, &replace_text);
+                cursor.text_jump_to(cursor_end.x, cursor_end.y, text_data, window);
+                Action::new_replace(index, cursor_end, cursor_start, len, removed_text)
             },
             Action::Delete { index, cursor_pos, len } => {
-                let (new_text_data, insert_text) = text_data._remove(index, len);
-                let inverted_action = Action::new_append(index, cursor_pos, insert_text);
-                cursor.text_jump_to(cursor_pos.x, cursor_pos.y, &new_text_data, window);
-                (new_text_data, inverted_action)
+                let insert_text = text_data._remove(index, len);
+                cursor.text_jump_to(cursor_pos.x, cursor_pos.y, text_data, window);
+                Action::new_append(index, cursor_pos, insert_text)
             },
             Action::Append { index, cursor_pos, append_text } => {
-                let (new_text_data, len) = text_data._insert(index, &append_text);
-                let inverted_action = Action::new_delete(index, cursor_pos, len);
-                cursor.text_jump_to(cursor_pos.x, cursor_pos.y, &new_text_data, window);
-                (new_text_data, inverted_action)
+                let len = text_data._insert(index, &append_text);
+                cursor.text_jump_to(cursor_pos.x, cursor_pos.y, text_data, window);
+                Action::new_delete(index, cursor_pos, len)
             },
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_test() {
+        let text = "0123456789\n".repeat(5_000);
+        let mut text_rope = TextRope::from_rope(Rope::from_str(&text));
+        let snapshot = text_rope.snapshot();
+        text_rope._insert(20_000, "edited");
+        text_rope._remove(0, 11);
+
+        let mut expected = text.clone();
+        expected.insert_str(20_000, "edited");
+        expected.replace_range(0..11, "");
+        assert_eq!(text_rope.slice(..).to_string(), expected);
+        assert_eq!(snapshot.slice(..).chars().collect::<String>(), text);
+        assert_eq!(snapshot.line_count(), 5_001);
+
+        // The snapshot outlives the document it came from
+        drop(text_rope);
+        assert_eq!(snapshot.len(), text.len());
+    }
+}
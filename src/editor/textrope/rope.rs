@@ -1,16 +1,34 @@
-use std::fmt::Debug;
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Display};
+use std::io::{self, Read};
 use std::iter::Iterator;
-use std::cmp::Ordering;
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
 
+/// Bytes asked of a reader at a time when building a rope from it
+const READ_BLOCK_SIZE: usize = 64 * 1024;
+
+/// Text split over the leaves of a balanced tree
+///
+/// Nodes are shared, so a clone is a snapshot taken in O(1). Edits change nodes in place unless a
+/// snapshot still holds them, then only the nodes on the path to the edit are copied
+#[derive(Clone)]
 pub enum Rope {
+    /// weight, bytes and line count the chars, bytes and newlines of the left side
     Branch {
         height: usize,
         weight: usize,
+        bytes: usize,
         line: usize,
-        left: Box<Rope>,
-        right: Box<Rope>,
+        left: Arc<Rope>,
+        right: Arc<Rope>,
+    },
+    /// len and lines count the chars and newlines of text, its byte length is the string's own
+    Leaf {
+        text: String,
+        len: usize,
+        lines: usize,
     },
-    Leaf(String)
 }
 
 impl Rope {
@@ -19,23 +37,112 @@ impl Rope {
         Self::default()
     }
 
-    pub fn new_branch(height: usize, weight: usize, line: usize, left: Box<Rope>, right: Box<Rope>) -> Self {
-        Rope::Branch { height, weight, line, left, right }
+    pub fn new_branch(height: usize, weight: usize, bytes: usize, line: usize, left: Arc<Rope>, right: Arc<Rope>) -> Self {
+        Rope::Branch { height, weight, bytes, line, left, right }
     }
 
-    pub fn chars(&self) -> RopeIterator {
-        RopeIterator::new(self)
+    pub fn new_leaf(text: String) -> Self {
+        let (len, lines) = (text.chars().count(), Self::get_line_count(&text));
+        Rope::Leaf { text, len, lines }
     }
 
-    pub fn lines(&self) -> RopeLineIterator {
-        RopeLineIterator::new(self)
+    /// Builds a balanced rope holding text in one pass, instead of inserting it a chunk at a time with rotations
+    pub fn from_str(text: &str) -> Self {
+        let mut leaves = Vec::with_capacity(text.len() / Self::MAX_NODE_INSERT_SIZE + 1);
+        let mut rest = text;
+        while !rest.is_empty() {
+            let split = rest.char_indices().nth(Self::MAX_NODE_INSERT_SIZE).map_or(rest.len(), |(i, _)| i);
+            let (leaf, after) = rest.split_at(split);
+            leaves.push(leaf.to_string());
+            rest = after;
+        }
+        Self::from_leaves(leaves)
+    }
+
+    /// Builds a balanced rope out of the UTF-8 text read from reader, a block at a time
+    pub fn from_reader(mut reader: impl Read) -> io::Result<Self> {
+        let mut leaves = Vec::new();
+        let mut leaf = String::with_capacity(Self::MAX_NODE_INSERT_SIZE);
+        let mut leaf_len = 0;
+        let mut buffer = vec![0; READ_BLOCK_SIZE];
+        // Bytes of a char cut off by the end of the last block, kept at the start of the buffer
+        let mut pending = 0;
+        loop {
+            let read = match reader.read(&mut buffer[pending..]) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            let filled = pending + read;
+            let valid_len = match std::str::from_utf8(&buffer[..filled]) {
+                Ok(_) => filled,
+                Err(err) if err.error_len().is_none() => err.valid_up_to(),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            };
+            let text = std::str::from_utf8(&buffer[..valid_len]).expect("Checked above");
+            for c in text.chars() {
+                leaf.push(c);
+                leaf_len += 1;
+                if leaf_len == Self::MAX_NODE_INSERT_SIZE {
+                    leaves.push(std::mem::replace(&mut leaf, String::with_capacity(Self::MAX_NODE_INSERT_SIZE)));
+                    leaf_len = 0;
+                }
+            }
+            buffer.copy_within(valid_len..filled, 0);
+            pending = filled - valid_len;
+        }
+        if pending > 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Text ends in the middle of a UTF-8 character"));
+        }
+        if !leaf.is_empty() {
+            leaves.push(leaf);
+        }
+        Ok(Self::from_leaves(leaves))
+    }
+
+    /// Borrows the chars in range without copying them
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> RopeSlice<'_> {
+        RopeSlice::new(self, range)
+    }
+
+    /// Lines without their newlines, borrowed from the rope
+    pub fn lines(&self) -> Lines<'_> {
+        self.slice(..).lines()
+    }
+
+    /// Borrows line without its newline, None if the text has no such line
+    ///
+    /// Expects line to be zero indexed
+    pub fn line(&self, line: usize) -> Option<RopeSlice<'_>> {
+        let len = self.line_len(line)?;
+        let start = self.line_start_index(line);
+        Some(self.slice(start..start + len))
+    }
+
+    /// Chars in line, not counting its newline, None if the text has no such line
+    ///
+    /// Expects line to be zero indexed
+    pub fn line_len(&self, line: usize) -> Option<usize> {
+        if line >= self.line_count() {
+            return None;
+        }
+        let start = self.line_start_index(line);
+        Some(self.next_newline(start).unwrap_or_else(|| self.len()) - start)
     }
 
-    #[allow(dead_code)]
     pub fn len(&self) -> usize {
         match self {
             Rope::Branch { weight, right, .. } => weight + right.len(),
-            Rope::Leaf(text) => text.chars().count(),
+            Rope::Leaf { len, .. } => *len,
+        }
+    }
+
+    /// Length of the text in bytes
+    pub fn byte_len(&self) -> usize {
+        match self {
+            Rope::Branch { bytes, right, .. } => bytes + right.byte_len(),
+            Rope::Leaf { text, .. } => text.len(),
         }
     }
 
@@ -50,7 +157,6 @@ impl Rope {
         }
     }
 
-    #[allow(dead_code)]
     pub fn line_count(&self) -> usize {
         self._line_count() + 1
     }
@@ -65,18 +171,21 @@ impl Rope {
                     right.get(target_index - weight)
                 }
             },
-            Rope::Leaf(text) => text.chars().nth(target_index)
+            Rope::Leaf { text, len, .. } => {
+                let char_index = Self::get_char_index(text, *len, target_index.min(*len));
+                text[char_index..].chars().next()
+            },
         }
     }
 
-    pub fn insert(mut self, mut index: usize, insert_text: &str) -> Self {
+    pub fn insert(&mut self, mut index: usize, insert_text: &str) {
         let mut chars_iter = insert_text.chars();
         loop {
             let chunk = chars_iter.by_ref().take(Rope::MAX_NODE_INSERT_SIZE).collect::<String>();
             if chunk.is_empty() {
-                return self;
+                return;
             }
-            self = self._insert(
+            self._insert(
                 index,
                 &chunk,
                 chunk.chars().count(),
@@ -86,9 +195,11 @@ impl Rope {
         }
     }
 
-    pub fn remove(self, index: usize, len: usize) -> Self {
-        let (result, _) = self._remove(index, len);
-        result.map_or_else(Rope::new, |x| *x)
+    pub fn remove(&mut self, index: usize, len: usize) {
+        let (kept, _) = self._remove(index, len);
+        if !kept {
+            *self = Rope::new();
+        }
     }
 
     pub fn get_line_count(text: &str) -> usize {
@@ -97,244 +208,156 @@ impl Rope {
 
     /// Returns line, char
     pub fn index_pos(&self, index: usize) -> (usize, usize) {
+        let line = self.line_at(index);
+        (line, index - self.line_start_index(line))
+    }
+}
+
+impl Rope {
+    /// Inserts into the node in place, Arc::make_mut copies only the nodes a snapshot still shares
+    fn _insert(&mut self, index: usize, insert_text: &str, insert_text_len: usize, insert_text_lines: usize) {
         match self {
-            Rope::Leaf(text) => text.chars().take(index).fold((0, 0), |(line, col), c| {
-                match c {
-                    '\n' => (line + 1, 0),
-                    _ => (line, col + 1),
+            Rope::Leaf { text, len, lines } => {
+                if index > *len {
+                    panic!("[Insert] Out of bounds index: Leaf of len {}, Index {}", len, index);
                 }
-            }),
-            Rope::Branch { weight, line, left, right, .. } => {
+                if *len + insert_text_len <= Self::MAX_NODE_INSERT_SIZE {
+                    let char_index = Self::get_char_index(text, *len, index);
+                    text.insert_str(char_index, insert_text);
+                    *len += insert_text_len;
+                    *lines += insert_text_lines;
+                } else {
+                    let mut chars_iter = text.chars();
+                    let mut insert_chars_iter = insert_text.chars();
+                    let half_insert_len = insert_text_len / 2;
+
+                    let mut new_left_str = String::with_capacity(index + half_insert_len);
+                    new_left_str.extend(chars_iter.by_ref().take(index).chain(insert_chars_iter.by_ref().take(half_insert_len)));
+
+                    let mut new_right_str = String::with_capacity(*len - index + insert_text_len - half_insert_len);
+                    new_right_str.extend(insert_chars_iter.chain(chars_iter));
+                    let new_left = Rope::new_leaf(new_left_str);
+
+                    *self = Rope::new_branch(
+                        1,
+                        index + half_insert_len,
+                        new_left.byte_len(),
+                        new_left._line_count(),
+                        Arc::new(new_left),
+                        Arc::new(Rope::new_leaf(new_right_str)),
+                    );
+                }
+            },
+            Rope::Branch { height, weight, bytes, line, left, right } => {
                 if index <= *weight {
-                    left.index_pos(index)
+                    let left_weight = left.weight();
+                    Arc::make_mut(left)._insert(index, insert_text, insert_text_len, insert_text_lines);
+                    *height = (*height).max(left.height() + 1);
+                    *weight += insert_text_len;
+                    *bytes += insert_text.len();
+                    *line += insert_text_lines;
+
+                    if (right.height() as isize - left.height() as isize) < -1 {
+                        if index > left_weight {
+                            Arc::make_mut(left).rotate(Self::rotate_left);
+                        }
+                        self.rotate(Self::rotate_right);
+                    }
                 } else {
-                    let (right_line, right_col) = right.index_pos(index - *weight);
-                    if right_line == 0 {
-                        (*line, weight + right_col)
-                    } else {
-                        (*line + right_line, right_col)
+                    let right_weight = right.weight();
+                    Arc::make_mut(right)._insert(index - *weight, insert_text, insert_text_len, insert_text_lines);
+                    *height = (*height).max(right.height() + 1);
+
+                    if (left.height() as isize - right.height() as isize) < -1 {
+                        if index - *weight <= right_weight {
+                            Arc::make_mut(right).rotate(Self::rotate_right);
+                        }
+                        self.rotate(Self::rotate_left);
                     }
                 }
-            }
+            },
         }
     }
-}
 
-impl Rope {
-    fn _insert(self, index: usize, insert_text: &str, insert_text_len: usize, insert_text_lines: usize) -> Self {
+    /// Removes from the node in place, returns false if nothing is left of it and how much of delete_len
+    /// still has to be removed after it
+    fn _remove(&mut self, index: usize, delete_len: usize) -> (bool, usize) {
         match self {
-            Rope::Leaf(text) => Self::_insert_leaf(index,  insert_text, insert_text_lines, text),
-            Rope::Branch {
-                height,
-                weight,
-                line,
-                left,
-                right
-            } => Self::_insert_branch(index, insert_text, insert_text_len, insert_text_lines, height, weight, line, left, right),
-        }
-    }
-
-    fn _insert_leaf(index: usize, insert_text: &str, insert_text_len: usize, mut text: String) -> Self {
-        let text_len = text.chars().count();
-        if index > text_len {
-            panic!("[Insert] Out of bounds index: Leaf of len {}, Index {}", text_len, index);
-        }
-        if text_len + insert_text_len <= Self::MAX_NODE_INSERT_SIZE {
-            let char_index= Self::get_char_index(&text, text_len, index);
-            text.insert_str(char_index, insert_text);
-            Rope::Leaf(text)
-        } else {
-            let mut chars_iter = text.chars();
-            let mut insert_chars_iter = insert_text.chars();
-            let half_insert_len = insert_text_len / 2;
-
-            let mut new_left_str = String::with_capacity(index + half_insert_len);
-            new_left_str.extend(chars_iter.by_ref().take(index).chain(insert_chars_iter.by_ref().take(half_insert_len)));
-
-            let mut new_right_str = String::with_capacity(text_len - index + insert_text_len - half_insert_len);
-            new_right_str.extend(insert_chars_iter.chain(chars_iter));
-            let lines = Self::get_line_count(&new_left_str);
-
-            Rope::new_branch(
-                1,
-                index + half_insert_len,
-                lines,
-                Box::new(Rope::Leaf(new_left_str)),
-                Box::new(Rope::Leaf(new_right_str)),
-            )
-        }
-    }
-
-    fn _insert_branch(
-        index: usize,
-        insert_text: &str,
-        insert_text_len: usize,
-        insert_text_lines: usize,
-        height: usize,
-        weight: usize,
-        line: usize,
-        left: Box<Self>,
-        right: Box<Self>,
-    ) -> Self {
-        if index <= weight {
-            let left_weight = left.weight();
-            let left_branch = left._insert(index, insert_text, insert_text_len, insert_text_lines);
-            let new_height = height.max(left_branch.height() + 1);
-
-            if (right.height() as isize - left_branch.height() as isize) < -1 && index <= left_weight {
-                Rope::new_branch(
-                    new_height,
-                    weight + insert_text_len,
-                    line + insert_text_lines,
-                    Box::new(left_branch),
-                    right,
-                ).rotate_right()
-            } else if (right.height() as isize - left_branch.height() as isize) < -1 && index > left_weight {
-                Rope::new_branch(
-                    new_height,
-                    weight + insert_text_len,
-                    line + insert_text_lines,
-                    Box::new(left_branch.rotate_left()),
-                    right,
-                ).rotate_right()
-            } else {
-                Rope::Branch {
-                    height: new_height,
-                    weight: weight + insert_text_len,
-                    line: line + insert_text_lines,
-                    left: Box::new(left_branch),
-                    right: right
+            Rope::Leaf { text, len, lines } => {
+                if index >= *len {
+                    panic!("[Remove] Out of bounds index: Leaf of len {}, Index {}", len, index);
+                }
+                let removed_len = delete_len.min(*len - index);
+                if removed_len == *len {
+                    return (false, delete_len - removed_len);
+                }
+                let start = Self::get_char_index(text, *len, index);
+                let end = Self::get_char_index(text, *len, index + removed_len);
+                *lines -= Self::get_line_count(&text[start..end]);
+                text.replace_range(start..end, "");
+                *len -= removed_len;
+                (true, delete_len - removed_len)
+            },
+            Rope::Branch { height, weight, bytes, line, left, right } => {
+                if index >= *weight {
+                    let (right_kept, remaining_del) = Arc::make_mut(right)._remove(index - *weight, delete_len);
+                    if !right_kept {
+                        *self = Arc::unwrap_or_clone(std::mem::take(left));
+                        return (true, remaining_del);
+                    }
+                    *height = left.height().max(right.height()) + 1;
+                    return (true, remaining_del);
                 }
-            }
-        } else {
-            let right_weight = right.weight();
-            let right_branch = right._insert(index - weight, insert_text, insert_text_len, insert_text_lines);
-            let new_height = height.max(right_branch.height() + 1);
 
-            if (left.height() as isize - right_branch.height() as isize) < -1 && index - weight > right_weight {
-                Rope::new_branch(
-                    new_height,
-                    weight,
-                    line,
-                    left,
-                    Box::new(right_branch),
-                ).rotate_left()
-            } else if (left.height() as isize - right_branch.height() as isize) < -1 && index - weight <= right_weight {
-                Rope::new_branch(
-                    new_height,
-                    weight,
-                    line,
-                    left,
-                    Box::new(right_branch.rotate_right()),
-                ).rotate_left()
-            } else {
-                Rope::new_branch(
-                    new_height,
-                    weight,
-                    line,
-                    left,
-                    Box::new(right_branch),
-                )
-            }
+                let (left_kept, remaining_del) = Arc::make_mut(left)._remove(index, delete_len);
+                if !left_kept {
+                    // The right side takes this node's place and loses whatever the left could not hold
+                    *self = Arc::unwrap_or_clone(std::mem::take(right));
+                    return if remaining_del == 0 { (true, 0) } else { self._remove(0, remaining_del) };
+                }
+                let (right_kept, remaining_del_len) = if remaining_del == 0 {
+                    (true, 0)
+                } else {
+                    Arc::make_mut(right)._remove(0, remaining_del)
+                };
+                if !right_kept {
+                    *self = Arc::unwrap_or_clone(std::mem::take(left));
+                    return (true, remaining_del_len);
+                }
+                *height = left.height().max(right.height()) + 1;
+                *weight = *weight + remaining_del - delete_len;
+                *bytes = left.byte_len();
+                *line = left._line_count();
+                (true, remaining_del_len)
+            },
         }
     }
 
-    fn _remove(self, index: usize, delete_len: usize) -> (Option<Box<Self>>, usize) {
+    /// Number of newlines before index
+    fn line_at(&self, index: usize) -> usize {
         match self {
-            Rope::Leaf(text) => Self::_remove_leaf(index, delete_len, text),
-            Rope::Branch {
-                weight,
-                line,
-                left,
-                right,
-                ..
-            } => Self::_remove_branch(index, delete_len, weight, line, left, right),
+            Rope::Leaf { lines: 0, .. } => 0,
+            Rope::Leaf { text, .. } => text.chars().take(index).filter(|&c| c == '\n').count(),
+            Rope::Branch { weight, line, left, right, .. } => {
+                if index <= *weight {
+                    left.line_at(index)
+                } else {
+                    line + right.line_at(index - weight)
+                }
+            },
         }
     }
 
-    fn _remove_leaf(index: usize, delete_len: usize, text: String) -> (Option<Box<Self>>, usize) {
-        let text_len = text.chars().count();
-        if index >= text_len {
-            panic!("[Remove] Out of bounds index: Leaf of len {}, Index {}", text_len, index);
-        }
-        let len_after_index = text_len - index;
-        match len_after_index.cmp(&delete_len) {
-            Ordering::Equal => if index == 0 {
-                (None, 0)
-            } else {
-                (Some(Box::new(Rope::Leaf(text.chars().take(index).collect()))), 0)
-            },
-            Ordering::Greater => {
-                let mut char_iter = text.chars();
-                let mut new_str = String::with_capacity(text_len - delete_len);
-                new_str.extend(char_iter.by_ref().take(index));
-                new_str.extend(char_iter.skip(delete_len));
-                (Some(Box::new(Rope::Leaf(new_str))), 0)
-            },
-            Ordering::Less => if index == 0 {
-                (None, delete_len - text_len)
-            } else {
-                let mut char_iter = text.chars();
-                let mut new_str = String::with_capacity(index);
-                new_str.extend(char_iter.by_ref().take(index));
-                (Some(Box::new(Rope::Leaf(new_str))), delete_len - len_after_index)
-            }
-        }
+    /// Index of the first newline at or after index
+    fn next_newline(&self, index: usize) -> Option<usize> {
+        let line = self.line_at(index);
+        (line < self._line_count()).then(|| self._line_start_index(line) - 1)
     }
 
-    fn _remove_branch(
-        index: usize,
-        delete_len: usize,
-        weight: usize,
-        line: usize,
-        left: Box<Self>,
-        right: Box<Self>
-    ) -> (Option<Box<Self>>, usize) {
-        if index < weight {
-            match left._remove(index, delete_len) {
-                (Some(left_branch), 0) => (
-                    Some(Box::new(Rope::new_branch(
-                        left_branch.height().max(right.height()) + 1,
-                        weight - delete_len,
-                        left_branch._line_count(),
-                        left_branch,
-                        right,
-                    ))),
-                    0,
-                ),
-                (Some(left_branch), remaining_del) => {
-                    match right._remove(0, remaining_del) {
-                        (Some(right_branch), remaining_del_len) => (
-                            Some(Box::new(Rope::new_branch(
-                                left_branch.height().max(right_branch.height()) + 1,
-                                weight + remaining_del - delete_len,
-                                left_branch._line_count(),
-                                left_branch,
-                                right_branch,
-                            ))),
-                            remaining_del_len,
-                        ),
-                        (_, remaining_del_len) => (Some(left_branch), remaining_del_len),
-                    }
-                },
-                (None, 0) => (Some(right), 0),
-                (None, remaining_del) => right._remove(0, remaining_del),
-            }
-        } else {
-            match right._remove(index - weight, delete_len) {
-                (Some(right_branch), remaining_del_len) => (
-                    Some(Box::new(Self::new_branch(
-                        left.height().max(right_branch.height()) + 1,
-                        weight,
-                        line,
-                        left,
-                        right_branch,
-                    ))),
-                    remaining_del_len,
-                ),
-                (None, remaining_del_len) => (Some(left), remaining_del_len),
-            }
-        }
+    /// Index of the last newline before index
+    fn prev_newline(&self, index: usize) -> Option<usize> {
+        let line = self.line_at(index);
+        (line > 0).then(|| self._line_start_index(line - 1) - 1)
     }
 
     fn _line_start_index(&self, target_line: usize) -> usize {
@@ -346,7 +369,7 @@ impl Rope {
                     weight + right._line_start_index(target_line - line)
                 }
             },
-            Rope::Leaf(text) => {
+            Rope::Leaf { text, .. } => {
                 let target_newline_index = text.chars()
                     .enumerate()
                     .filter(|&(_, c)| c == '\n')
@@ -358,10 +381,39 @@ impl Rope {
         }
     }
 
+    /// Hangs leaves, in order, under a tree whose two sides never differ in height by more than one
+    fn from_leaves(leaves: Vec<String>) -> Self {
+        let count = leaves.len();
+        if count == 0 {
+            return Self::new();
+        }
+        Self::build(&mut leaves.into_iter(), count).0
+    }
+
+    /// Returns the subtree over the next count leaves with its char, byte and newline counts
+    fn build(leaves: &mut impl Iterator<Item = String>, count: usize) -> (Self, usize, usize, usize) {
+        if count == 1 {
+            let leaf = Rope::new_leaf(leaves.next().expect("Asked for more leaves than there are"));
+            let counts = (leaf.len(), leaf.byte_len(), leaf._line_count());
+            return (leaf, counts.0, counts.1, counts.2);
+        }
+        let (left, left_len, left_bytes, left_lines) = Self::build(leaves, count / 2);
+        let (right, right_len, right_bytes, right_lines) = Self::build(leaves, count - count / 2);
+        let height = left.height().max(right.height()) + 1;
+        let branch = Rope::new_branch(height, left_len, left_bytes, left_lines, Arc::new(left), Arc::new(right));
+        (branch, left_len + right_len, left_bytes + right_bytes, left_lines + right_lines)
+    }
+
+    /// Applies a rotation to the node where it sits in the tree
+    fn rotate(&mut self, rotation: fn(Self) -> Self) {
+        *self = rotation(std::mem::take(self));
+    }
+
     fn rotate_right(self) -> Self {
         if let Rope::Branch { 
             height: _,
             weight: root_weight,
+            bytes: root_bytes,
             line: root_line,
             left: root_left,
             right: root_right
@@ -369,19 +421,22 @@ impl Rope {
             if let Rope::Branch {
                 height: _,
                 weight: left_weight,
+                bytes: left_bytes,
                 line: left_line,
                 left: left_left,
                 right: left_right
-            } = *root_left {
+            } = Arc::unwrap_or_clone(root_left) {
                 let old_root_height = root_right.height().max(left_right.height()) + 1;
                 Rope::Branch {
                     height: old_root_height.max(left_left.height()) + 1,
                     weight: left_weight,
+                    bytes: left_bytes,
                     line: left_line,
                     left: left_left,
-                    right: Box::new(Rope::Branch {
+                    right: Arc::new(Rope::Branch {
                         height: old_root_height,
                         weight: root_weight - left_weight,
+                        bytes: root_bytes - left_bytes,
                         line: root_line - left_line,
                         left: left_right,
                         right: root_right,
@@ -395,6 +450,7 @@ impl Rope {
         if let Rope::Branch { 
             height: _,
             weight: root_weight,
+            bytes: root_bytes,
             line: root_line,
             left: root_left,
             right: root_right
@@ -402,18 +458,21 @@ impl Rope {
             if let Rope::Branch {
                 height: _,
                 weight: right_weight,
+                bytes: right_bytes,
                 line: right_line,
                 left: right_left,
                 right: right_right
-            } = *root_right {
+            } = Arc::unwrap_or_clone(root_right) {
                 let old_root_height = root_left.height().max(right_left.height()) + 1;
                 Rope::Branch {
                     height: old_root_height.max(right_right.height()) + 1,
                     weight: root_weight + right_weight,
+                    bytes: root_bytes + right_bytes,
                     line: root_line + right_line,
-                    left: Box::new(Rope::Branch {
+                    left: Arc::new(Rope::Branch {
                         height: old_root_height,
                         weight: root_weight,
+                        bytes: root_bytes,
                         line: root_line,
                         left: root_left,
                         right: right_left,
@@ -435,7 +494,7 @@ impl Rope {
     fn weight(&self) -> usize {
         match self {
             Rope::Branch { weight, .. } => *weight,
-            Rope::Leaf(text) => text.chars().count()
+            Rope::Leaf { len, .. } => *len,
         }
     }
 
@@ -451,7 +510,7 @@ impl Rope {
     fn _line_count(&self) -> usize {
         match self {
             Rope::Branch { line, right, .. } => *line + right._line_count(),
-            Rope::Leaf(text) => Self::get_line_count(&text),
+            Rope::Leaf { lines, .. } => *lines,
         }
     }
 
@@ -459,18 +518,21 @@ impl Rope {
         match index {
             i if i == text_len  => text.len(),
             0 => 0,
+            // Every char is a single byte
+            _ if text_len == text.len() => index,
             _ => text.char_indices().nth(index).expect("Should be impossible").0
         }
     }
 
     fn as_str(&self, as_str: &mut String, tabs: usize) {
         match self {
-            Rope::Branch { height, weight, line, left, right } => {
+            Rope::Branch { height, weight, bytes, line, left, right } => {
                 as_str.push_str("Branch [\n");
                 as_str.push_str("-".repeat(tabs+1).as_str());
-                as_str.push_str(format!("h: {}, w: {}, ln: {},\n",
+                as_str.push_str(format!("h: {}, w: {}, b: {}, ln: {},\n",
                     height,
                     weight,
+                    bytes,
                     line,
                 ).as_str());
                 as_str.push_str("-".repeat(tabs+1).as_str());
@@ -484,7 +546,7 @@ impl Rope {
                 as_str.push_str("-".repeat(tabs).as_str());
                 as_str.push(']');
             },
-            Rope::Leaf(text) => {
+            Rope::Leaf { text, .. } => {
                 as_str.push_str(format!("Leaf [txt: {:?}]", text).as_str());
             }
         }
@@ -501,197 +563,263 @@ impl Debug for Rope {
 
 impl Default for Rope {
     fn default() -> Self {
-        Rope::Leaf(String::new())
+        Rope::new_leaf(String::new())
     }
 }
 
-pub struct RopeIterator<'a> {
-    stack: Vec<&'a Rope>,
-    current_leaf: Option<(&'a String, usize)>,
+/// Borrowed run of the chars start..end of a rope, cheap to make and to copy
+///
+/// Offsets given to and returned by its methods count from the start of the slice
+#[derive(Clone, Copy)]
+pub struct RopeSlice<'a> {
+    root: &'a Rope,
+    start: usize,
+    end: usize,
 }
 
-impl <'a> RopeIterator<'a> {
-    fn new(root: &'a Rope) -> Self {
-        let mut new_iter = Self {
-            stack: Vec::new(),
-            current_leaf: None,
+impl<'a> RopeSlice<'a> {
+    fn new(root: &'a Rope, range: impl RangeBounds<usize>) -> Self {
+        let (start, end) = Self::bounds(range, root.len());
+        Self { root, start, end }
+    }
+
+    /// Start and end of range over len chars
+    fn bounds(range: impl RangeBounds<usize>, len: usize) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start + 1,
+            Bound::Unbounded => 0,
         };
-        new_iter.push_left(root);
-        new_iter
-    }
-    
-    fn push_left(&mut self, mut node: &'a Rope) {
-        while let Rope::Branch { left, .. } = node {
-            self.stack.push(node);
-            node = left;
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end + 1,
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+        if start > end || end > len {
+            panic!("[Slice] Out of bounds range: Slice of len {}, Range {}..{}", len, start, end);
         }
-        self.stack.push(node);
+        (start, end)
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Narrows the slice down to range
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> RopeSlice<'a> {
+        let (start, end) = Self::bounds(range, self.len());
+        RopeSlice { root: self.root, start: self.start + start, end: self.start + end }
+    }
+
+    pub fn chunks(&self) -> Chunks<'a> {
+        Chunks::new(*self)
+    }
+
+    pub fn chars(self) -> impl DoubleEndedIterator<Item = char> + 'a {
+        self.chunks().flat_map(str::chars)
+    }
+
+    /// Lines of the slice without their newlines, a slice ending in a newline ends with an empty line
+    pub fn lines(&self) -> Lines<'a> {
+        Lines { root: self.root, front: self.start, back: self.end, finished: false }
     }
 }
 
-impl <'a> Iterator for RopeIterator<'a> {
-    type Item = char;
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if let Some((text, ref mut idx)) = self.current_leaf {
-                if *idx < text.len() {
-                    let ch = text[*idx..].chars().next()?;
-                    *idx += ch.len_utf8();
-                    return Some(ch);
-                } else {
-                    self.current_leaf = None;
-                }
-            }
+impl Display for RopeSlice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chunks().try_for_each(|chunk| f.write_str(chunk))
+    }
+}
 
-            loop {
-                let node = self.stack.pop()?;
-                match node {
-                    Rope::Leaf(text) => {
-                        self.current_leaf = Some((text, 0));
-                        break;
-                    },
-                    Rope::Branch { right, .. } => self.push_left(right),
-                }
-            }
+/// Borrows the text when it lies in a single leaf, copies it otherwise
+impl<'a> From<RopeSlice<'a>> for Cow<'a, str> {
+    fn from(slice: RopeSlice<'a>) -> Self {
+        let mut chunks = slice.chunks();
+        match (chunks.next(), chunks.next()) {
+            (None, _) => Cow::Borrowed(""),
+            (Some(chunk), None) => Cow::Borrowed(chunk),
+            _ => Cow::Owned(slice.to_string()),
         }
     }
 }
 
-pub struct RopeLineIterator<'a> {
-    stack: Vec<&'a Rope>,
-    current_leaf: Option<(&'a String, usize)>,
-    current_line: Vec<(u32, u32)>,
-    finished: bool,
+/// Pieces of a slice in order, borrowed from the leaves of the rope
+pub struct Chunks<'a> {
+    /// Nodes still to visit from the front, the next one on top
+    front: Vec<&'a Rope>,
+    /// Nodes still to visit from the back, the next one on top
+    back: Vec<&'a Rope>,
+    /// Chars before the slice in the first leaf taken from the front
+    front_skip: usize,
+    /// Chars up to the end of the slice in the first leaf taken from the back
+    back_keep: Option<usize>,
+    /// Chars of the slice not handed out from either end yet
+    remaining: usize,
 }
 
-impl <'a> RopeLineIterator<'a> {
-    fn new(root: &'a Rope) -> Self {
-        let mut new_iter = Self {
-            stack: Vec::new(),
-            current_leaf: None,
-            current_line: Vec::new(),
-            finished: false,
+impl<'a> Chunks<'a> {
+    fn new(slice: RopeSlice<'a>) -> Self {
+        let mut chunks = Self {
+            front: Vec::new(),
+            back: Vec::new(),
+            front_skip: 0,
+            back_keep: None,
+            remaining: slice.len(),
         };
-        new_iter.push_left(root);
-        new_iter
-    }
-
-    fn check_current_line(&mut self) {
-        if let Some((_, level)) = self.current_line.last() {
-            if *level == 0 {
-                let (lines, _) = self.current_line.pop().unwrap();
-                self.current_line.last_mut().map(|(pos, _)| *pos += lines);
+        if chunks.remaining == 0 {
+            return chunks;
+        }
+        let (mut node, mut index) = (slice.root, slice.start);
+        while let Rope::Branch { weight, left, right, .. } = node {
+            if index < *weight {
+                chunks.front.push(right);
+                node = left;
+            } else {
+                index -= weight;
+                node = right;
             }
         }
-    }
+        chunks.front.push(node);
+        chunks.front_skip = index;
 
-    fn push_left(&mut self, mut node: &'a Rope) {
-        self.check_current_line();
-        let current_stack_len = self.stack.len() as u32;
-        while let Rope::Branch { left, .. } = node {
-            self.stack.push(node);
-            node = left;
+        let (mut node, mut index) = (slice.root, slice.end);
+        while let Rope::Branch { weight, left, right, .. } = node {
+            if index <= *weight {
+                node = left;
+            } else {
+                chunks.back.push(left);
+                index -= weight;
+                node = right;
+            }
         }
-        self.stack.push(node);
-        let new_stack_len = self.stack.len() as u32;
-        self.current_line.push((0, new_stack_len - current_stack_len));
+        chunks.back.push(node);
+        chunks.back_keep = Some(index);
+        chunks
     }
 }
 
-impl <'a> Iterator for RopeLineIterator<'a> {
-    type Item = String;
+impl<'a> Iterator for Chunks<'a> {
+    type Item = &'a str;
     fn next(&mut self) -> Option<Self::Item> {
-        let mut line_str = String::new();
-        loop {
-            while let Some((text, ref mut index)) = self.current_leaf {
-                if *index < text.len() {
-                    let Some(ch) = text[*index..].chars().next() else { break };
-                    *index += ch.len_utf8();
-                    if ch == '\n' {
-                        self.current_line.last_mut().map(|(pos, _)| *pos += 1);
-                        return Some(line_str);
-                    } else {
-                        line_str.push(ch);
-                    }
-                } else {
-                    self.current_leaf = None;
-                }
+        while self.remaining > 0 {
+            let mut node = self.front.pop()?;
+            while let Rope::Branch { left, right, .. } = node {
+                self.front.push(right);
+                node = left;
             }
-
-            loop {
-                self.check_current_line();
-                let Some(node) = self.stack.pop() else {
-                    if self.finished {
-                        return None;
-                    } else {
-                        self.finished = true;
-                        return Some(line_str);
-                    }
-                };
-                self.current_line.last_mut().map(|(_, level)| *level -= 1);
-                match node {
-                    Rope::Leaf(text) => {
-                        self.current_leaf = Some((text, 0));
-                        break;
-                    },
-                    Rope::Branch { right, .. } => self.push_left(right),
-                }
+            let Rope::Leaf { text, len, .. } = node else { unreachable!() };
+            let skip = std::mem::take(&mut self.front_skip);
+            let take = (len - skip).min(self.remaining);
+            if take == 0 {
+                continue;
             }
+            self.remaining -= take;
+            let start = Rope::get_char_index(text, *len, skip);
+            let end = Rope::get_char_index(text, *len, skip + take);
+            return Some(&text[start..end]);
         }
+        None
     }
+}
 
-    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
-        loop {
-            if let Some(Rope::Branch { line, .. }) = self.stack.last() {
-                if *line + self.current_line.last().map(|&(pos, _)| pos as usize).unwrap() < n {
-                    self.current_leaf = None;
-                }
+impl DoubleEndedIterator for Chunks<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.remaining > 0 {
+            let mut node = self.back.pop()?;
+            while let Rope::Branch { left, right, .. } = node {
+                self.back.push(left);
+                node = right;
             }
-            if let Some((text, ref mut index)) = self.current_leaf {
-                if *index < text.len() {
-                    let nth_newline = text[*index..].char_indices()
-                        .filter(|&(_, c)| c == '\n')
-                        .scan((n, 0), |(acc, _), (index, _)| { *acc -= 1; Some((*acc, index)) })
-                        .take(n)
-                        .last();
-                    if let Some((new_n, new_index)) = nth_newline {
-                        self.current_line.last_mut().map(|(pos, _)| *pos += (n - new_n) as u32);
-                        *index += new_index + '\n'.len_utf8();
-                        if new_n == 0 {
-                            break;
-                        }
-                        n = new_n;
-                    } else {
-                        break;
-                    }
-                }
+            let Rope::Leaf { text, len, .. } = node else { unreachable!() };
+            let keep = self.back_keep.take().unwrap_or(*len);
+            let take = keep.min(self.remaining);
+            if take == 0 {
+                continue;
             }
+            self.remaining -= take;
+            let start = Rope::get_char_index(text, *len, keep - take);
+            let end = Rope::get_char_index(text, *len, keep);
+            return Some(&text[start..end]);
+        }
+        None
+    }
+}
 
-            loop {
-                self.check_current_line();
-                let Some(node) = self.stack.pop() else {
-                    return self.next();
-                };
-                self.current_line.last_mut().map(|(_, level)| *level -= 1);
-                match node {
-                    Rope::Leaf(text) => {
-                        self.current_leaf = Some((text, 0));
-                        break;
-                    },
-                    Rope::Branch { right, line: current_node_line, .. } => {
-                        if let Some(Rope::Branch { line: parent_line, .. }) = self.stack.last() {
-                            if *parent_line + self.current_line.last().map(|&(pos, _)| pos as usize).unwrap() < n {
-                                continue;
-                            }
-                        }
-                        n -= current_node_line - self.current_line.last().map(|&(pos, _)| pos as usize).unwrap();
-                        self.current_line.last_mut().map(|(pos, _)| *pos = *current_node_line as u32);
-                        self.push_left(right);
-                    }
-                }
-            }
+/// Lines of a slice from either end, each found in O(log n) from the newline counts of the rope
+pub struct Lines<'a> {
+    root: &'a Rope,
+    /// Start of the next line from the front
+    front: usize,
+    /// End of the next line from the back
+    back: usize,
+    finished: bool,
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = RopeSlice<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.root.next_newline(self.front).filter(|&newline| newline < self.back) {
+            Some(newline) => {
+                let line = self.root.slice(self.front..newline);
+                self.front = newline + 1;
+                Some(line)
+            },
+            None => {
+                self.finished = true;
+                Some(self.root.slice(self.front..self.back))
+            },
+        }
+    }
+
+    /// Jumps straight to the line using the newline counts of the rope, instead of going over every line before it
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        if self.finished || n == 0 {
+            return self.next();
+        }
+        match self.line_start_after(n) {
+            Some(start) => {
+                self.front = start;
+                self.next()
+            },
+            None => {
+                self.finished = true;
+                None
+            },
+        }
+    }
+}
+
+impl<'a> Lines<'a> {
+    /// Start of the line n lines below the next one from the front, None if the slice ends before it
+    fn line_start_after(&self, n: usize) -> Option<usize> {
+        let target_line = self.root.line_at(self.front) + n;
+        if target_line >= self.root.line_count() {
+            return None;
+        }
+        let start = self.root.line_start_index(target_line);
+        (start <= self.back).then_some(start)
+    }
+}
+
+impl DoubleEndedIterator for Lines<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.root.prev_newline(self.back).filter(|&newline| newline >= self.front) {
+            Some(newline) => {
+                let line = self.root.slice(newline + 1..self.back);
+                self.back = newline;
+                Some(line)
+            },
+            None => {
+                self.finished = true;
+                Some(self.root.slice(self.front..self.back))
+            },
         }
-        self.next()
     }
 }
 
@@ -702,35 +830,35 @@ mod tests {
 
     #[test]
     fn new_test() {
-        let new_rope: String = Rope::new().chars().collect();
+        let new_rope: String = Rope::new().slice(..).chars().collect();
         assert_eq!(new_rope, "");
     }
 
     #[test]
     fn empty_insert_test() {
-        let new_rope: String = Rope::new().insert(0, "Hello, world!").chars().collect();
-        assert_eq!(new_rope, "Hello, world!");
+        let mut rope = Rope::new();
+        rope.insert(0, "Hello, world!");
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "Hello, world!");
     }
 
     #[test]
     fn multi_insert_test() {
-        let new_rope: String = Rope::new()
-            .insert(0, "Helloworld!")
-            .insert(5, ", ")
-            .chars().collect();
-        assert_eq!(new_rope, "Hello, world!");
+        let mut rope = Rope::new();
+        rope.insert(0, "Helloworld!");
+        rope.insert(5, ", ");
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "Hello, world!");
     }
 
     #[test]
     fn multi_insert_height_test() {
-        let new_rope = Rope::new()
-            .insert(0, "Helloworld!")
-            .insert(5, ", ")
-            .insert(0, "0")
-            .insert(7, "0")
-            .insert(2, "0")
-            .insert(9, "0");
-        assert_eq!(new_rope.chars().collect::<String>(), "0H0ello,00 world!");
+        let mut new_rope = Rope::new();
+        new_rope.insert(0, "Helloworld!");
+        new_rope.insert(5, ", ");
+        new_rope.insert(0, "0");
+        new_rope.insert(7, "0");
+        new_rope.insert(2, "0");
+        new_rope.insert(9, "0");
+        assert_eq!(new_rope.slice(..).chars().collect::<String>(), "0H0ello,00 world!");
         assert_eq!(new_rope.height(), 0)
     }
 
@@ -747,10 +875,12 @@ mod tests {
             let correct_output: String = words.iter().map(|word| word.chars()).flatten().collect();
 
             let rope_output: String = words.iter()
-                .fold(Rope::new(), |rope, word| {
+                .fold(Rope::new(), |mut rope, word| {
                     let rope_len = rope.len();
-                    rope.insert(rope_len, word)
+                    rope.insert(rope_len, word);
+                    rope
                 })
+                .slice(..)
                 .chars()
                 .collect();
 
@@ -782,16 +912,19 @@ mod tests {
 
             let rope = random_floats.into_iter()
                 .zip(words.iter())
-                .fold(Rope::new(), |rope, (float, word)| {
+                .fold(Rope::new(), |mut rope, (float, word)| {
                     let rope_len = rope.len() as f64;
-                    rope.insert((rope_len * float) as usize, &word)
+                    rope.insert((rope_len * float) as usize, &word);
+                    rope
                 }
             );
-            let rope_output: String = rope.chars().collect();
+            let rope_output: String = rope.slice(..).chars().collect();
 
             assert_eq!(correct_output.len(), rope_output.len());
             assert_eq!(correct_output, rope_output);
             assert_eq!(rope.line_count(), expected_lines + 1);
+            check_metadata(&rope);
+            check_positions(&rope, &correct_output, &mut rng);
         }
     }
 
@@ -800,22 +933,24 @@ mod tests {
         let rope = Rope::Branch {
             height: 2,
             weight: 7,
+            bytes: 7,
             line: 3,
-            left: Box::new(Rope::Branch {
+            left: Arc::new(Rope::Branch {
                 height: 1,
                 weight: 4,
+                bytes: 4,
                 line: 1,
-                left: Box::new(Rope::Leaf(String::from("TES\n"))),
-                right: Box::new(Rope::Leaf(String::from("t\n\n"))),
+                left: Arc::new(Rope::new_leaf(String::from("TES\n"))),
+                right: Arc::new(Rope::new_leaf(String::from("t\n\n"))),
             }),
-            right: Box::new(Rope::Leaf(String::from("xin\n"))),
+            right: Arc::new(Rope::new_leaf(String::from("xin\n"))),
         };
         let rope = rope.rotate_right();
         assert_eq!(rope.height(), 2);
         assert_eq!(rope.len(), 11);
         assert_eq!(rope.line_count(), 5);
         assert_eq!(rope.weight(), 4);
-        assert_eq!(rope.chars().collect::<String>(), String::from("TES\nt\n\nxin\n"));
+        assert_eq!(rope.slice(..).chars().collect::<String>(), String::from("TES\nt\n\nxin\n"));
 
         match &rope {
             Rope::Branch { left, right, .. } => { 
@@ -823,13 +958,13 @@ mod tests {
                 assert_eq!(left.len(), 4);
                 assert_eq!(left._line_count(), 1);
                 assert_eq!(left.weight(), 4);
-                assert_eq!(left.chars().collect::<String>(), String::from("TES\n"));
+                assert_eq!(left.slice(..).chars().collect::<String>(), String::from("TES\n"));
 
                 assert_eq!(right.height(), 1);
                 assert_eq!(right.len(), 7);
                 assert_eq!(right._line_count(), 3);
                 assert_eq!(right.weight(), 3);
-                assert_eq!(right.chars().collect::<String>(), String::from("t\n\nxin\n"));
+                assert_eq!(right.slice(..).chars().collect::<String>(), String::from("t\n\nxin\n"));
             }
             _ => assert!(false),
         }
@@ -840,15 +975,17 @@ mod tests {
         let rope = Rope::Branch {
             height: 2,
             weight: 4,
+            bytes: 4,
             line: 1,
-            right: Box::new(Rope::Branch {
+            right: Arc::new(Rope::Branch {
                 height: 1,
                 weight: 3,
+                bytes: 3,
                 line: 1,
-                left: Box::new(Rope::Leaf(String::from("ts\n"))),
-                right: Box::new(Rope::Leaf(String::from("xin\n"))),
+                left: Arc::new(Rope::new_leaf(String::from("ts\n"))),
+                right: Arc::new(Rope::new_leaf(String::from("xin\n"))),
             }),
-            left: Box::new(Rope::Leaf(String::from("TES\n"))),
+            left: Arc::new(Rope::new_leaf(String::from("TES\n"))),
         };
 
         let rope = rope.rotate_left();
@@ -856,7 +993,7 @@ mod tests {
         assert_eq!(rope.len(), 11);
         assert_eq!(rope.line_count(), 4);
         assert_eq!(rope.weight(), 7);
-        assert_eq!(rope.chars().collect::<String>(), String::from("TES\nts\nxin\n"));
+        assert_eq!(rope.slice(..).chars().collect::<String>(), String::from("TES\nts\nxin\n"));
 
         match &rope {
             Rope::Branch { left, right, .. } => {
@@ -864,9 +1001,9 @@ mod tests {
                 assert_eq!(right.len(), 4);
                 assert_eq!(right._line_count(), 1);
                 assert_eq!(right.weight(), 4);
-                assert_eq!(right.chars().collect::<String>(), String::from("xin\n"));
+                assert_eq!(right.slice(..).chars().collect::<String>(), String::from("xin\n"));
 
-                assert_eq!(left.chars().collect::<String>(), String::from("TES\nts\n"));
+                assert_eq!(left.slice(..).chars().collect::<String>(), String::from("TES\nts\n"));
                 assert_eq!(left.height(), 1);
                 assert_eq!(left._line_count(), 2);
                 assert_eq!(left.weight(), 4);
@@ -881,24 +1018,26 @@ mod tests {
         let rope = Rope::Branch {
             height: 2,
             weight: 7,
+            bytes: 7,
             line: 1,
-            left: Box::new(Rope::Branch {
+            left: Arc::new(Rope::Branch {
                 height: 1,
                 weight: 3,
+                bytes: 3,
                 line: 1,
-                left: Box::new(Rope::Leaf(String::from("ts\n"))),
-                right: Box::new(Rope::Leaf(String::from("xing"))),
+                left: Arc::new(Rope::new_leaf(String::from("ts\n"))),
+                right: Arc::new(Rope::new_leaf(String::from("xing"))),
             }),
-            right: Box::new(Rope::Leaf(String::from("TE\n\n"))),
+            right: Arc::new(Rope::new_leaf(String::from("TE\n\n"))),
         };
         let expected_height = rope.height();
         let expected_len = rope.len();
         let expected_weight = rope.weight();
         let expected_lines = rope.line_count();
-        let expected_str: String = rope.chars().collect();
+        let expected_str: String = rope.slice(..).chars().collect();
         let rope = rope.rotate_right().rotate_left();
 
-        assert_eq!(rope.chars().collect::<String>(), expected_str);
+        assert_eq!(rope.slice(..).chars().collect::<String>(), expected_str);
         assert_eq!(rope.height(), expected_height);
         assert_eq!(rope.weight(), expected_weight);
         assert_eq!(rope.len(), expected_len);
@@ -910,24 +1049,26 @@ mod tests {
         let rope = Rope::Branch {
             height: 2,
             weight: 4,
+            bytes: 4,
             line: 0, 
-            left: Box::new(Rope::Leaf(String::from("TEST"))),
-            right: Box::new(Rope::Branch {
+            left: Arc::new(Rope::new_leaf(String::from("TEST"))),
+            right: Arc::new(Rope::Branch {
                 height: 1,
                 weight: 3,
+                bytes: 3,
                 line: 0,
-                left: Box::new(Rope::Leaf(String::from("tst"))),
-                right: Box::new(Rope::Leaf(String::from("xing"))),
+                left: Arc::new(Rope::new_leaf(String::from("tst"))),
+                right: Arc::new(Rope::new_leaf(String::from("xing"))),
             }),
         };
         let expected_height = rope.height();
         let expected_len = rope.len();
         let expected_weight = rope.weight();
-        let expected_str: String = rope.chars().collect();
+        let expected_str: String = rope.slice(..).chars().collect();
         let expected_lines = rope.line_count();
         let rope = rope.rotate_left().rotate_right();
 
-        assert_eq!(rope.chars().collect::<String>(), expected_str);
+        assert_eq!(rope.slice(..).chars().collect::<String>(), expected_str);
         assert_eq!(rope.height(), expected_height);
         assert_eq!(rope.weight(), expected_weight);
         assert_eq!(rope.len(), expected_len);
@@ -936,29 +1077,149 @@ mod tests {
 
     #[test]
     fn non_ascii_insert_test() {
-        let rope = Rope::new();
-        let rope = rope.insert(0, "爆発しませんように");
-        let rope = rope.insert(4, "何をしていますか？");
+        let mut rope = Rope::new();
+        rope.insert(0, "爆発しませんように");
+        rope.insert(4, "何をしていますか？");
 
-        assert_eq!(rope.chars().collect::<String>(), "爆発しま何をしていますか？せんように");
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "爆発しま何をしていますか？せんように");
     }
 
     #[test]
     fn non_ascii_and_ascii_insert_test() {
-        let rope = Rope::new();
-        let rope = rope.insert(0, "爆発しませんように");
-        let rope = rope.insert(4, "何をしていますか？");
-        let rope = rope.insert(7, "hElLo!");
+        let mut rope = Rope::new();
+        rope.insert(0, "爆発しませんように");
+        rope.insert(4, "何をしていますか？");
+        rope.insert(7, "hElLo!");
+
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "爆発しま何をしhElLo!ていますか？せんように");
+    }
+
+    #[test]
+    fn mixed_width_fuzz_test() {
+        let mut rng = rand::thread_rng();
+        let pieces = ["a", "é", "爆発", "\n", "🦀", "bc\n", "\n\n", "何をしていますか？"];
+        for _ in 0..20 {
+            let mut text = String::new();
+            let mut rope = Rope::new();
+            for _ in 0..5_000 {
+                let len = text.chars().count();
+                let index = rng.gen_range(0..=len);
+                if rng.gen_bool(0.3) && index < len {
+                    let remove_len = rng.gen_range(1..=(len - index).min(40));
+                    let start = text.char_indices().nth(index).unwrap().0;
+                    let end = text.char_indices().nth(index + remove_len).map_or(text.len(), |(i, _)| i);
+                    text.replace_range(start..end, "");
+                    rope.remove(index, remove_len);
+                } else {
+                    let insert_text = (0..rng.gen_range(1..20)).map(|_| pieces[rng.gen_range(0..pieces.len())]).collect::<String>();
+                    let start = text.char_indices().nth(index).map_or(text.len(), |(i, _)| i);
+                    text.insert_str(start, &insert_text);
+                    rope.insert(index, &insert_text);
+                }
+            }
+            assert_eq!(rope.slice(..).chars().collect::<String>(), text);
+            check_metadata(&rope);
+            check_positions(&rope, &text, &mut rng);
+        }
+    }
+
+    #[test]
+    fn slice_test() {
+        let mut rng = rand::thread_rng();
+        let pieces = ["a", "é", "爆発", "\n", "🦀", "bc\n", "\n\n", "何をしていますか？"];
+        let text = (0..30_000).map(|_| pieces[rng.gen_range(0..pieces.len())]).collect::<String>();
+        let chars = text.chars().collect::<Vec<_>>();
+        let rope = Rope::from_str(&text);
+        for _ in 0..200 {
+            let start = rng.gen_range(0..=chars.len());
+            let end = rng.gen_range(start..=chars.len());
+            let expected = chars[start..end].iter().collect::<String>();
+            let slice = rope.slice(start..end);
+
+            assert_eq!(slice.len(), end - start);
+            assert_eq!(slice.to_string(), expected);
+            assert_eq!(slice.chunks().rev().collect::<Vec<_>>().concat().len(), expected.len());
+            assert_eq!(slice.chars().rev().collect::<String>(), expected.chars().rev().collect::<String>());
+
+            let lines = expected.split('\n').collect::<Vec<_>>();
+            let line = rng.gen_range(0..lines.len() + 1);
+            assert_eq!(slice.lines().nth(line).map(|line| line.to_string()).as_deref(), lines.get(line).copied());
+            assert_eq!(slice.lines().map(|line| line.to_string()).collect::<Vec<_>>(), lines);
+            assert_eq!(slice.lines().rev().map(|line| line.to_string()).collect::<Vec<_>>(), lines.iter().rev().copied().collect::<Vec<_>>());
+
+            let inner_start = rng.gen_range(0..=slice.len());
+            let inner = slice.slice(inner_start..);
+            assert_eq!(inner.to_string(), chars[start + inner_start..end].iter().collect::<String>());
+        }
+    }
 
-        assert_eq!(rope.chars().collect::<String>(), "爆発しま何をしhElLo!ていますか？せんように");
+    #[test]
+    fn slice_chunks_meet_test() {
+        let text = "0123456789".repeat(2_000);
+        let rope = Rope::from_str(&text);
+        let slice = rope.slice(5..19_995);
+        let mut chunks = slice.chunks();
+        let mut front = String::new();
+        let mut back = Vec::new();
+        loop {
+            match (chunks.next(), chunks.next_back()) {
+                (None, None) => break,
+                (next, next_back) => {
+                    front.push_str(next.unwrap_or(""));
+                    back.extend(next_back);
+                },
+            }
+        }
+        back.reverse();
+        assert_eq!(front + &back.concat(), &text[5..19_995]);
+        assert_eq!(rope.slice(3..3).to_string(), "");
+        assert_eq!(rope.slice(..).lines().count(), 1);
+        assert_eq!(Rope::from_str("a\n").slice(..).lines().map(|line| line.to_string()).collect::<Vec<_>>(), ["a", ""]);
+    }
+
+    #[test]
+    fn slice_cow_test() {
+        let text = "0123456789".repeat(2_000);
+        let rope = Rope::from_str(&text);
+        assert!(matches!(Cow::from(rope.slice(5..15)), Cow::Borrowed("5678901234")));
+        assert!(matches!(Cow::from(rope.slice(3..3)), Cow::Borrowed("")));
+        let across_leaves = Cow::from(rope.slice(5..19_995));
+        assert!(matches!(across_leaves, Cow::Owned(_)));
+        assert_eq!(across_leaves, &text[5..19_995]);
+    }
+
+    #[test]
+    fn snapshot_test() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Rope>();
+
+        let text = "0123456789\n".repeat(5_000);
+        let mut rope = Rope::from_str(&text);
+        let snapshot = rope.clone();
+        rope.insert(20_000, "edited");
+        rope.remove(0, 11);
+
+        assert_eq!(snapshot.slice(..).chars().collect::<String>(), text);
+        let mut expected = text.clone();
+        expected.insert_str(20_000, "edited");
+        expected.replace_range(0..11, "");
+        assert_eq!(rope.slice(..).chars().collect::<String>(), expected);
+        check_metadata(&rope);
+        check_metadata(&snapshot);
+
+        // Both edits were on the left, the right side is still the snapshot's
+        match (&rope, &snapshot) {
+            (Rope::Branch { right, .. }, Rope::Branch { right: snapshot_right, .. }) => assert!(Arc::ptr_eq(right, snapshot_right)),
+            _ => panic!("Expected branches"),
+        }
     }
 
     #[test]
     fn remove_test() {
         let mut rope = Rope::new();
-        rope = rope.insert(0, "This is not cool!");
-        rope = rope.remove(8, 4);
-        assert_eq!(rope.chars().collect::<String>(), "This is cool!");
+        rope.insert(0, "This is not cool!");
+        rope.remove(8, 4);
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "This is cool!");
     }
 
     #[test]
@@ -975,14 +1236,15 @@ mod tests {
 
             let correct_output: String = words.iter().map(|word| word.chars()).flatten().collect();
 
-            let mut rope = words.iter().fold(Rope::new(), |rope, word| {
+            let mut rope = words.iter().fold(Rope::new(), |mut rope, word| {
                 let rope_len = rope.len();
-                rope.insert(rope_len, word)
+                rope.insert(rope_len, word);
+                rope
             });
             
-            rope = rope.insert(100, &word_to_add_remove);
-            rope = rope.remove(100, word_to_add_remove_char_count);
-            assert_eq!(rope.chars().collect::<String>(), correct_output);
+            rope.insert(100, &word_to_add_remove);
+            rope.remove(100, word_to_add_remove_char_count);
+            assert_eq!(rope.slice(..).chars().collect::<String>(), correct_output);
         }
     }
 
@@ -1016,25 +1278,121 @@ mod tests {
 
             let mut rope = random_floats.into_iter()
                 .zip(words.iter())
-                .fold(Rope::new(), |rope, (float, word)| {
+                .fold(Rope::new(), |mut rope, (float, word)| {
                     let rope_len = rope.len() as f64;
-                    rope.insert((rope_len * float) as usize, &word)
+                    rope.insert((rope_len * float) as usize, &word);
+                    rope
                 }
             );
             let insert_index = (rope.len() as f64 * insert_remove_ratio) as usize;
-            rope = rope.insert(insert_index, &insert_remove_word);
-            rope = rope.remove(insert_index, insert_remove_word_char_count);
+            rope.insert(insert_index, &insert_remove_word);
+            rope.remove(insert_index, insert_remove_word_char_count);
             
-            assert_eq!(rope.chars().collect::<String>(), correct_output);
+            assert_eq!(rope.slice(..).chars().collect::<String>(), correct_output);
             assert_eq!(rope.line_count(), expected_lines + 1);
+            check_metadata(&rope);
+            check_positions(&rope, &correct_output, &mut rng);
+        }
+    }
+
+    /// True if no branch's sides differ in height by more than one
+    fn is_balanced(rope: &Rope) -> bool {
+        match rope {
+            Rope::Branch { left, right, .. } => rope.get_balance().abs() <= 1 && is_balanced(left) && is_balanced(right),
+            Rope::Leaf { .. } => true,
+        }
+    }
+
+    /// Checks every count cached in the rope against the text under it
+    fn check_metadata(rope: &Rope) {
+        match rope {
+            Rope::Branch { height, weight, bytes, line, left, right } => {
+                let left_text = left.slice(..).chars().collect::<String>();
+                assert_eq!(*height, left.height().max(right.height()) + 1);
+                assert_eq!(*weight, left_text.chars().count());
+                assert_eq!(*bytes, left_text.len());
+                assert_eq!(*line, Rope::get_line_count(&left_text));
+                check_metadata(left);
+                check_metadata(right);
+            },
+            Rope::Leaf { text, len, lines } => {
+                assert_eq!(*len, text.chars().count());
+                assert_eq!(*lines, Rope::get_line_count(text));
+            },
+        }
+    }
+
+    /// Compares char lookups and line positions at random points of the rope with those of text
+    fn check_positions(rope: &Rope, text: &str, rng: &mut impl Rng) {
+        let chars = text.chars().collect::<Vec<_>>();
+        let line_starts = std::iter::once(0)
+            .chain(chars.iter().enumerate().filter(|&(_, &c)| c == '\n').map(|(i, _)| i + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(rope.len(), chars.len());
+        assert_eq!(rope.byte_len(), text.len());
+        assert_eq!(rope.line_count(), line_starts.len());
+        for _ in 0..200 {
+            let index = rng.gen_range(0..=chars.len());
+            let line = line_starts.partition_point(|&start| start <= index) - 1;
+            assert_eq!(rope.get(index), chars.get(index).copied());
+            assert_eq!(rope.index_pos(index), (line, index - line_starts[line]));
+            assert_eq!(rope.line_start_index(line), line_starts[line]);
         }
     }
 
+    /// Hands out a few bytes per read, cutting multi-byte chars in half
+    struct TrickleReader<'a>(&'a [u8]);
+
+    impl Read for TrickleReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = self.0.len().min(buf.len()).min(3);
+            buf[..len].copy_from_slice(&self.0[..len]);
+            self.0 = &self.0[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn from_str_test() {
+        assert_eq!(Rope::from_str("").slice(..).chars().collect::<String>(), "");
+
+        let text = (0..20_000).map(|i| format!("Line {i}: 爆発しませんように\n")).collect::<String>();
+        let mut rope = Rope::from_str(&text);
+        assert!(is_balanced(&rope));
+        check_metadata(&rope);
+        assert_eq!(rope.slice(..).chars().collect::<String>(), text);
+        assert_eq!(rope.len(), text.chars().count());
+        assert_eq!(rope.line_count(), 20_001);
+        assert_eq!(rope.lines().nth(12_345).map(|line| line.to_string()), Some(String::from("Line 12345: 爆発しませんように")));
+        assert_eq!(rope.index_pos(rope.line_start_index(777) + 3), (777, 3));
+
+        rope.insert(5, "inserted");
+        rope.remove(0, 5);
+        assert!(rope.slice(..).chars().collect::<String>().starts_with("inserted0: "));
+    }
+
+    #[test]
+    fn from_reader_test() {
+        let text = (0..5_000).map(|i| format!("{i} 何をしていますか？\n")).collect::<String>();
+        let rope = Rope::from_reader(TrickleReader(text.as_bytes())).unwrap();
+        assert!(is_balanced(&rope));
+        check_metadata(&rope);
+        assert_eq!(rope.slice(..).chars().collect::<String>(), text);
+        assert_eq!(rope.line_count(), 5_001);
+
+        let rope = Rope::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(rope.slice(..).chars().collect::<String>(), text);
+
+        assert!(Rope::from_reader(&[b'a', 0xFF, b'b'][..]).is_err());
+        assert!(Rope::from_reader("何".as_bytes()[..2].as_ref()).is_err());
+    }
+
     #[test]
     fn lines_iter_test() {
-        let rope = Rope::new().insert(0, "Hello!\nHow are you?\nI hope you are good!\n");
-        assert_eq!(rope.chars().collect::<String>(), "Hello!\nHow are you?\nI hope you are good!\n");
-        let mut rope_lines_iter = rope.lines();
+        let mut rope = Rope::new();
+        rope.insert(0, "Hello!\nHow are you?\nI hope you are good!\n");
+        assert_eq!(rope.slice(..).chars().collect::<String>(), "Hello!\nHow are you?\nI hope you are good!\n");
+        let mut rope_lines_iter = rope.lines().map(|line| line.to_string());
         assert_eq!(rope_lines_iter.next(), Some(String::from("Hello!")));
         assert_eq!(rope_lines_iter.next(), Some(String::from("How are you?")));
         assert_eq!(rope_lines_iter.next(), Some(String::from("I hope you are good!")));
@@ -1042,21 +1400,59 @@ mod tests {
         assert_eq!(rope_lines_iter.next(), None);
     }
 
+    #[test]
+    fn line_test() {
+        let rope = Rope::from_str(&"Hello!\n爆発しませんように\n\n".repeat(3_000));
+        assert_eq!(rope.line_len(0), Some(6));
+        assert_eq!(rope.line_len(4), Some(9));
+        assert_eq!(rope.line_len(8), Some(0));
+        assert_eq!(rope.line_len(9_000), Some(0));
+        assert_eq!(rope.line_len(9_001), None);
+        assert_eq!(rope.line(7_000).map(|line| line.to_string()), Some(String::from("爆発しませんように")));
+        assert_eq!(rope.line(6_000).map(|line| line.to_string()), Some(String::from("Hello!")));
+        assert!(rope.line(9_001).is_none());
+        assert_eq!(Rope::from_str("no newline").line_len(0), Some(10));
+        assert_eq!(Rope::from_str("no newline").line_len(1), None);
+        assert_eq!(Rope::new().line(0).map(|line| line.to_string()), Some(String::new()));
+        assert!(Rope::new().line(1).is_none());
+
+        // nth stops at the lines already taken from the back
+        let mut lines = rope.lines();
+        assert_eq!(lines.next_back().map(|line| line.to_string()), Some(String::new()));
+        assert_eq!(lines.next_back().map(|line| line.to_string()), Some(String::new()));
+        assert_eq!(lines.nth(8_998).map(|line| line.to_string()), Some(String::from("爆発しませんように")));
+        assert!(lines.next().is_none());
+
+        let mut lines = rope.lines();
+        lines.next_back();
+        lines.next_back();
+        assert!(lines.nth(8_999).is_none());
+        assert!(lines.next_back().is_none());
+
+        let rope = Rope::from_str("a\nb\n\nc");
+        let mut lines = rope.lines();
+        assert_eq!(lines.next_back().map(|line| line.to_string()), Some(String::from("c")));
+        assert_eq!(lines.nth(1).map(|line| line.to_string()), Some(String::from("b")));
+        assert_eq!(lines.next_back().map(|line| line.to_string()), Some(String::new()));
+        assert!(lines.next().is_none());
+    }
+
     #[test]
     fn lines_iter_skip_test() {
         let mut rope = Rope::new();
 
         for i in 0..30_000 {
             let rope_len = rope.len();
-            rope = rope.insert(rope_len, format!("What is this: {}\n", i).as_str());
+            rope.insert(rope_len, format!("What is this: {}\n", i).as_str());
         }
-        assert_eq!(rope.lines().skip(29_999).next(), Some(String::from("What is this: 29999")));
-        assert_eq!(rope.lines().skip(29_998).next(), Some(String::from("What is this: 29998")));
+        assert_eq!(rope.lines().skip(29_999).next().map(|line| line.to_string()), Some(String::from("What is this: 29999")));
+        assert_eq!(rope.lines().skip(29_998).next().map(|line| line.to_string()), Some(String::from("What is this: 29998")));
+        assert!(rope.lines().nth(30_001).is_none());
 
         let mut line_iter = rope.lines();
-        let large_skip = line_iter.by_ref().skip(23_000).next();
-        let skip_after_skip = line_iter.by_ref().skip(5000).take(3).collect::<Vec<_>>();
-        let step_by_after_skip = line_iter.by_ref().skip(600).take(11).step_by(5).collect::<Vec<_>>();
+        let large_skip = line_iter.by_ref().skip(23_000).next().map(|line| line.to_string());
+        let skip_after_skip = line_iter.by_ref().skip(5000).take(3).map(|line| line.to_string()).collect::<Vec<_>>();
+        let step_by_after_skip = line_iter.by_ref().skip(600).take(11).step_by(5).map(|line| line.to_string()).collect::<Vec<_>>();
         assert_eq!(large_skip, Some(String::from("What is this: 23000")));
         assert_eq!(skip_after_skip[2], String::from("What is this: 28003"));
         assert_eq!(step_by_after_skip[0], String::from("What is this: 28604"));
@@ -0,0 +1,262 @@
+use std::{collections::HashMap, error::Error, fs, path::PathBuf};
+
+use sdl3::pixels::Color;
+
+use crate::editor::{config, highlight::TokenKind};
+
+const THEME_EXTENSION: &str = "toml";
+pub const BUNDLED_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+/// Colors for every part of the editor
+///
+/// Theme files are `key = "#rrggbb"` lines in the `themes` folder of the config directory, with
+/// token colors under a `[syntax]` header, e.g. `themes/mine.toml` is picked with `:theme mine`.
+/// A file can start with `base = "light"` to only change a few colors of a bundled theme
+#[derive(Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub foreground: Color,
+    pub selection: Color,
+    pub cursor: Color,
+    /// Line numbers, the cursor's line is drawn in the foreground color
+    pub gutter: Color,
+    pub console: Color,
+    /// Cursor position and file format shown in the console
+    pub status: Color,
+    pub tab_bar: Color,
+    pub error: Color,
+    /// Gaps between split panes
+    pub border: Color,
+    syntax: HashMap<TokenKind, Color>,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            name: String::from("dark"),
+            background: Color::RGB(20, 20, 20),
+            foreground: Color::RGB(180, 225, 225),
+            selection: Color::RGB(80, 80, 80),
+            cursor: Color::RGB(180, 225, 225),
+            gutter: Color::RGB(90, 110, 110),
+            console: Color::RGB(20, 20, 60),
+            status: Color::RGB(180, 225, 225),
+            tab_bar: Color::RGB(35, 35, 45),
+            error: Color::RGB(240, 110, 110),
+            border: Color::RGB(0, 0, 0),
+            syntax: syntax_colors([
+                (200, 130, 220), (110, 200, 190), (120, 170, 240), (170, 210, 120), (230, 160, 100), (110, 120, 120),
+                (120, 170, 240), (230, 190, 110), (220, 140, 140), (110, 200, 190), (230, 160, 100),
+            ]),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            name: String::from("light"),
+            background: Color::RGB(250, 250, 245),
+            foreground: Color::RGB(40, 50, 60),
+            selection: Color::RGB(200, 210, 225),
+            cursor: Color::RGB(40, 50, 60),
+            gutter: Color::RGB(150, 160, 165),
+            console: Color::RGB(230, 232, 240),
+            status: Color::RGB(40, 50, 60),
+            tab_bar: Color::RGB(225, 225, 230),
+            error: Color::RGB(200, 40, 40),
+            border: Color::RGB(180, 180, 185),
+            syntax: syntax_colors([
+                (150, 40, 160), (0, 120, 120), (30, 90, 190), (60, 130, 40), (180, 90, 20), (140, 145, 150),
+                (30, 90, 190), (170, 100, 0), (170, 50, 60), (0, 120, 120), (180, 90, 20),
+            ]),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: String::from("high-contrast"),
+            background: Color::RGB(0, 0, 0),
+            foreground: Color::RGB(255, 255, 255),
+            selection: Color::RGB(0, 70, 160),
+            cursor: Color::RGB(255, 255, 0),
+            gutter: Color::RGB(200, 200, 200),
+            console: Color::RGB(0, 0, 0),
+            status: Color::RGB(255, 255, 255),
+            tab_bar: Color::RGB(0, 0, 0),
+            error: Color::RGB(255, 80, 80),
+            border: Color::RGB(255, 255, 255),
+            syntax: syntax_colors([
+                (255, 200, 0), (0, 255, 255), (120, 200, 255), (120, 255, 120), (255, 160, 60), (180, 180, 180),
+                (120, 200, 255), (255, 255, 0), (255, 120, 200), (0, 255, 255), (255, 160, 60),
+            ]),
+        }
+    }
+
+    pub fn bundled(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Loads the theme file called name from the config directory, falling back to the bundled themes
+    pub fn load(name: &str) -> Result<Self, Box<dyn Error>> {
+        if let Some(path) = themes_dir().map(|dir| dir.join(name).with_extension(THEME_EXTENSION)) && path.is_file() {
+            let source = fs::read_to_string(&path)?;
+            return Self::parse(name, &source).map_err(|err| format!("{}: {err}", path.display()).into());
+        }
+        Self::bundled(name).ok_or_else(|| format!("Unknown theme \"{name}\"").into())
+    }
+
+    /// Names of the bundled themes and of every theme file in the config directory
+    pub fn available() -> Vec<String> {
+        let mut names = BUNDLED_THEMES.map(String::from).to_vec();
+        let files = themes_dir().and_then(|dir| fs::read_dir(dir).ok());
+        for entry in files.into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == THEME_EXTENSION)
+                && let Some(name) = path.file_stem().and_then(|stem| stem.to_str())
+                && !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// Reads a theme file, every color it leaves out comes from its base theme
+    pub fn parse(name: &str, source: &str) -> Result<Self, String> {
+        let mut theme = Self::dark();
+        let mut in_syntax = false;
+        let mut colors_set = false;
+        for (i, line) in source.lines().enumerate() {
+            let line = config::strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_syntax = match line {
+                    "[syntax]" => true,
+                    _ => return Err(format!("line {}: unknown section {line}, expected [syntax]", i + 1)),
+                };
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(format!("line {}: expected key = value", i + 1));
+            };
+            let key = key.trim();
+            let value = config::unquote(value.trim());
+            if key == "base" && !in_syntax {
+                if colors_set {
+                    return Err(format!("line {}: base must come before any color", i + 1));
+                }
+                theme = Self::bundled(value).ok_or_else(|| format!("line {}: unknown base theme \"{value}\"", i + 1))?;
+                continue;
+            }
+            let color = parse_color(value).ok_or_else(|| format!("line {}: \"{value}\" is not a #rrggbb color", i + 1))?;
+            let slot = if in_syntax {
+                TokenKind::from_name(key).map(|kind| theme.syntax.entry(kind).or_insert(color))
+            } else {
+                theme.ui_color_mut(key)
+            };
+            let Some(slot) = slot else {
+                return Err(format!("line {}: unknown color \"{key}\"", i + 1));
+            };
+            *slot = color;
+            colors_set = true;
+        }
+        theme.name = name.to_string();
+        Ok(theme)
+    }
+
+    /// Color of a syntax token
+    pub fn syntax(&self, kind: TokenKind) -> Color {
+        self.syntax.get(&kind).copied().unwrap_or(self.foreground)
+    }
+
+    fn ui_color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        match key {
+            "background" => Some(&mut self.background),
+            "foreground" => Some(&mut self.foreground),
+            "selection" => Some(&mut self.selection),
+            "cursor" => Some(&mut self.cursor),
+            "gutter" => Some(&mut self.gutter),
+            "console" => Some(&mut self.console),
+            "status" => Some(&mut self.status),
+            "tab_bar" => Some(&mut self.tab_bar),
+            "error" => Some(&mut self.error),
+            "border" => Some(&mut self.border),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+fn themes_dir() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join("themes"))
+}
+
+/// Pairs the colors with `TokenKind::ALL`, in the same order
+fn syntax_colors(colors: [(u8, u8, u8); TokenKind::ALL.len()]) -> HashMap<TokenKind, Color> {
+    TokenKind::ALL.into_iter()
+        .zip(colors)
+        .map(|(kind, (r, g, b))| (kind, Color::RGB(r, g, b)))
+        .collect()
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let source = "\
+            # Mostly light, with a darker console\n\
+            base = \"light\"\n\
+            console = \"#203040\"  # navy\n\
+            \n\
+            [syntax]\n\
+            keyword = \"#FF0080\"\n";
+        let theme = Theme::parse("mine", source).unwrap();
+        assert_eq!(theme.name, "mine");
+        assert_eq!(theme.console, Color::RGB(0x20, 0x30, 0x40));
+        assert_eq!(theme.syntax(TokenKind::KEYWORD), Color::RGB(255, 0, 128));
+        assert_eq!(theme.background, Theme::light().background);
+        assert_eq!(theme.syntax(TokenKind::STRING), Theme::light().syntax(TokenKind::STRING));
+    }
+
+    #[test]
+    fn parse_error_test() {
+        assert_eq!(Theme::parse("bad", "background = \"#12345\"").unwrap_err(), "line 1: \"#12345\" is not a #rrggbb color");
+        assert_eq!(Theme::parse("bad", "\nfont = \"#123456\"").unwrap_err(), "line 2: unknown color \"font\"");
+        assert_eq!(Theme::parse("bad", "[colors]").unwrap_err(), "line 1: unknown section [colors], expected [syntax]");
+        assert_eq!(Theme::parse("bad", "cursor = \"#123456\"\nbase = \"dark\"").unwrap_err(), "line 2: base must come before any color");
+        assert!(Theme::parse("bad", "base = \"solarized\"").is_err());
+        assert!(Theme::parse("bad", "[syntax]\nbackground = \"#123456\"").is_err());
+    }
+
+    #[test]
+    fn bundled_test() {
+        for name in BUNDLED_THEMES {
+            let theme = Theme::bundled(name).unwrap();
+            assert_eq!(theme.name, name);
+            assert!(TokenKind::ALL.iter().all(|kind| theme.syntax.contains_key(kind)));
+        }
+        assert_eq!(Theme::bundled("solarized"), None);
+    }
+}
\ No newline at end of file
@@ -1,14 +1,33 @@
-use crate::{editor::textrope::TextRope, vector::Vector2D};
+use crate::{editor::{tabs, textrope::TextRope, wrap::{self, Row}}, vector::Vector2D};
 
+/// Part of a text box that changed since it was last drawn
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Damage {
+    NONE,
+    /// First and last text line to repaint, inclusive
+    LINES(usize, usize),
+    FULL,
+}
+
+#[derive(Clone)]
 pub struct WindowState {
     start_line: usize,
-    start_char: usize,
+    /// Column at the left edge when scrolled sideways
+    start_col: usize,
+    /// Row of start_line shown at the top when wrapping
+    start_row: usize,
+    /// Long lines continue on the next row instead of scrolling sideways
+    wrap: bool,
     line_count: usize,
     line_char_count: usize,
+    /// Columns between tab stops
+    tab_width: usize,
 
     text_padding: u32,
     line_padding: u32,
-    should_render: bool,
+    /// Characters taken by the line numbers left of the text
+    gutter_chars: usize,
+    damage: Damage,
 
     text_width: f32,
     text_height: f32,
@@ -18,7 +37,6 @@ pub struct WindowState {
 }
 
 impl WindowState {
-    const SCROLL_FACTOR: usize = 8;
     pub fn new(pos: Vector2D, window_width: u32, window_height: u32, text_width: u32, text_height: u32, text_padding: u32, line_padding: u32) -> Self {
         let mut new_window_state = Self {
             pos,
@@ -32,14 +50,31 @@ impl WindowState {
         new_window_state
     }
 
-    pub fn check_render(&mut self) -> bool {
-        let should_render = self.should_render;
-        self.should_render = false;
-        should_render
+    pub fn has_damage(&self) -> bool {
+        self.damage != Damage::NONE
+    }
+
+    /// Returns what changed since the last call, the caller is expected to repaint it
+    pub fn take_damage(&mut self) -> Damage {
+        std::mem::replace(&mut self.damage, Damage::NONE)
     }
 
     pub fn set_render_flag(&mut self) {
-        self.should_render = true;
+        self.damage = Damage::FULL;
+    }
+
+    /// Marks text lines first through last as changed
+    pub fn damage_lines(&mut self, first: usize, last: usize) {
+        self.damage = match self.damage {
+            Damage::NONE => Damage::LINES(first, last),
+            Damage::LINES(old_first, old_last) => Damage::LINES(old_first.min(first), old_last.max(last)),
+            Damage::FULL => Damage::FULL,
+        };
+    }
+
+    /// Marks every line from first to the bottom of the window as changed
+    pub fn damage_from(&mut self, first: usize) {
+        self.damage_lines(first, usize::MAX);
     }
 
     pub fn get_pos(&self) -> Vector2D {
@@ -51,6 +86,19 @@ impl WindowState {
         (self.text_width, self.text_height)
     }
 
+    /// Width of the line number gutter in pixels
+    pub fn gutter_width(&self) -> u32 {
+        (self.gutter_chars as f32 * self.text_width) as u32
+    }
+
+    /// Makes room for a gutter of chars characters, the text shifts right to follow it
+    pub fn set_gutter_chars(&mut self, chars: usize) {
+        if chars != self.gutter_chars {
+            self.gutter_chars = chars;
+            self.resize_text(self.text_width as u32, self.text_height as u32);
+        }
+    }
+
     /// returns (width, height)
     pub fn get_window_dim(&self) -> (u32, u32) {
         (self.window_width, self.window_height)
@@ -72,23 +120,98 @@ impl WindowState {
         self.text_width = text_width as f32;
 
         let window_height = self.window_height.saturating_sub(self.text_padding);
-        let window_width = self.window_width.saturating_sub(self.text_padding);
+        let window_width = self.window_width.saturating_sub(self.text_padding + self.gutter_width());
         let text_height = text_height + self.line_padding;
         self.line_count = (window_height / text_height) as usize;
         self.line_char_count = (window_width / text_width) as usize;
-        self.should_render = true;
+        // start_line may now wrap into fewer rows, the next draw clamps start_row to them
+        self.damage = Damage::FULL;
     }
 
-    pub fn in_screen_bound(&self, x: u32, y: u32) -> Option<Vector2D> {
-        let window_first_char = self.start_char as u32;
+    /// Keeps start_row within the rows of start_line, which change with the wrap width
+    pub fn clamp_start_row(&mut self, text_data: &TextRope) {
+        if !self.wrap {
+            return;
+        }
+        let Some(line_text) = text_data.line(self.start_line) else {
+            return;
+        };
+        let rows = wrap::row_starts(line_text.chars(), self.line_char_count, self.tab_width).len();
+        self.start_row = self.start_row.min(rows - 1);
+    }
+
+    pub fn is_wrapped(&self) -> bool {
+        self.wrap
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+        self.start_col = 0;
+        self.start_row = 0;
+        self.damage = Damage::FULL;
+    }
+
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        if tab_width != self.tab_width {
+            self.tab_width = tab_width;
+            self.start_row = 0;
+            self.damage = Damage::FULL;
+        }
+    }
+
+    /// Rows shown in the window from top to bottom, fewer than lines() once the text runs out
+    pub fn rows(&self, text_data: &TextRope) -> Vec<Row> {
+        if !self.wrap {
+            return text_data.lines()
+                .enumerate()
+                .skip(self.start_line)
+                .take(self.line_count)
+                .map(|(line, line_text)| {
+                    let start = tabs::char_at(line_text.chars(), self.start_col, self.tab_width);
+                    let end = tabs::char_at(line_text.chars(), self.start_col + self.line_char_count, self.tab_width);
+                    Row { line, start, len: end - start, col: self.start_col, last: true }
+                })
+                .collect();
+        }
+        text_data.lines()
+            .enumerate()
+            .skip(self.start_line)
+            .flat_map(|(line, line_text)| wrap::rows(line, line_text.chars(), self.line_char_count, self.tab_width))
+            .skip(self.start_row)
+            .take(self.line_count)
+            .collect()
+    }
+
+    /// Position of the character at (x, y) on the screen as (column, row), None if it is scrolled out of view
+    pub fn screen_pos(&self, x: u32, y: u32, text_data: &TextRope) -> Option<Vector2D> {
+        let line_text = text_data.line(y as usize)?;
+        let col = tabs::col_of(line_text.chars(), x as usize, self.tab_width);
+        if !self.wrap {
+            return self.in_screen_bound(col as u32, y);
+        }
+        let (x, y) = (x as usize, y as usize);
+        self.rows(text_data)
+            .iter()
+            .enumerate()
+            .find(|(_, row)| row.line == y && x >= row.start && (x < row.start + row.len || row.last))
+            .map(|(screen_row, row)| Vector2D::new((col - row.col) as u32, screen_row as u32))
+    }
+
+    /// Screen position of column col of line y, None if it is scrolled out of view
+    pub fn in_screen_bound(&self, col: u32, y: u32) -> Option<Vector2D> {
+        let window_first_col = self.start_col as u32;
         let window_first_line = self.start_line as u32;
-        let window_char_len = self.line_char_count as u32;
+        let window_col_len = self.line_char_count as u32;
         let window_line_len = self.line_count as u32;
 
-        let shifted_x = if x < window_first_char || x >= window_char_len + window_first_char {
+        let shifted_x = if col < window_first_col || col >= window_col_len + window_first_col {
             return None;
         } else {
-            x - window_first_char
+            col - window_first_col
         };
         let shifted_y = if y < window_first_line || y >= window_line_len + window_first_line {
             return None;
@@ -109,8 +232,8 @@ impl WindowState {
         self.start_line
     }
 
-    pub fn get_first_char(&self) -> usize {
-        self.start_char
+    pub fn get_first_col(&self) -> usize {
+        self.start_col
     }
 
     pub fn lines(&self) -> usize {
@@ -121,28 +244,54 @@ impl WindowState {
         self.line_char_count
     }
 
+    /// Takes effect on the next resize_text
+    pub fn set_padding(&mut self, text_padding: u32, line_padding: u32) {
+        self.text_padding = text_padding;
+        self.line_padding = line_padding;
+    }
+
     /// Returns (text_padding, line_padding)
     pub fn get_padding(&self) -> (u32, u32) {
         (self.text_padding, self.line_padding)
     }
 
-    pub fn scroll_up(&mut self, distance: usize) {
-        self.start_line = self.start_line.saturating_sub(distance * Self::SCROLL_FACTOR);
-        self.should_render = true;
+    pub fn scroll_up(&mut self, distance: usize, text_data: &TextRope) {
+        if self.wrap {
+            (self.start_line, self.start_row) = self.rows_up((self.start_line, self.start_row), distance, text_data);
+        } else {
+            self.start_line = self.start_line.saturating_sub(distance);
+        }
+        self.damage = Damage::FULL;
     }
 
-    pub fn scroll_down(&mut self, distance: usize, max_line_count: usize) {
-        self.start_line = (self.start_line + distance * Self::SCROLL_FACTOR).min(max_line_count.saturating_sub(self.line_count));
-        self.should_render = true;
+    pub fn scroll_down(&mut self, distance: usize, text_data: &TextRope) {
+        if self.wrap {
+            let last_line = text_data.line_count() - 1;
+            let last_row = text_data.line(last_line)
+                .map_or(0, |line_text| wrap::row_starts(line_text.chars(), self.line_char_count, self.tab_width).len() - 1);
+            let max_start = self.rows_up((last_line, last_row), self.line_count.saturating_sub(1), text_data);
+            let new_start = self.rows_down((self.start_line, self.start_row), distance, text_data);
+            (self.start_line, self.start_row) = new_start.min(max_start).max((self.start_line, self.start_row));
+        } else {
+            self.start_line = (self.start_line + distance).min(text_data.line_count().saturating_sub(self.line_count));
+        }
+        self.damage = Damage::FULL;
     }
 
     pub fn adjust_focus(&mut self, x: usize, y: usize, text_data: &TextRope) {
-        let new_char_start = if x < self.start_char {
-            x
-        } else if x + 1 >= self.start_char + self.line_char_count {
-            x + 1 - self.line_char_count
+        if self.wrap {
+            return self.adjust_wrapped_focus(x, y, text_data);
+        }
+        let Some(line_text) = text_data.line(y) else {
+            return;
+        };
+        let col = tabs::col_of(line_text.chars(), x, self.tab_width);
+        let new_col_start = if col < self.start_col {
+            col
+        } else if col + 1 >= self.start_col + self.line_char_count {
+            col + 1 - self.line_char_count
         } else {
-            self.start_char
+            self.start_col
         };
 
         let new_line_start = if y < self.start_line {
@@ -153,9 +302,68 @@ impl WindowState {
             self.start_line
         };
 
-        self.start_char = new_char_start.min(text_data.lines().nth(y).unwrap().chars().count().saturating_sub(self.line_char_count / 4));
-        self.start_line = new_line_start.min(text_data.line_count().saturating_sub(self.line_count));
-        self.should_render = true;
+        let new_col_start = new_col_start.min(tabs::width(line_text.chars(), self.tab_width).saturating_sub(self.line_char_count / 4));
+        let new_line_start = new_line_start.min(text_data.line_count().saturating_sub(self.line_count));
+        // Only scrolling moves every line, the text box damages the lines an edit touched itself
+        if new_col_start != self.start_col || new_line_start != self.start_line {
+            self.start_col = new_col_start;
+            self.start_line = new_line_start;
+            self.damage = Damage::FULL;
+        }
+    }
+
+    /// Scrolls by rows so the cursor's row is on screen
+    fn adjust_wrapped_focus(&mut self, x: usize, y: usize, text_data: &TextRope) {
+        let Some(line_text) = text_data.line(y) else {
+            return;
+        };
+        let cursor = (y, wrap::row_of(&wrap::row_starts(line_text.chars(), self.line_char_count, self.tab_width), x));
+        let start = (self.start_line, self.start_row);
+        let new_start = if cursor < start {
+            cursor
+        } else if self.rows_down(start, self.line_count.saturating_sub(1), text_data) < cursor {
+            self.rows_up(cursor, self.line_count.saturating_sub(1), text_data)
+        } else {
+            start
+        };
+        if new_start != start {
+            (self.start_line, self.start_row) = new_start;
+            self.damage = Damage::FULL;
+        }
+    }
+
+    /// Returns the (line, row) count rows above pos, or the first row of the text
+    fn rows_up(&self, (line, row): (usize, usize), count: usize, text_data: &TextRope) -> (usize, usize) {
+        if row >= count {
+            return (line, row - count);
+        }
+        // Every line takes at least one row, so count lines back is always far enough
+        let mut remaining = count - row;
+        let first_line = line.saturating_sub(remaining);
+        let lines = text_data.lines().skip(first_line).take(line - first_line).collect::<Vec<_>>();
+        for (i, line_text) in lines.iter().enumerate().rev() {
+            let rows = wrap::row_starts(line_text.chars(), self.line_char_count, self.tab_width).len();
+            if rows >= remaining {
+                return (first_line + i, rows - remaining);
+            }
+            remaining -= rows;
+        }
+        (0, 0)
+    }
+
+    /// Returns the (line, row) count rows below pos, or the last row of the text
+    fn rows_down(&self, (line, row): (usize, usize), count: usize, text_data: &TextRope) -> (usize, usize) {
+        let mut remaining = row + count;
+        let mut last = (line, row);
+        for (i, line_text) in text_data.lines().enumerate().skip(line) {
+            let rows = wrap::row_starts(line_text.chars(), self.line_char_count, self.tab_width).len();
+            if remaining < rows {
+                return (i, remaining);
+            }
+            remaining -= rows;
+            last = (i, rows - 1);
+        }
+        last
     }
 }
 
@@ -163,12 +371,16 @@ impl Default for WindowState {
     fn default() -> Self {
         Self {
             start_line: 0,
-            start_char: 0,
+            start_col: 0,
+            start_row: 0,
+            wrap: false,
             line_count: 0,
             line_char_count: 0,
+            tab_width: 4,
             text_padding: 0,
             line_padding: 0,
-            should_render: false,
+            gutter_chars: 0,
+            damage: Damage::NONE,
             text_height: 0.0,
             text_width: 0.0,
             pos: Vector2D::default(),
@@ -0,0 +1,110 @@
+use crate::editor::tabs;
+
+/// Part of a line drawn on one screen row
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Row {
+    pub line: usize,
+    /// Char offset of the first character on the row
+    pub start: usize,
+    pub len: usize,
+    /// Column of the line shown at the left edge of the row
+    pub col: usize,
+    /// True if the row holds the end of the line
+    pub last: bool,
+}
+
+/// Char offsets where each row of the line made of chars starts when wrapped to width columns
+///
+/// Rows break after the last space that fits, words longer than a row are cut at the edge
+pub fn row_starts(chars: impl IntoIterator<Item = char>, width: usize, tab_width: usize) -> Vec<usize> {
+    let chars = chars.into_iter().collect::<Vec<char>>();
+    starts(&chars, &columns(&chars, tab_width), width)
+}
+
+/// Splits the line made of chars into the rows it takes up when wrapped to width columns
+pub fn rows(line_num: usize, chars: impl IntoIterator<Item = char>, width: usize, tab_width: usize) -> Vec<Row> {
+    let chars = chars.into_iter().collect::<Vec<char>>();
+    let cols = columns(&chars, tab_width);
+    let starts = starts(&chars, &cols, width);
+    starts.iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(chars.len());
+            Row { line: line_num, start, len: end - start, col: cols[start], last: i + 1 == starts.len() }
+        })
+        .collect()
+}
+
+/// Index of the row holding the character at x, the end of a row belongs to the row after it
+pub fn row_of(starts: &[usize], x: usize) -> usize {
+    starts.iter().rposition(|&start| start <= x).unwrap_or(0)
+}
+
+/// Row starts of chars, whose columns are cols
+fn starts(chars: &[char], cols: &[usize], width: usize) -> Vec<usize> {
+    let width = width.max(1);
+    let mut starts = vec![0];
+    let mut start = 0;
+    while cols[chars.len()] - cols[start] > width {
+        // A tab wider than the row still gets a row of its own
+        let fit = (start + 2..=chars.len())
+            .take_while(|&end| cols[end] - cols[start] <= width)
+            .last()
+            .unwrap_or(start + 1);
+        let next_start = chars[start..fit]
+            .iter()
+            .rposition(|&c| c == ' ' || c == '\t')
+            .map_or(fit, |space| start + space + 1);
+        starts.push(next_start);
+        start = next_start;
+    }
+    starts
+}
+
+/// Column of every char of chars, followed by the width of them all
+fn columns(chars: &[char], tab_width: usize) -> Vec<usize> {
+    let mut cols = Vec::with_capacity(chars.len() + 1);
+    let mut col = 0;
+    for &c in chars {
+        cols.push(col);
+        col += tabs::char_width(c, col, tab_width);
+    }
+    cols.push(col);
+    cols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_starts_test() {
+        assert_eq!(row_starts("".chars(), 10, 4), vec![0]);
+        assert_eq!(row_starts("exactly10!".chars(), 10, 4), vec![0]);
+        assert_eq!(row_starts("the quick brown fox jumps".chars(), 10, 4), vec![0, 10, 20]);
+        assert_eq!(row_starts("abcdefghijklmnopqrstuvwxy".chars(), 10, 4), vec![0, 10, 20]);
+        assert_eq!(row_starts("ab abcdefghijklmnop".chars(), 5, 4), vec![0, 3, 8, 13, 18]);
+    }
+
+    #[test]
+    fn rows_test() {
+        let rows = rows(4, "the quick brown fox jumps".chars(), 10, 4);
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1], Row { line: 4, start: 10, len: 10, col: 10, last: false });
+        assert_eq!(rows[2], Row { line: 4, start: 20, len: 5, col: 20, last: true });
+
+        let starts = row_starts("the quick brown fox jumps".chars(), 10, 4);
+        assert_eq!(row_of(&starts, 9), 0);
+        assert_eq!(row_of(&starts, 10), 1);
+        assert_eq!(row_of(&starts, 25), 2);
+    }
+
+    #[test]
+    fn tab_rows_test() {
+        assert_eq!(row_starts("\t\tabcd".chars(), 10, 4), vec![0, 2]);
+        assert_eq!(row_starts("ab\tcdefgh".chars(), 8, 4), vec![0, 3]);
+        assert_eq!(row_starts("\tab".chars(), 2, 4), vec![0, 1]);
+        let rows = rows(0, "a\tbcdefghij".chars(), 8, 4);
+        assert_eq!(rows[1], Row { line: 0, start: 2, len: 8, col: 4, last: false });
+    }
+}
\ No newline at end of file
@@ -4,11 +4,10 @@ extern crate sdl3;
 mod editor;
 pub mod vector;
 use std::process;
-use std::time::{Duration, Instant};
 
 use sdl3::sys::mouse::{SDL_CreateSystemCursor, SDL_SetCursor, SDL_SystemCursor};
 use sdl3::ttf;
-use crate::editor::Editor;
+use crate::editor::{Config, Editor};
 
 pub fn main() {
     //unsafe { std::env::set_var("RUST_BACKTRACE", "1"); }
@@ -18,14 +17,10 @@ pub fn main() {
 }
 
 pub fn run(starting_file: Option<String>) {
-    const FRAME_RATE: u64 = 100;
-    const FRAME_DELTA: Duration = Duration::from_nanos(1_000_000_000 / FRAME_RATE);
-    const INIT_WINDOW_WIDTH: u32 = 800;
-    const INIT_WINDOW_HEIGHT: u32 = 600;
-    const MIN_WINDOW_WIDTH: u32 = 400;
-    const MIN_WINDOW_HEIGHT: u32 = 400;
     const WINDOW_NAME: &str = "Text Editor";
 
+    let (config, config_errors) = Config::load();
+
     let sdl_context = sdl3::init().unwrap_or_else(|err| {
         eprintln!("Failed to initialize SDL3: {err}");
         process::exit(1);
@@ -41,7 +36,7 @@ pub fn run(starting_file: Option<String>) {
     });
 
     let mut window = video_subsytem
-        .window(WINDOW_NAME, INIT_WINDOW_WIDTH, INIT_WINDOW_HEIGHT)
+        .window(WINDOW_NAME, config.window_width, config.window_height)
         .position_centered()
         .resizable()
         .opengl()
@@ -50,39 +45,45 @@ pub fn run(starting_file: Option<String>) {
             eprintln!("Failed to create window \"{WINDOW_NAME}\": {}", err.to_string());
             process::exit(1);
         });
-    window.set_minimum_size(MIN_WINDOW_WIDTH, MIN_WINDOW_HEIGHT).unwrap_or_else(|err| {
+    window.set_minimum_size(Config::MIN_WINDOW_WIDTH, Config::MIN_WINDOW_HEIGHT).unwrap_or_else(|err| {
         eprintln!("Failed to set minimum window size: {}", err.to_string());
         process::exit(1);
     });
 
+    let event_subsystem = sdl_context.event().unwrap_or_else(|err| {
+        eprintln!("Failed to open event subsystem: {err}");
+        process::exit(1);
+    });
     let mut events = sdl_context.event_pump().unwrap_or_else(|err| {
         eprintln!("Failed to create event pump: {}", err.to_string());
         process::exit(1);
     });
 
-    let mut state = Editor::build(&video_subsytem, &ttf_context, &mut events, window).unwrap_or_else(|err| {
+    let canvas = window.into_canvas();
+    let texture_creator = canvas.texture_creator();
+    let mut state = Editor::build(&video_subsytem, &ttf_context, &mut events, &event_subsystem, canvas, &texture_creator, config).unwrap_or_else(|err| {
         eprintln!("Failed to create editor state: {}", err.to_string());
         process::exit(1);
     });
 
+    state.report_errors(config_errors);
     if let Some(starting_file) = starting_file {
         state.open_file(&starting_file);
     }
 
     _ = unsafe { SDL_SetCursor(SDL_CreateSystemCursor(SDL_SystemCursor::TEXT)) };
 
+    // Render before waiting, so whatever the last update changed is on screen while idle
     while !state.should_quit() {
-        let frame_start = Instant::now();
-        state.handle_input().unwrap_or_else(|err| {
-            eprintln!("Failed to handle event: {}", err.to_string());
-            process::exit(1);
-        });
         state.render().unwrap_or_else(|err| {
             eprintln!("Failed to render: {}", err.to_string());
             process::exit(1);
         });
+        state.handle_input().unwrap_or_else(|err| {
+            eprintln!("Failed to handle event: {}", err.to_string());
+            process::exit(1);
+        });
         state.update();
-        std::thread::sleep(FRAME_DELTA.saturating_sub(frame_start.elapsed()));
     }
 
     state.close()